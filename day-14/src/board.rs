@@ -0,0 +1,175 @@
+// The Kanban board: one column per status, side by side, sized to the
+// terminal. Titles wrap inside their column, and the In Progress column shows
+// the work-in-progress (WIP) limit next to its count.
+
+use std::cmp::Reverse;
+use std::env;
+use std::fs::File;
+use std::process::{Command, Stdio};
+
+use crate::{Filterable, Status, TaskManager};
+
+const DEFAULT_WIDTH: usize = 80;
+const MIN_COLUMN_WIDTH: usize = 12;
+const SEPARATOR: &str = " | ";
+const DEFAULT_WIP_LIMIT: usize = 3;
+
+// The width of the terminal as `stty size` reports it for /dev/tty, else
+// COLUMNS, else 80 characters. Shells set COLUMNS for themselves but seldom
+// export it, so it is only a fallback, for example when there is no terminal.
+pub(crate) fn terminal_width() -> usize {
+    choose_width(tty_width(), env::var("COLUMNS").ok().as_deref())
+}
+
+fn choose_width(tty: Option<usize>, columns: Option<&str>) -> usize {
+    tty.or_else(|| columns.and_then(|c| c.trim().parse().ok()))
+        .filter(|&width| width > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+// The columns from `stty size` ("rows columns"), or None where there is no
+// terminal or no stty.
+fn tty_width() -> Option<usize> {
+    let tty = File::open("/dev/tty").ok()?;
+    let output = Command::new("stty")
+        .arg("size")
+        .stdin(tty)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let size = String::from_utf8(output.stdout).ok()?;
+    size.split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+        .filter(|&width| width > 0)
+}
+
+// How many tasks may be in progress at once, from TASKS_WIP_LIMIT; 0 means
+// no limit.
+pub(crate) fn wip_limit() -> usize {
+    env::var("TASKS_WIP_LIMIT")
+        .ok()
+        .and_then(|limit| limit.trim().parse().ok())
+        .unwrap_or(DEFAULT_WIP_LIMIT)
+}
+
+// The warning to show when more tasks are in progress than the limit allows.
+pub(crate) fn wip_warning(in_progress: usize, limit: usize) -> Option<String> {
+    (limit > 0 && in_progress > limit).then(|| {
+        format!(
+            "Warning: {} tasks are in progress, over the WIP limit of {}.",
+            in_progress, limit
+        )
+    })
+}
+
+impl TaskManager {
+    pub(crate) fn board(&self, width: usize, limit: usize) -> String {
+        let column_width = (width.saturating_sub(SEPARATOR.len() * 2) / 3).max(MIN_COLUMN_WIDTH);
+        let statuses = [Status::Pending, Status::InProgress, Status::Completed];
+
+        let mut headers = Vec::new();
+        let mut columns = Vec::new();
+        for status in statuses {
+            let mut tasks = self.filter_by(status);
+            // Most urgent first, then oldest first.
+            tasks.sort_by_key(|t| (Reverse(t.priority), t.id));
+            headers.push(if status == Status::InProgress && limit > 0 {
+                format!("{} ({}/{})", status, tasks.len(), limit)
+            } else {
+                format!("{} ({})", status, tasks.len())
+            });
+
+            let mut lines = Vec::new();
+            for task in tasks {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.extend(wrap(&format!("#{} {}", task.id, task.title), column_width));
+                lines.extend(wrap(
+                    &format!("[{}] [{}]", task.priority, task.category),
+                    column_width,
+                ));
+            }
+            columns.push(lines);
+        }
+
+        let headers: Vec<Vec<String>> = headers.iter().map(|h| wrap(h, column_width)).collect();
+        let mut out = String::new();
+        push_rows(&mut out, &headers, column_width);
+        let rule = "-".repeat(column_width);
+        out.push_str(&[rule.as_str(); 3].join("-+-"));
+        out.push('\n');
+        push_rows(&mut out, &columns, column_width);
+        if let Some(warning) = wip_warning(self.filter_by(Status::InProgress).len(), limit) {
+            out.push_str(&format!("\n{}\n", warning));
+        }
+        out
+    }
+}
+
+// Writes the columns of lines next to each other, padding the shorter ones.
+fn push_rows(out: &mut String, columns: &[Vec<String>], width: usize) {
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+    for i in 0..height {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|lines| format!("{:<width$}", lines.get(i).map_or("", String::as_str)))
+            .collect();
+        out.push_str(cells.join(SEPARATOR).trim_end());
+        out.push('\n');
+    }
+}
+
+// Breaks `text` into lines of at most `width` characters, between words
+// where possible; a word longer than a line is split.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word;
+        loop {
+            let line_len = line.chars().count();
+            let word_len = word.chars().count();
+            if line.is_empty() && word_len > width {
+                let split = word
+                    .char_indices()
+                    .nth(width)
+                    .map_or(word.len(), |(i, _)| i);
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            } else if line.is_empty() {
+                line.push_str(word);
+                break;
+            } else if line_len + 1 + word_len <= width {
+                line.push(' ');
+                line.push_str(word);
+                break;
+            } else {
+                lines.push(std::mem::take(&mut line));
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_prefers_the_terminal_then_columns() {
+        assert_eq!(choose_width(Some(120), Some("100")), 120);
+        assert_eq!(choose_width(None, Some(" 100 ")), 100);
+        assert_eq!(choose_width(None, Some("wide")), DEFAULT_WIDTH);
+        assert_eq!(choose_width(None, Some("0")), DEFAULT_WIDTH);
+        assert_eq!(choose_width(None, None), DEFAULT_WIDTH);
+    }
+}
@@ -0,0 +1,395 @@
+// Categories are defined by the user. Tasks refer to their category by name,
+// so renaming or merging a category updates its tasks and the saved views
+// that mention it. A new data file starts with Work, Personal, Learning,
+// Health and Finance, and there is always at least one category. Names
+// compare without regard to case.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{TaskError, TaskManager};
+
+const DEFAULT_CATEGORIES: [&str; 5] = ["Work", "Personal", "Learning", "Health", "Finance"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Category {
+    pub(crate) name: String,
+    pub(crate) color: Option<Color>,
+    pub(crate) description: Option<String>,
+}
+
+impl Category {
+    pub(crate) fn new(name: &str) -> Self {
+        Category {
+            name: name.to_string(),
+            color: None,
+            description: None,
+        }
+    }
+
+    pub(crate) fn is(&self, name: &str) -> bool {
+        self.name.to_lowercase() == name.trim().to_lowercase()
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub(crate) fn default_categories() -> Vec<Category> {
+    DEFAULT_CATEGORIES
+        .iter()
+        .map(|name| Category::new(name))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl Color {
+    pub(crate) const ALL: [Color; 6] = [
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+        }
+    }
+
+    // `text` between the terminal escape codes that color it.
+    pub(crate) fn paint(self, text: &str) -> String {
+        let code = match self {
+            Color::Red => 31,
+            Color::Green => 32,
+            Color::Yellow => 33,
+            Color::Blue => 34,
+            Color::Magenta => 35,
+            Color::Cyan => 36,
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+}
+
+impl FromStr for Color {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::ALL
+            .into_iter()
+            .find(|color| color.name() == s.trim().to_lowercase())
+            .ok_or_else(|| TaskError::InvalidInput(format!("unknown color '{}'", s.trim())))
+    }
+}
+
+// Names are trimmed, with runs of spaces collapsed. They may not be empty or
+// a number, which picks a category by its place in the menu.
+fn validate_name(name: &str) -> Result<String, TaskError> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err(TaskError::InvalidInput(
+            "category name cannot be empty".to_string(),
+        ));
+    }
+    if name.parse::<usize>().is_ok() {
+        return Err(TaskError::InvalidInput(format!(
+            "category name '{}' cannot be a number",
+            name
+        )));
+    }
+    Ok(name)
+}
+
+impl TaskManager {
+    // The category called `input`, or with that number in the menu.
+    pub(crate) fn find_category(&self, input: &str) -> Result<&Category, TaskError> {
+        let input = input.trim();
+        let found = match input.parse::<usize>() {
+            Ok(n) => n.checked_sub(1).and_then(|i| self.categories.get(i)),
+            Err(_) => self.categories.iter().find(|c| c.is(input)),
+        };
+        found.ok_or_else(|| TaskError::InvalidInput(format!("unknown category '{}'", input)))
+    }
+
+    // How many tasks are in the category `name`.
+    pub(crate) fn category_count(&self, name: &str) -> usize {
+        self.tasks.values().filter(|t| t.category == name).count()
+    }
+
+    // Adds a category and returns its name as stored.
+    pub(crate) fn add_category(&mut self, name: &str) -> Result<String, TaskError> {
+        let name = validate_name(name)?;
+        if self.categories.iter().any(|c| c.is(&name)) {
+            return Err(TaskError::InvalidInput(format!(
+                "category '{}' already exists",
+                name
+            )));
+        }
+        self.categories.push(Category::new(&name));
+        Ok(name)
+    }
+
+    pub(crate) fn rename_category(
+        &mut self,
+        category: &str,
+        new_name: &str,
+    ) -> Result<(), TaskError> {
+        let old = self.find_category(category)?.name.clone();
+        let new_name = validate_name(new_name)?;
+        let renamed = new_name.to_lowercase() != old.to_lowercase();
+        if renamed && self.categories.iter().any(|c| c.is(&new_name)) {
+            return Err(TaskError::InvalidInput(format!(
+                "category '{}' already exists",
+                new_name
+            )));
+        }
+        self.move_category(&old, &new_name);
+        if let Some(category) = self.categories.iter_mut().find(|c| c.name == old) {
+            category.name = new_name;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_category_color(
+        &mut self,
+        category: &str,
+        color: Option<Color>,
+    ) -> Result<(), TaskError> {
+        let name = self.find_category(category)?.name.clone();
+        if let Some(category) = self.categories.iter_mut().find(|c| c.name == name) {
+            category.color = color;
+        }
+        Ok(())
+    }
+
+    // None, or a blank description, removes the description.
+    pub(crate) fn set_category_description(
+        &mut self,
+        category: &str,
+        description: Option<&str>,
+    ) -> Result<(), TaskError> {
+        let name = self.find_category(category)?.name.clone();
+        let description = description
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(String::from);
+        if let Some(category) = self.categories.iter_mut().find(|c| c.name == name) {
+            category.description = description;
+        }
+        Ok(())
+    }
+
+    // Deletes a category. Its tasks, if it has any, move to `move_to`;
+    // returns how many moved.
+    pub(crate) fn delete_category(
+        &mut self,
+        category: &str,
+        move_to: Option<&str>,
+    ) -> Result<usize, TaskError> {
+        let name = self.find_category(category)?.name.clone();
+        if self.categories.len() == 1 {
+            return Err(TaskError::InvalidInput(
+                "the last category cannot be deleted".to_string(),
+            ));
+        }
+        let count = self.category_count(&name);
+        let target = match move_to {
+            Some(target) => Some(self.find_category(target)?.name.clone()),
+            None if count > 0 => {
+                return Err(TaskError::InvalidInput(format!(
+                    "{} task(s) are in '{}'; choose a category to move them to",
+                    count, name
+                )));
+            }
+            None => None,
+        };
+        if let Some(target) = target {
+            if target == name {
+                return Err(TaskError::InvalidInput(format!(
+                    "cannot move the tasks of '{}' into itself",
+                    name
+                )));
+            }
+            self.move_category(&name, &target);
+        }
+        self.categories.retain(|c| c.name != name);
+        Ok(count)
+    }
+
+    // Moves every task of `from` into `into` and deletes `from`; returns how
+    // many tasks moved.
+    pub(crate) fn merge_categories(&mut self, from: &str, into: &str) -> Result<usize, TaskError> {
+        let into = self.find_category(into)?.name.clone();
+        self.delete_category(from, Some(&into))
+    }
+
+    // Points the tasks and saved views that use `from` at `to`.
+    fn move_category(&mut self, from: &str, to: &str) {
+        for task in self.tasks.values_mut() {
+            if task.category == from {
+                task.category = to.to_string();
+            }
+        }
+        for filter in self.views.values_mut() {
+            filter.rename_category(from, to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+    use crate::filter::Filter;
+
+    fn manager() -> TaskManager {
+        let mut manager = TaskManager::new();
+        manager.add_task("Report".to_string(), Priority::High, "Work".to_string());
+        manager.add_task("Gym".to_string(), Priority::Low, "Health".to_string());
+        manager.add_task("Slides".to_string(), Priority::Medium, "Work".to_string());
+        manager.views.insert(
+            "work".to_string(),
+            "category:work or text:gym".parse().unwrap(),
+        );
+        manager
+    }
+
+    fn names(manager: &TaskManager) -> Vec<&str> {
+        manager.categories.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn invalid<T: fmt::Debug>(result: Result<T, TaskError>, expected: &str) {
+        match result {
+            Err(TaskError::InvalidInput(message)) => {
+                assert!(message.contains(expected), "{:?}", message)
+            }
+            other => panic!(
+                "expected an error mentioning {:?}, got {:?}",
+                expected, other
+            ),
+        }
+    }
+
+    #[test]
+    fn finds_categories_by_number_or_name() {
+        let manager = manager();
+        assert_eq!(names(&manager), DEFAULT_CATEGORIES);
+        assert_eq!(manager.find_category("2").unwrap().name, "Personal");
+        assert_eq!(manager.find_category(" heALTH ").unwrap().name, "Health");
+        invalid(manager.find_category("0"), "'0'");
+        invalid(manager.find_category("6"), "'6'");
+        invalid(manager.find_category("Garden"), "Garden");
+        assert_eq!(manager.category_count("Work"), 2);
+    }
+
+    #[test]
+    fn added_names_are_tidied_and_unique() {
+        let mut manager = manager();
+        assert_eq!(
+            manager.add_category("  Side   Project ").unwrap(),
+            "Side Project"
+        );
+        assert_eq!(manager.find_category("6").unwrap().name, "Side Project");
+        invalid(manager.add_category("side project"), "already exists");
+        invalid(manager.add_category("   "), "empty");
+        invalid(manager.add_category("42"), "number");
+        assert_eq!(manager.categories.len(), 6);
+    }
+
+    #[test]
+    fn renaming_updates_tasks_and_views() {
+        let mut manager = manager();
+        manager.rename_category("work", "Job").unwrap();
+        assert_eq!(names(&manager)[0], "Job");
+        assert_eq!(manager.category_count("Job"), 2);
+        assert_eq!(manager.category_count("Work"), 0);
+        assert_eq!(
+            manager.views["work"],
+            "category:Job or text:gym".parse::<Filter>().unwrap()
+        );
+
+        // Changing only the case is allowed; taking another name is not.
+        manager.rename_category("job", "JOB").unwrap();
+        assert_eq!(manager.tasks[&1].category, "JOB");
+        invalid(manager.rename_category("JOB", "health"), "already exists");
+        invalid(manager.rename_category("JOB", "7"), "number");
+        invalid(manager.rename_category("Garden", "Yard"), "Garden");
+        assert_eq!(manager.tasks[&1].category, "JOB");
+    }
+
+    #[test]
+    fn deleting_moves_tasks_only_when_told_where() {
+        let mut manager = manager();
+        assert_eq!(manager.delete_category("personal", None).unwrap(), 0);
+        invalid(manager.delete_category("work", None), "2 task(s)");
+        invalid(manager.delete_category("work", Some("WORK")), "into itself");
+        invalid(manager.delete_category("work", Some("Garden")), "Garden");
+        assert_eq!(manager.category_count("Work"), 2);
+
+        assert_eq!(manager.delete_category("work", Some("health")).unwrap(), 2);
+        assert_eq!(names(&manager), ["Learning", "Health", "Finance"]);
+        assert!(manager.tasks.values().all(|t| t.category == "Health"));
+        assert_eq!(
+            manager.views["work"],
+            "category:Health or text:gym".parse::<Filter>().unwrap()
+        );
+    }
+
+    #[test]
+    fn the_last_category_stays() {
+        let mut manager = manager();
+        assert_eq!(manager.merge_categories("work", "finance").unwrap(), 2);
+        assert_eq!(manager.merge_categories("health", "finance").unwrap(), 1);
+        manager.delete_category("learning", None).unwrap();
+        manager.delete_category("personal", None).unwrap();
+        assert_eq!(names(&manager), ["Finance"]);
+        invalid(manager.delete_category("finance", None), "last category");
+        invalid(
+            manager.merge_categories("finance", "finance"),
+            "last category",
+        );
+    }
+
+    #[test]
+    fn colors_and_descriptions() {
+        let mut manager = manager();
+        assert_eq!(" Magenta ".parse::<Color>().unwrap(), Color::Magenta);
+        invalid("pink".parse::<Color>(), "pink");
+        assert_eq!(Color::Red.paint("x"), "\x1b[31mx\x1b[0m");
+
+        manager
+            .set_category_color("work", Some(Color::Blue))
+            .unwrap();
+        manager
+            .set_category_description("1", Some("  Paid work "))
+            .unwrap();
+        let work = &manager.categories[0];
+        assert_eq!(work.color, Some(Color::Blue));
+        assert_eq!(work.description.as_deref(), Some("Paid work"));
+
+        manager
+            .set_category_description("work", Some("  "))
+            .unwrap();
+        manager.set_category_color("work", None).unwrap();
+        assert_eq!(manager.categories[0], Category::new("Work"));
+    }
+}
@@ -0,0 +1,245 @@
+// The statistics screen: the number of tasks of each priority and in each
+// category as horizontal bar charts sized to the terminal, with their share
+// of all tasks, and a table of each category's tasks by status with its
+// completion rate.
+
+use crate::{Statistics, Status};
+
+const MIN_BAR_WIDTH: usize = 10;
+const MIN_LABEL_WIDTH: usize = 8;
+const BAR: char = '█';
+
+impl Statistics {
+    pub(crate) fn report(&self, width: usize) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("Total tasks: {}\n", self.total));
+        out.push_str(&format!("Completed: {}\n", self.completed));
+        out.push_str(&format!("In Progress: {}\n", self.in_progress));
+        out.push_str(&format!("Pending: {}\n", self.pending));
+        if self.total == 0 {
+            return out;
+        }
+        out.push_str(&format!(
+            "Completion rate: {}\n",
+            percent(self.completed, self.total)
+        ));
+
+        let priorities: Vec<(String, usize)> = self
+            .by_priority
+            .iter()
+            .map(|(priority, count)| (priority.name().to_string(), *count))
+            .collect();
+        out.push_str("\nBy Priority:\n");
+        out.push_str(&bar_chart(&priorities, self.total, width));
+
+        let categories: Vec<(String, usize)> = self
+            .by_category
+            .iter()
+            .map(|c| (c.name.clone(), c.total))
+            .collect();
+        out.push_str("\nBy Category:\n");
+        out.push_str(&bar_chart(&categories, self.total, width));
+
+        let label_width = label_width(
+            self.by_category.iter().map(|c| c.name.as_str()),
+            "Category",
+            width,
+        );
+        out.push_str("\nCompletion by Category:\n");
+        out.push_str(&format!(
+            "  {:<label_width$}  {:>7}  {:>11}  {:>9}  {:>6}\n",
+            "Category",
+            Status::Pending.to_string(),
+            Status::InProgress.to_string(),
+            Status::Completed.to_string(),
+            "Rate"
+        ));
+        for c in &self.by_category {
+            let rate = if c.total == 0 {
+                "-".to_string()
+            } else {
+                percent(c.completed, c.total)
+            };
+            out.push_str(&format!(
+                "  {:<label_width$}  {:>7}  {:>11}  {:>9}  {:>6}\n",
+                truncate(&c.name, label_width),
+                c.pending,
+                c.in_progress,
+                c.completed,
+                rate
+            ));
+        }
+        out
+    }
+}
+
+// One line per row: its label, a bar as long as its count relative to the
+// largest count, the count, and its share of `total`.
+fn bar_chart(rows: &[(String, usize)], total: usize, width: usize) -> String {
+    let label_width = label_width(rows.iter().map(|(label, _)| label.as_str()), "", width);
+    let max = rows.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    let count_width = max.to_string().len();
+    let fixed = 2 + label_width + 2 + 2 + count_width + 2 + "100.0%".len();
+    let bar_width = width.saturating_sub(fixed).max(MIN_BAR_WIDTH);
+
+    let mut out = String::new();
+    for (label, count) in rows {
+        // Rounded, but never so short that a count above zero has no bar.
+        let length = match *count {
+            0 => 0,
+            count => ((count * bar_width + max / 2) / max).max(1),
+        };
+        let bar: String = std::iter::repeat_n(BAR, length).collect();
+        out.push_str(&format!(
+            "  {:<label_width$}  {:<bar_width$}  {:>count_width$}  {:>6}\n",
+            truncate(label, label_width),
+            bar,
+            count,
+            percent(*count, total)
+        ));
+    }
+    out
+}
+
+// Wide enough for the longest label, or `heading`, but at most a third of the
+// terminal.
+fn label_width<'a>(labels: impl Iterator<Item = &'a str>, heading: &str, width: usize) -> usize {
+    let longest = labels
+        .map(|label| label.chars().count())
+        .chain([heading.chars().count()])
+        .max()
+        .unwrap_or(0);
+    longest.min((width / 3).max(MIN_LABEL_WIDTH))
+}
+
+// `text` cut to `width` characters, ending in '…' if anything was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+fn percent(part: usize, whole: usize) -> String {
+    format!("{:.1}%", part as f64 * 100.0 / whole as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Priority, Task, TaskManager};
+
+    fn rows(rows: &[(&str, usize)]) -> Vec<(String, usize)> {
+        rows.iter()
+            .map(|&(label, count)| (label.to_string(), count))
+            .collect()
+    }
+
+    fn bar_length(line: &str) -> usize {
+        line.chars().filter(|&c| c == BAR).count()
+    }
+
+    #[test]
+    fn bars_scale_to_the_largest_count_and_fill_the_width() {
+        let chart = bar_chart(&rows(&[("High", 4), ("Low", 2), ("None", 0)]), 6, 80);
+        let lines: Vec<&str> = chart.lines().collect();
+        // 80 columns less the label, count, percentage and spacing.
+        assert_eq!(
+            lines.iter().map(|l| bar_length(l)).collect::<Vec<_>>(),
+            [61, 31, 0]
+        );
+        assert!(lines.iter().all(|l| l.chars().count() == 80), "{}", chart);
+        assert!(lines[0].starts_with("  High  █"));
+        assert!(lines[0].ends_with("  4   66.7%"));
+        assert!(lines[2].ends_with("  0    0.0%"));
+    }
+
+    #[test]
+    fn zero_counts_have_no_bar_and_small_counts_one() {
+        let chart = bar_chart(&rows(&[("A", 0), ("B", 0)]), 5, 80);
+        assert_eq!(bar_length(&chart), 0);
+        assert_eq!(chart.matches("0.0%").count(), 2);
+
+        let chart = bar_chart(&rows(&[("A", 1000), ("B", 1)]), 1001, 80);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(bar_length(lines[1]), 1);
+        assert!(lines[1].ends_with("     1    0.1%"));
+    }
+
+    #[test]
+    fn narrow_widths_keep_a_minimum_bar_and_label() {
+        let long = "A category with a long name";
+        for width in [0, 10, 20] {
+            let chart = bar_chart(&rows(&[(long, 2), ("B", 1)]), 3, width);
+            let lines: Vec<&str> = chart.lines().collect();
+            assert_eq!(bar_length(lines[0]), MIN_BAR_WIDTH, "width {}", width);
+            assert_eq!(bar_length(lines[1]), MIN_BAR_WIDTH / 2);
+            assert!(lines[0].starts_with("  A categ…  "), "{}", lines[0]);
+        }
+    }
+
+    #[test]
+    fn labels_take_at_most_a_third_of_the_width() {
+        let labels = ["Work", "Personal"];
+        assert_eq!(label_width(labels.into_iter(), "", 80), 8);
+        assert_eq!(label_width(["Work"].into_iter(), "Category", 80), 8);
+        assert_eq!(label_width(["Work"].into_iter(), "", 80), 4);
+        let long = "x".repeat(40);
+        assert_eq!(label_width([long.as_str()].into_iter(), "", 90), 30);
+        assert_eq!(
+            label_width([long.as_str()].into_iter(), "", 12),
+            MIN_LABEL_WIDTH
+        );
+        assert_eq!(label_width([].into_iter(), "", 80), 0);
+        // Characters, not bytes.
+        assert_eq!(label_width(["Überfällig"].into_iter(), "", 80), 10);
+    }
+
+    #[test]
+    fn truncate_cuts_with_an_ellipsis() {
+        assert_eq!(truncate("Work", 8), "Work");
+        assert_eq!(truncate("Personal", 8), "Personal");
+        assert_eq!(truncate("Personal", 5), "Pers…");
+        assert_eq!(truncate("Überfällig", 4), "Übe…");
+        assert_eq!(truncate("Work", 1), "…");
+        assert_eq!(truncate("", 0), "");
+    }
+
+    #[test]
+    fn report_of_an_empty_list_has_no_charts() {
+        let report = TaskManager::new().statistics().report(80);
+        assert_eq!(
+            report,
+            "Total tasks: 0\nCompleted: 0\nIn Progress: 0\nPending: 0\n"
+        );
+    }
+
+    #[test]
+    fn report_lists_every_priority_and_category() {
+        let mut manager = TaskManager::new();
+        let task = Task::new(
+            1,
+            "Fix login bug".to_string(),
+            Priority::High,
+            "Work".to_string(),
+        );
+        manager.tasks.insert(1, task);
+        let report = manager.statistics().report(60);
+        assert!(report.contains("Completion rate: 0.0%\n"));
+        for priority in Priority::ALL {
+            assert!(report.contains(priority.name()), "{}", priority.name());
+        }
+        for category in &manager.categories {
+            assert!(report.contains(&format!("  {}", category.name)));
+        }
+        // Every line fits, and empty categories have no rate.
+        assert!(
+            report.lines().all(|l| l.chars().count() <= 60),
+            "{}",
+            report
+        );
+        assert!(report.contains("       -\n"));
+    }
+}
@@ -0,0 +1,72 @@
+// Where the menu reads its input and writes its output: the terminal, or a
+// file of commands in batch mode. A batch run shows no menus, prompts or
+// hints, only the results, and stops at the first error.
+
+use std::io::{self, BufRead, Write};
+
+use crate::TaskError;
+
+pub(crate) struct Console<'a> {
+    input: Box<dyn BufRead + 'a>,
+    pub(crate) output: Box<dyn Write + 'a>,
+    pub(crate) batch: bool,
+    // Whether the output may use terminal colors.
+    pub(crate) color: bool,
+    // Lines read so far, so that an error can name the line of the script.
+    pub(crate) line: usize,
+}
+
+impl<'a> Console<'a> {
+    pub(crate) fn new(input: impl BufRead + 'a, output: impl Write + 'a, batch: bool) -> Self {
+        Console {
+            input: Box::new(input),
+            output: Box::new(output),
+            batch,
+            color: false,
+            line: 0,
+        }
+    }
+
+    // The next line without its line break; None at the end of the input.
+    pub(crate) fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    // Shows `label` and reads the trimmed answer; the end of the input
+    // counts as an empty answer.
+    pub(crate) fn prompt(&mut self, label: &str) -> io::Result<String> {
+        if !self.batch {
+            write!(self.output, "{}", label)?;
+            self.output.flush()?;
+        }
+        Ok(self.read_line()?.unwrap_or_default().trim().to_string())
+    }
+
+    // Shows a line that only helps someone typing, such as a list of choices.
+    pub(crate) fn hint(&mut self, text: &str) -> io::Result<()> {
+        if !self.batch {
+            writeln!(self.output, "{}", text)?;
+        }
+        Ok(())
+    }
+
+    // Shows an error and carries on with a default value, or, in batch mode,
+    // passes the error on to end the run.
+    pub(crate) fn recover<T: Default>(
+        &mut self,
+        result: Result<T, TaskError>,
+    ) -> Result<T, TaskError> {
+        match result {
+            Err(e) if !self.batch => {
+                writeln!(self.output, "Error: {}", e)?;
+                Ok(T::default())
+            }
+            result => result,
+        }
+    }
+}
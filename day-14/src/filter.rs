@@ -0,0 +1,517 @@
+// Filters that combine conditions on a task's fields, written as expressions
+// such as
+//
+//   priority:urgent and category:work
+//   not status:completed and (text:rust or category:learning)
+//
+// `not` binds tighter than `and`, which binds tighter than `or`, and two
+// conditions next to each other mean `and`. `text:` looks for the words in
+// the title or the description, ignoring case; quote them to include spaces,
+// as in text:"pay rent". Named views keep a filter with the tasks so that it
+// can be run again later.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::{Filterable, Priority, Status, Task, TaskError, TaskManager};
+
+// Deeper nesting of parentheses and `not` is refused instead of overflowing
+// the stack.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Filter {
+    Priority(Priority),
+    // A category name, matched without regard to case.
+    Category(String),
+    Status(Status),
+    Text(String),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    pub(crate) fn matches(&self, task: &Task) -> bool {
+        match self {
+            Filter::Priority(priority) => task.priority == *priority,
+            Filter::Category(category) => task.category.to_lowercase() == category.to_lowercase(),
+            Filter::Status(status) => task.status == *status,
+            Filter::Text(text) => {
+                let text = text.to_lowercase();
+                task.title.to_lowercase().contains(&text)
+                    || task
+                        .description
+                        .as_ref()
+                        .is_some_and(|d| d.to_lowercase().contains(&text))
+            }
+            Filter::Not(filter) => !filter.matches(task),
+            Filter::And(a, b) => a.matches(task) && b.matches(task),
+            Filter::Or(a, b) => a.matches(task) || b.matches(task),
+        }
+    }
+
+    pub(crate) fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    pub(crate) fn or(self, other: Filter) -> Filter {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    pub(crate) fn negate(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+
+    // Follows a category from `from` to its new name `to`.
+    pub(crate) fn rename_category(&mut self, from: &str, to: &str) {
+        match self {
+            Filter::Category(category) if category.to_lowercase() == from.to_lowercase() => {
+                *category = to.to_string();
+            }
+            Filter::Not(filter) => filter.rename_category(from, to),
+            Filter::And(a, b) | Filter::Or(a, b) => {
+                a.rename_category(from, to);
+                b.rename_category(from, to);
+            }
+            _ => {}
+        }
+    }
+
+    // How tightly the filter binds, to know where Display needs parentheses.
+    fn precedence(&self) -> u8 {
+        match self {
+            Filter::Or(..) => 1,
+            Filter::And(..) => 2,
+            Filter::Not(_) => 3,
+            _ => 4,
+        }
+    }
+}
+
+// Writes the expression that parses back to the same filter.
+impl Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Priority(priority) => write!(f, "priority:{}", priority.name().to_lowercase()),
+            Filter::Category(category) => write!(f, "category:{}", quote(category)),
+            Filter::Status(Status::InProgress) => write!(f, "status:in-progress"),
+            Filter::Status(status) => write!(f, "status:{}", status.name().to_lowercase()),
+            Filter::Text(text) => write!(f, "text:{}", quote(text)),
+            Filter::Not(filter) => {
+                write!(f, "not ")?;
+                operand(f, filter, 3)
+            }
+            // Both operators group to the left, so a right operand of the
+            // same kind needs parentheses to parse back the same way.
+            Filter::And(a, b) => {
+                operand(f, a, 2)?;
+                write!(f, " and ")?;
+                operand(f, b, 3)
+            }
+            Filter::Or(a, b) => {
+                operand(f, a, 1)?;
+                write!(f, " or ")?;
+                operand(f, b, 2)
+            }
+        }
+    }
+}
+
+fn operand(f: &mut fmt::Formatter<'_>, filter: &Filter, precedence: u8) -> fmt::Result {
+    if filter.precedence() < precedence {
+        write!(f, "({})", filter)
+    } else {
+        write!(f, "{}", filter)
+    }
+}
+
+fn quote(text: &str) -> String {
+    let bare = !text.is_empty()
+        && !text
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"' | '\\'));
+    if bare {
+        return text.to_string();
+    }
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl FromStr for Filter {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            depth: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err(TaskError::InvalidInput("empty filter".to_string()));
+        }
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(filter),
+            Some(token) => Err(unexpected(token)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    // `and`, `or` and `not`, or a stray word.
+    Word(String),
+    Condition(String, String),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, TaskError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    let ends_word = |c: char| c.is_whitespace() || c == '(' || c == ')';
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '(' || c == ')' {
+            chars.next();
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            continue;
+        }
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if ends_word(c) || c == ':' {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        if chars.peek() != Some(&':') {
+            tokens.push(Token::Word(word));
+            continue;
+        }
+        chars.next();
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => value.extend(chars.next()),
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(TaskError::InvalidInput(format!(
+                            "missing closing quote after '{}:'",
+                            word
+                        )));
+                    }
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if ends_word(c) {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        if value.trim().is_empty() {
+            return Err(TaskError::InvalidInput(format!(
+                "missing value after '{}:'",
+                word
+            )));
+        }
+        tokens.push(Token::Condition(word, value));
+    }
+    Ok(tokens)
+}
+
+fn unexpected(token: &Token) -> TaskError {
+    let text = match token {
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::Word(word) => word.clone(),
+        Token::Condition(key, value) => format!("{}:{}", key, value),
+    };
+    TaskError::InvalidInput(format!("unexpected '{}' in filter", text))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Filter, TaskError> {
+        let mut filter = self.and()?;
+        while self.eat_word("or") {
+            filter = filter.or(self.and()?);
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, TaskError> {
+        let mut filter = self.not()?;
+        loop {
+            let starts_operand = match self.tokens.get(self.pos) {
+                Some(Token::Open | Token::Condition(..)) => true,
+                Some(Token::Word(word)) => !word.eq_ignore_ascii_case("or"),
+                _ => false,
+            };
+            if !starts_operand {
+                return Ok(filter);
+            }
+            self.eat_word("and");
+            filter = filter.and(self.not()?);
+        }
+    }
+
+    fn not(&mut self) -> Result<Filter, TaskError> {
+        if self.eat_word("not") {
+            return Ok(self.nested(Parser::not)?.negate());
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Filter, TaskError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(TaskError::InvalidInput("incomplete filter".to_string()));
+        };
+        self.pos += 1;
+        match token {
+            Token::Open => {
+                let filter = self.nested(Parser::or)?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    return Err(TaskError::InvalidInput("missing ')' in filter".to_string()));
+                }
+                self.pos += 1;
+                Ok(filter)
+            }
+            Token::Condition(key, value) => condition(&key, &value),
+            token => Err(unexpected(&token)),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Filter, TaskError>,
+    ) -> Result<Filter, TaskError> {
+        if self.depth == MAX_DEPTH {
+            return Err(TaskError::InvalidInput(
+                "filter is nested too deeply".to_string(),
+            ));
+        }
+        self.depth += 1;
+        let filter = parse(self);
+        self.depth -= 1;
+        filter
+    }
+
+    fn eat_word(&mut self, expected: &str) -> bool {
+        let found = matches!(
+            self.tokens.get(self.pos),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(expected)
+        );
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+}
+
+fn condition(key: &str, value: &str) -> Result<Filter, TaskError> {
+    match key.to_lowercase().as_str() {
+        "priority" => Ok(Filter::Priority(value.parse()?)),
+        "category" => Ok(Filter::Category(value.trim().to_string())),
+        "status" => Ok(Filter::Status(value.parse()?)),
+        "text" => Ok(Filter::Text(value.trim().to_string())),
+        _ => Err(TaskError::InvalidInput(format!(
+            "unknown condition '{}'; use priority, category, status or text",
+            key
+        ))),
+    }
+}
+
+// The matching tasks, in id order.
+impl Filterable<&Filter> for TaskManager {
+    fn filter_by(&self, filter: &Filter) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self.tasks.values().filter(|t| filter.matches(t)).collect();
+        tasks.sort_by_key(|t| t.id);
+        tasks
+    }
+}
+
+impl TaskManager {
+    // Saves `filter` under `name`, replacing any view of that name; returns
+    // whether one was replaced.
+    pub(crate) fn save_view(&mut self, name: &str, filter: Filter) -> Result<bool, TaskError> {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return Err(TaskError::InvalidInput(
+                "view name cannot be empty".to_string(),
+            ));
+        }
+        Ok(self.views.insert(name, filter).is_some())
+    }
+
+    pub(crate) fn delete_view(&mut self, name: &str) -> Result<Filter, TaskError> {
+        self.views
+            .remove(name)
+            .ok_or_else(|| TaskError::InvalidInput(format!("no view named '{}'", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Filter {
+        s.parse().unwrap_or_else(|e| panic!("{:?}: {}", s, e))
+    }
+
+    fn error(s: &str) -> String {
+        match s.parse::<Filter>() {
+            Err(TaskError::InvalidInput(message)) => message,
+            other => panic!("{:?} should not parse: {:?}", s, other),
+        }
+    }
+
+    fn category(name: &str) -> Filter {
+        Filter::Category(name.to_string())
+    }
+
+    fn text(words: &str) -> Filter {
+        Filter::Text(words.to_string())
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_and_and_than_or() {
+        let urgent = Filter::Priority(Priority::Urgent);
+        let done = Filter::Status(Status::Completed);
+        assert_eq!(
+            parse("priority:urgent or category:work and not status:completed"),
+            urgent
+                .clone()
+                .or(category("work").and(done.clone().negate()))
+        );
+        assert_eq!(
+            parse("(priority:urgent OR category:work) status:completed"),
+            urgent.clone().or(category("work")).and(done.clone())
+        );
+        assert_eq!(
+            parse("not (priority:urgent and status:completed)"),
+            urgent.clone().and(done.clone()).negate()
+        );
+        assert_eq!(parse("Not not status:completed"), done.negate().negate());
+        // Operators of one kind group to the left.
+        assert_eq!(
+            parse("text:a and text:b and text:c"),
+            text("a").and(text("b")).and(text("c"))
+        );
+        assert_eq!(
+            parse("text:a or text:b or text:c"),
+            text("a").or(text("b")).or(text("c"))
+        );
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces_and_escapes() {
+        assert_eq!(parse("text:\"pay rent\""), text("pay rent"));
+        assert_eq!(parse("text:\"say \\\"hi\\\"\""), text("say \"hi\""));
+        assert_eq!(parse("text:\"a \\\\ b\""), text("a \\ b"));
+        assert_eq!(parse("category:\"Side Project\""), category("Side Project"));
+        assert_eq!(parse("(text:\"(x)\")"), text("(x)"));
+        assert!(error("text:\"pay rent").contains("closing quote"));
+        assert!(error("text:\"  \"").contains("missing value"));
+        assert!(error("text:").contains("missing value"));
+    }
+
+    #[test]
+    fn rejects_malformed_filters() {
+        assert!(error("").contains("empty"));
+        assert!(error("   ").contains("empty"));
+        assert!(error("priority:extreme").contains("extreme"));
+        assert!(error("colour:red").contains("unknown condition"));
+        assert!(error("status:completed or").contains("incomplete"));
+        assert!(error("not").contains("incomplete"));
+        assert!(error("(status:completed").contains("missing ')'"));
+        assert!(error("status:completed)").contains("')'"));
+        assert!(error("and status:completed").contains("'and'"));
+        assert!(error("status:completed or or text:a").contains("'or'"));
+        assert!(error("()").contains("')'"));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |n: usize| format!("{}text:x{}", "(".repeat(n), ")".repeat(n));
+        assert_eq!(parse(&nested(MAX_DEPTH)), text("x"));
+        assert!(error(&nested(MAX_DEPTH + 1)).contains("too deeply"));
+        assert!(error(&nested(100_000)).contains("too deeply"));
+
+        let negated = |n: usize| format!("{}text:x", "not ".repeat(n));
+        parse(&negated(MAX_DEPTH));
+        assert!(error(&negated(MAX_DEPTH + 1)).contains("too deeply"));
+    }
+
+    #[test]
+    fn display_parses_back_to_the_same_filter() {
+        for expression in [
+            "priority:urgent",
+            "status:in-progress and not status:completed",
+            "category:\"Side Project\" or text:\"say \\\"hi\\\" (now)\"",
+            "text:a or text:b and text:c",
+            "(text:a or text:b) and text:c",
+            "text:a and (text:b and text:c)",
+            "text:a or (text:b or text:c)",
+            "not (text:a or text:b) and not not text:c",
+            "text:\"back\\\\slash\"",
+        ] {
+            let filter = parse(expression);
+            let written = filter.to_string();
+            assert_eq!(parse(&written), filter, "{} -> {}", expression, written);
+        }
+        assert_eq!(
+            parse("(PRIORITY:Urgent) AND (category:work OR text:\"pay rent\")").to_string(),
+            "priority:urgent and (category:work or text:\"pay rent\")"
+        );
+        assert_eq!(
+            text("a").and(text("b").and(text("c"))).to_string(),
+            "text:a and (text:b and text:c)"
+        );
+    }
+
+    #[test]
+    fn matches_ignoring_case() {
+        let mut task = Task::new(
+            1,
+            "Pay Rent".to_string(),
+            Priority::High,
+            "Home".to_string(),
+        );
+        task.description = Some("Before the FIRST".to_string());
+        assert!(parse("category:home text:rent").matches(&task));
+        assert!(parse("text:first").matches(&task));
+        assert!(!parse("text:\"rent before\"").matches(&task));
+        assert!(parse("priority:high and not status:completed").matches(&task));
+        assert!(!parse("priority:low or category:work").matches(&task));
+    }
+
+    #[test]
+    fn renames_categories_throughout_the_filter() {
+        let mut filter =
+            parse("category:work or not (category:WORK and category:home) or text:work");
+        filter.rename_category("Work", "Job");
+        assert_eq!(
+            filter,
+            parse("category:Job or not (category:Job and category:home) or text:work")
+        );
+        // An unknown name leaves the filter alone.
+        let before = filter.clone();
+        filter.rename_category("Garden", "Yard");
+        assert_eq!(filter, before);
+    }
+}
@@ -2,63 +2,202 @@
 // This file contains the Week 2 project for the 21 Days of Rust Challenge
 // Build a task manager using Structs, Enums, Error Handling, Collections, Generics, and Traits!
 
-use std::collections::HashMap;
+mod board;
+mod categories;
+mod charts;
+mod console;
+mod filter;
+mod storage;
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fmt::{self, Display};
-use std::io::{self, Write};
+use std::fs::File;
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::process;
+use std::str::FromStr;
+
+use categories::{Category, Color};
+use console::Console;
+use filter::Filter;
+
+// Where the tasks are kept between runs, unless TASKS_FILE says otherwise.
+const DEFAULT_DATA_FILE: &str = "tasks.txt";
+
+// What the menu loop needs to know besides the tasks.
+struct Settings {
+    path: String,
+    // With autosave every change is written at once; otherwise on exit.
+    autosave: bool,
+    wip_limit: usize,
+}
 
 fn main() {
-    println!("=== Day 14: Week 2 Project - Task Manager ===\n");
-    println!(
-        "This project combines: Structs, Enums, Error Handling, Collections, Generics, and Traits\n"
-    );
+    let mut settings = Settings {
+        path: env::var("TASKS_FILE").unwrap_or_else(|_| DEFAULT_DATA_FILE.to_string()),
+        autosave: env::var("TASKS_AUTOSAVE").is_ok_and(|v| v == "1" || v == "true"),
+        wip_limit: board::wip_limit(),
+    };
+    let mut script = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--autosave" => settings.autosave = true,
+            "--batch" => match args.next() {
+                Some(file) => script = Some(file),
+                None => usage(),
+            },
+            _ => usage(),
+        }
+    }
 
-    let mut manager = TaskManager::new();
+    if script.is_none() {
+        println!("=== Day 14: Week 2 Project - Task Manager ===\n");
+        println!(
+            "This project combines: Structs, Enums, Error Handling, Collections, Generics, and Traits\n"
+        );
+    }
+    let mut manager = match storage::open(&settings.path) {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("Error: could not open {}: {}", settings.path, e);
+            process::exit(1);
+        }
+    };
+
+    let result = match &script {
+        Some(script) => {
+            let file = match File::open(script) {
+                Ok(file) => file,
+                Err(e) => {
+                    eprintln!("Error: could not open {}: {}", script, e);
+                    process::exit(1);
+                }
+            };
+            let mut console = Console::new(BufReader::new(file), io::stdout(), true);
+            run(&mut manager, &settings, &mut console)
+                .map_err(|e| format!("{}:{}: {}", script, console.line, e))
+        }
+        None => {
+            println!("Loaded {} task(s) from {}", manager.count(), settings.path);
+            let mut console = Console::new(io::stdin().lock(), io::stdout(), false);
+            console.color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+            run(&mut manager, &settings, &mut console).map_err(|e| e.to_string())
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
 
+fn usage() -> ! {
+    eprintln!("Usage: day-14 [--autosave] [--batch FILE]");
+    process::exit(2);
+}
+
+// Runs menu commands until "8" or the end of the input. In batch mode blank
+// lines and lines starting with '#' between commands are skipped.
+fn run(
+    manager: &mut TaskManager,
+    settings: &Settings,
+    console: &mut Console,
+) -> Result<(), TaskError> {
+    let mut unsaved = false;
     loop {
-        print_menu();
-
-        let mut choice = String::new();
-        io::stdin()
-            .read_line(&mut choice)
-            .expect("Failed to read line");
-
-        match choice.trim() {
-            "1" => add_task_interactive(&mut manager),
-            "2" => list_tasks(&manager),
-            "3" => complete_task_interactive(&mut manager),
-            "4" => delete_task_interactive(&mut manager),
-            "5" => filter_by_priority(&manager),
-            "6" => filter_by_category(&manager),
-            "7" => show_statistics(&manager),
+        print_menu(console)?;
+        let choice = match console.read_line()? {
+            Some(choice) => choice.trim().to_string(),
+            // End of input (e.g. Ctrl-D) exits like option 8.
+            None => {
+                console.hint("")?;
+                "8".to_string()
+            }
+        };
+        if console.batch && (choice.is_empty() || choice.starts_with('#')) {
+            continue;
+        }
+
+        let result = match choice.as_str() {
+            "1" => add_task_interactive(manager, console),
+            "2" => list_tasks(manager, console),
+            "3" => complete_task_interactive(manager, console),
+            "4" => delete_task_interactive(manager, console),
+            "5" => filter_by_priority(manager, console),
+            "6" => filter_by_category(manager, console),
+            "7" => show_statistics(manager, console),
+            "9" => edit_task_interactive(manager, console),
+            "10" => start_task_interactive(manager, console, settings.wip_limit),
+            "11" => show_board(manager, console, settings.wip_limit),
+            "12" => filter_tasks_interactive(manager, console),
+            "13" => views_interactive(manager, console),
+            "14" => categories_interactive(manager, console),
             "8" => {
-                println!("Goodbye!");
-                break;
+                if unsaved {
+                    save(manager, &settings.path, console)?;
+                }
+                console.hint("Goodbye!")?;
+                return Ok(());
             }
-            _ => println!("Invalid option, please try again."),
+            other => Err(TaskError::InvalidInput(format!(
+                "unknown option '{}'",
+                other
+            ))),
+        };
+        if console.recover(result)? {
+            unsaved = !(settings.autosave && save(manager, &settings.path, console)?);
+        }
+        console.hint("")?;
+    }
+}
+
+// Saves the tasks; returns whether they were saved. A failure is reported,
+// or in batch mode returned.
+fn save(manager: &TaskManager, path: &str, console: &mut Console) -> Result<bool, TaskError> {
+    match manager.save(path) {
+        Ok(()) => Ok(true),
+        Err(e) if !console.batch => {
+            writeln!(
+                console.output,
+                "Error: could not save tasks to {}: {}",
+                path, e
+            )?;
+            Ok(false)
         }
-        println!();
+        Err(e) => Err(e),
     }
 }
 
-fn print_menu() {
-    println!("\n--- Task Manager Menu ---");
-    println!("1. Add Task");
-    println!("2. List All Tasks");
-    println!("3. Complete Task");
-    println!("4. Delete Task");
-    println!("5. Filter by Priority");
-    println!("6. Filter by Category");
-    println!("7. Show Statistics");
-    println!("8. Exit");
-    print!("\nChoose an option (1-8): ");
-    io::stdout().flush().unwrap();
+fn print_menu(console: &mut Console) -> io::Result<()> {
+    if console.batch {
+        return Ok(());
+    }
+    let out = &mut console.output;
+    writeln!(out, "\n--- Task Manager Menu ---")?;
+    writeln!(out, "1. Add Task")?;
+    writeln!(out, "2. List All Tasks")?;
+    writeln!(out, "3. Complete Task")?;
+    writeln!(out, "4. Delete Task")?;
+    writeln!(out, "5. Filter by Priority")?;
+    writeln!(out, "6. Filter by Category")?;
+    writeln!(out, "7. Show Statistics")?;
+    writeln!(out, "8. Exit")?;
+    writeln!(out, "9. Edit Task")?;
+    writeln!(out, "10. Start Task")?;
+    writeln!(out, "11. Show Board")?;
+    writeln!(out, "12. Filter Tasks")?;
+    writeln!(out, "13. Saved Views")?;
+    writeln!(out, "14. Manage Categories")?;
+    write!(out, "\nChoose an option (1-14): ")?;
+    out.flush()
 }
 
 // ============================================================
 // ENUMS
 // ============================================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Priority {
     Low,
     Medium,
@@ -66,6 +205,39 @@ enum Priority {
     Urgent,
 }
 
+impl Priority {
+    const ALL: [Priority; 4] = [
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Urgent,
+    ];
+
+    // The name written to the data file.
+    fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            _ => Err(TaskError::InvalidInput(format!("unknown priority '{}'", s))),
+        }
+    }
+}
+
 impl Display for Priority {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -84,33 +256,43 @@ enum Status {
     Completed,
 }
 
-impl Display for Status {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Status {
+    const ALL: [Status; 3] = [Status::Pending, Status::InProgress, Status::Completed];
+
+    // The name written to the data file.
+    fn name(self) -> &'static str {
         match self {
-            Status::Pending => write!(f, "Pending"),
-            Status::InProgress => write!(f, "In Progress"),
-            Status::Completed => write!(f, "Completed"),
+            Status::Pending => "Pending",
+            Status::InProgress => "InProgress",
+            Status::Completed => "Completed",
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Category {
-    Work,
-    Personal,
-    Learning,
-    Health,
-    Finance,
+impl FromStr for Status {
+    type Err = TaskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s
+            .trim()
+            .to_lowercase()
+            .replace([' ', '_', '-'], "")
+            .as_str()
+        {
+            "pending" => Ok(Status::Pending),
+            "inprogress" => Ok(Status::InProgress),
+            "completed" => Ok(Status::Completed),
+            _ => Err(TaskError::InvalidInput(format!("unknown status '{}'", s))),
+        }
+    }
 }
 
-impl Display for Category {
+impl Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Category::Work => write!(f, "Work"),
-            Category::Personal => write!(f, "Personal"),
-            Category::Learning => write!(f, "Learning"),
-            Category::Health => write!(f, "Health"),
-            Category::Finance => write!(f, "Finance"),
+            Status::Pending => write!(f, "Pending"),
+            Status::InProgress => write!(f, "In Progress"),
+            Status::Completed => write!(f, "Completed"),
         }
     }
 }
@@ -124,6 +306,7 @@ enum TaskError {
     NotFound(u32),
     AlreadyCompleted(u32),
     InvalidInput(String),
+    IoError(io::Error),
 }
 
 impl Display for TaskError {
@@ -132,26 +315,34 @@ impl Display for TaskError {
             TaskError::NotFound(id) => write!(f, "Task #{} not found", id),
             TaskError::AlreadyCompleted(id) => write!(f, "Task #{} is already completed", id),
             TaskError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            TaskError::IoError(e) => write!(f, "I/O error: {}", e),
         }
     }
 }
 
+impl From<io::Error> for TaskError {
+    fn from(error: io::Error) -> Self {
+        TaskError::IoError(error)
+    }
+}
+
 // ============================================================
 // STRUCTS
 // ============================================================
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 struct Task {
     id: u32,
     title: String,
     description: Option<String>,
     priority: Priority,
-    category: Category,
+    // The name of one of the manager's categories.
+    category: String,
     status: Status,
 }
 
 impl Task {
-    fn new(id: u32, title: String, priority: Priority, category: Category) -> Self {
+    fn new(id: u32, title: String, priority: Priority, category: String) -> Self {
         Task {
             id,
             title,
@@ -175,6 +366,29 @@ impl Task {
             Ok(())
         }
     }
+
+    fn start(&mut self) -> Result<(), TaskError> {
+        match self.status {
+            Status::Pending => {
+                self.status = Status::InProgress;
+                Ok(())
+            }
+            Status::InProgress => Err(TaskError::InvalidInput(format!(
+                "task #{} is already in progress",
+                self.id
+            ))),
+            Status::Completed => Err(TaskError::AlreadyCompleted(self.id)),
+        }
+    }
+}
+
+// Titles are trimmed and may not be empty.
+fn validate_title(title: &str) -> Result<String, TaskError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(TaskError::InvalidInput("title cannot be empty".to_string()));
+    }
+    Ok(title.to_string())
 }
 
 impl Display for Task {
@@ -221,6 +435,10 @@ trait Filterable<T> {
 struct TaskManager {
     tasks: HashMap<u32, Task>,
     next_id: u32,
+    // Saved filters by name.
+    views: BTreeMap<String, Filter>,
+    // In the order they are listed in menus; never empty.
+    categories: Vec<Category>,
 }
 
 impl TaskManager {
@@ -228,10 +446,12 @@ impl TaskManager {
         TaskManager {
             tasks: HashMap::new(),
             next_id: 1,
+            views: BTreeMap::new(),
+            categories: categories::default_categories(),
         }
     }
 
-    fn add_task(&mut self, title: String, priority: Priority, category: Category) -> u32 {
+    fn add_task(&mut self, title: String, priority: Priority, category: String) -> u32 {
         let id = self.next_id;
         let task = Task::new(id, title, priority, category);
         self.tasks.insert(id, task);
@@ -250,12 +470,56 @@ impl TaskManager {
         }
     }
 
+    fn start_task(&mut self, id: u32) -> Result<(), TaskError> {
+        self.task_mut(id)?.start()
+    }
+
     fn delete_task(&mut self, id: u32) -> Result<Task, TaskError> {
         self.tasks.remove(&id).ok_or(TaskError::NotFound(id))
     }
 
+    fn set_title(&mut self, id: u32, title: &str) -> Result<(), TaskError> {
+        let title = validate_title(title)?;
+        self.task_mut(id)?.title = title;
+        Ok(())
+    }
+
+    // None, or a blank description, removes the description.
+    fn set_description(&mut self, id: u32, description: Option<&str>) -> Result<(), TaskError> {
+        let description = description
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(String::from);
+        self.task_mut(id)?.description = description;
+        Ok(())
+    }
+
+    fn set_priority(&mut self, id: u32, priority: Priority) -> Result<(), TaskError> {
+        self.task_mut(id)?.priority = priority;
+        Ok(())
+    }
+
+    fn set_category(&mut self, id: u32, category: &str) -> Result<(), TaskError> {
+        let name = self.find_category(category)?.name.clone();
+        self.task_mut(id)?.category = name;
+        Ok(())
+    }
+
+    // Unlike complete_task, any status can be set, including back to Pending.
+    fn set_status(&mut self, id: u32, status: Status) -> Result<(), TaskError> {
+        self.task_mut(id)?.status = status;
+        Ok(())
+    }
+
+    fn task_mut(&mut self, id: u32) -> Result<&mut Task, TaskError> {
+        self.tasks.get_mut(&id).ok_or(TaskError::NotFound(id))
+    }
+
+    // Every task, in id order.
     fn list_all(&self) -> Vec<&Task> {
-        self.tasks.values().collect()
+        let mut tasks: Vec<&Task> = self.tasks.values().collect();
+        tasks.sort_by_key(|t| t.id);
+        tasks
     }
 
     fn count(&self) -> usize {
@@ -274,11 +538,11 @@ impl Filterable<Priority> for TaskManager {
 }
 
 // Implement Filterable for Category
-impl Filterable<Category> for TaskManager {
-    fn filter_by(&self, category: Category) -> Vec<&Task> {
+impl Filterable<&Category> for TaskManager {
+    fn filter_by(&self, category: &Category) -> Vec<&Task> {
         self.tasks
             .values()
-            .filter(|t| t.category == category)
+            .filter(|t| category.is(&t.category))
             .collect()
     }
 }
@@ -299,8 +563,18 @@ struct Statistics {
     completed: usize,
     pending: usize,
     in_progress: usize,
-    by_priority: HashMap<Priority, usize>,
-    by_category: HashMap<Category, usize>,
+    // Every priority, lowest first.
+    by_priority: Vec<(Priority, usize)>,
+    // Every category, the most tasks first.
+    by_category: Vec<CategoryStatistics>,
+}
+
+struct CategoryStatistics {
+    name: String,
+    total: usize,
+    completed: usize,
+    pending: usize,
+    in_progress: usize,
 }
 
 impl TaskManager {
@@ -310,8 +584,18 @@ impl TaskManager {
             completed: 0,
             pending: 0,
             in_progress: 0,
-            by_priority: HashMap::new(),
-            by_category: HashMap::new(),
+            by_priority: Priority::ALL.iter().map(|&p| (p, 0)).collect(),
+            by_category: self
+                .categories
+                .iter()
+                .map(|c| CategoryStatistics {
+                    name: c.name.clone(),
+                    total: 0,
+                    completed: 0,
+                    pending: 0,
+                    in_progress: 0,
+                })
+                .collect(),
         };
 
         for task in self.tasks.values() {
@@ -321,10 +605,29 @@ impl TaskManager {
                 Status::InProgress => stats.in_progress += 1,
             }
 
-            *stats.by_priority.entry(task.priority).or_insert(0) += 1;
-            *stats.by_category.entry(task.category).or_insert(0) += 1;
+            if let Some((_, count)) = stats
+                .by_priority
+                .iter_mut()
+                .find(|(p, _)| *p == task.priority)
+            {
+                *count += 1;
+            }
+            if let Some(category) = stats
+                .by_category
+                .iter_mut()
+                .find(|c| c.name == task.category)
+            {
+                category.total += 1;
+                match task.status {
+                    Status::Completed => category.completed += 1,
+                    Status::Pending => category.pending += 1,
+                    Status::InProgress => category.in_progress += 1,
+                }
+            }
         }
 
+        // A stable sort, so categories with as many tasks keep their order.
+        stats.by_category.sort_by_key(|c| Reverse(c.total));
         stats
     }
 }
@@ -333,154 +636,833 @@ impl TaskManager {
 // INTERACTIVE FUNCTIONS
 // ============================================================
 
-fn add_task_interactive(manager: &mut TaskManager) {
-    println!("\n--- Add New Task ---");
-
-    print!("Title: ");
-    io::stdout().flush().unwrap();
-    let mut title = String::new();
-    io::stdin().read_line(&mut title).unwrap();
-    let title = title.trim().to_string();
-
-    println!("Priority: 1=Low, 2=Medium, 3=High, 4=Urgent");
-    print!("Choose (1-4): ");
-    io::stdout().flush().unwrap();
-    let mut priority_input = String::new();
-    io::stdin().read_line(&mut priority_input).unwrap();
-    let priority = match priority_input.trim() {
-        "1" => Priority::Low,
-        "2" => Priority::Medium,
-        "3" => Priority::High,
-        "4" => Priority::Urgent,
-        _ => Priority::Medium,
+fn add_task_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+) -> Result<bool, TaskError> {
+    console.hint("\n--- Add New Task ---")?;
+    let title = validate_title(&console.prompt("Title: ")?)?;
+
+    // A blank answer takes the default. So does an unknown one, except in
+    // batch mode, where it is an error like any other.
+    console.hint(&priority_choices())?;
+    let input = console.prompt("Choose (1-4): ")?;
+    let priority = match choose(&input, &Priority::ALL, "priority") {
+        Ok(priority) => priority,
+        Err(e) if console.batch && !input.is_empty() => return Err(e),
+        Err(_) => Priority::Medium,
     };
 
-    println!("Category: 1=Work, 2=Personal, 3=Learning, 4=Health, 5=Finance");
-    print!("Choose (1-5): ");
-    io::stdout().flush().unwrap();
-    let mut category_input = String::new();
-    io::stdin().read_line(&mut category_input).unwrap();
-    let category = match category_input.trim() {
-        "1" => Category::Work,
-        "2" => Category::Personal,
-        "3" => Category::Learning,
-        "4" => Category::Health,
-        "5" => Category::Finance,
-        _ => Category::Personal,
+    console.hint(&category_choices(manager))?;
+    let input = console.prompt(&format!("Choose (1-{}): ", manager.categories.len()))?;
+    let category = match manager.find_category(&input) {
+        Ok(category) => category.name.clone(),
+        Err(e) if console.batch && !input.is_empty() => return Err(e),
+        Err(_) => manager.categories[0].name.clone(),
     };
 
+    let description = console.prompt("Description (optional): ")?;
+
     let id = manager.add_task(title, priority, category);
-    println!("Task #{} added successfully!", id);
+    manager.set_description(id, Some(&description))?;
+    writeln!(console.output, "Task #{} added successfully!", id)?;
+    if let Some(Task {
+        description: Some(description),
+        ..
+    }) = manager.get_task(id)
+    {
+        writeln!(console.output, "  {}", description)?;
+    }
+    Ok(true)
 }
 
-fn list_tasks(manager: &TaskManager) {
-    println!("\n--- All Tasks ---");
-    let tasks = manager.list_all();
-    if tasks.is_empty() {
-        println!("No tasks found.");
-    } else {
-        for task in tasks {
-            println!("{}", task);
+fn edit_task_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+) -> Result<bool, TaskError> {
+    console.hint("\n--- Edit Task ---")?;
+    let id = parse_id(&console.prompt("Enter task ID to edit: ")?)?;
+    let original = manager
+        .get_task(id)
+        .cloned()
+        .ok_or(TaskError::NotFound(id))?;
+    console.hint(&original.to_string())?;
+    console.hint("Press Enter to keep the current value.")?;
+
+    // A rejected value is reported and the field left as it was.
+    let title = console.prompt(&format!("Title [{}]: ", original.title))?;
+    if !title.is_empty() {
+        console.recover(manager.set_title(id, &title))?;
+    }
+
+    let description = console.prompt(&format!(
+        "Description [{}] ('-' to remove): ",
+        original.description.as_deref().unwrap_or("none")
+    ))?;
+    match description.as_str() {
+        "" => {}
+        "-" => console.recover(manager.set_description(id, None))?,
+        description => console.recover(manager.set_description(id, Some(description)))?,
+    }
+
+    console.hint(&priority_choices())?;
+    let priority = console.prompt(&format!("Choose (1-4) [{}]: ", original.priority))?;
+    if !priority.is_empty() {
+        let priority = choose(&priority, &Priority::ALL, "priority");
+        console.recover(priority.and_then(|p| manager.set_priority(id, p)))?;
+    }
+
+    console.hint(&category_choices(manager))?;
+    let category = console.prompt(&format!(
+        "Choose (1-{}) [{}]: ",
+        manager.categories.len(),
+        original.category
+    ))?;
+    if !category.is_empty() {
+        console.recover(manager.set_category(id, &category))?;
+    }
+
+    console.hint(&status_choices())?;
+    let status = console.prompt(&format!("Choose (1-3) [{}]: ", original.status))?;
+    if !status.is_empty() {
+        let status = choose(&status, &Status::ALL, "status");
+        console.recover(status.and_then(|s| manager.set_status(id, s)))?;
+    }
+
+    match manager.get_task(id) {
+        Some(task) if *task != original => {
+            writeln!(console.output, "Task #{} updated:", id)?;
+            writeln!(console.output, "{}", task)?;
+            Ok(true)
+        }
+        _ => {
+            writeln!(console.output, "No changes made.")?;
+            Ok(false)
         }
     }
 }
 
-fn complete_task_interactive(manager: &mut TaskManager) {
-    print!("Enter task ID to complete: ");
-    io::stdout().flush().unwrap();
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+fn parse_id(input: &str) -> Result<u32, TaskError> {
+    input
+        .trim()
+        .parse()
+        .map_err(|_| TaskError::InvalidInput(format!("invalid task ID '{}'", input.trim())))
+}
 
-    match input.trim().parse::<u32>() {
-        Ok(id) => match manager.complete_task(id) {
-            Ok(()) => println!("Task #{} completed!", id),
-            Err(e) => println!("Error: {}", e),
-        },
-        Err(_) => println!("Invalid ID"),
+// A menu choice: the number shown or the name, e.g. "3" or "high".
+fn choose<T: Copy + FromStr<Err = TaskError>>(
+    input: &str,
+    choices: &[T],
+    what: &str,
+) -> Result<T, TaskError> {
+    let input = input.trim();
+    match input.parse::<usize>() {
+        Ok(n) => n
+            .checked_sub(1)
+            .and_then(|i| choices.get(i))
+            .copied()
+            .ok_or_else(|| TaskError::InvalidInput(format!("unknown {} '{}'", what, input))),
+        Err(_) => input.parse(),
     }
 }
 
-fn delete_task_interactive(manager: &mut TaskManager) {
-    print!("Enter task ID to delete: ");
-    io::stdout().flush().unwrap();
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+// "1=Low, 2=Medium, ..." for a menu of choices.
+fn numbered(names: &[String]) -> String {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| format!("{}={}", i + 1, name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn priority_choices() -> String {
+    let names = Priority::ALL.map(|p| p.name().to_string());
+    format!("Priority: {}", numbered(&names))
+}
+
+fn status_choices() -> String {
+    let names = Status::ALL.map(|s| s.to_string());
+    format!("Status: {}", numbered(&names))
+}
+
+fn category_choices(manager: &TaskManager) -> String {
+    let names: Vec<String> = manager.categories.iter().map(|c| c.name.clone()).collect();
+    format!("Category: {}", numbered(&names))
+}
 
-    match input.trim().parse::<u32>() {
-        Ok(id) => match manager.delete_task(id) {
-            Ok(task) => println!("Deleted: {}", task.summary()),
-            Err(e) => println!("Error: {}", e),
-        },
-        Err(_) => println!("Invalid ID"),
+fn list_tasks(manager: &TaskManager, console: &mut Console) -> Result<bool, TaskError> {
+    writeln!(console.output, "\n--- All Tasks ---")?;
+    print_tasks(&manager.list_all(), console)?;
+    Ok(false)
+}
+
+fn complete_task_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+) -> Result<bool, TaskError> {
+    let id = parse_id(&console.prompt("Enter task ID to complete: ")?)?;
+    manager.complete_task(id)?;
+    writeln!(console.output, "Task #{} completed!", id)?;
+    Ok(true)
+}
+
+fn start_task_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+    wip_limit: usize,
+) -> Result<bool, TaskError> {
+    let id = parse_id(&console.prompt("Enter task ID to start: ")?)?;
+    manager.start_task(id)?;
+    writeln!(console.output, "Task #{} started!", id)?;
+    let in_progress = manager.filter_by(Status::InProgress).len();
+    if let Some(warning) = board::wip_warning(in_progress, wip_limit) {
+        writeln!(console.output, "{}", warning)?;
     }
+    Ok(true)
 }
 
-fn filter_by_priority(manager: &TaskManager) {
-    println!("Priority: 1=Low, 2=Medium, 3=High, 4=Urgent");
-    print!("Choose (1-4): ");
-    io::stdout().flush().unwrap();
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+fn show_board(
+    manager: &TaskManager,
+    console: &mut Console,
+    wip_limit: usize,
+) -> Result<bool, TaskError> {
+    writeln!(console.output, "\n--- Board ---")?;
+    write!(
+        console.output,
+        "{}",
+        manager.board(board::terminal_width(), wip_limit)
+    )?;
+    Ok(false)
+}
 
-    let priority = match input.trim() {
-        "1" => Priority::Low,
-        "2" => Priority::Medium,
-        "3" => Priority::High,
-        "4" => Priority::Urgent,
-        _ => return println!("Invalid priority"),
-    };
+fn delete_task_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+) -> Result<bool, TaskError> {
+    let id = parse_id(&console.prompt("Enter task ID to delete: ")?)?;
+    let task = manager.delete_task(id)?;
+    writeln!(console.output, "Deleted: {}", task.summary())?;
+    Ok(true)
+}
 
-    let tasks: Vec<&Task> = manager.filter_by(priority);
-    println!("\n--- {} Priority Tasks ---", priority);
+fn filter_by_priority(manager: &TaskManager, console: &mut Console) -> Result<bool, TaskError> {
+    console.hint(&priority_choices())?;
+    let priority = choose(
+        &console.prompt("Choose (1-4): ")?,
+        &Priority::ALL,
+        "priority",
+    )?;
+
+    let mut tasks: Vec<&Task> = manager.filter_by(priority);
+    tasks.sort_by_key(|t| t.id);
+    writeln!(console.output, "\n--- {} Priority Tasks ---", priority)?;
     for task in tasks {
-        println!("{}", task);
+        writeln!(console.output, "{}", task)?;
     }
+    Ok(false)
 }
 
-fn filter_by_category(manager: &TaskManager) {
-    println!("Category: 1=Work, 2=Personal, 3=Learning, 4=Health, 5=Finance");
-    print!("Choose (1-5): ");
-    io::stdout().flush().unwrap();
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
+fn filter_by_category(manager: &TaskManager, console: &mut Console) -> Result<bool, TaskError> {
+    console.hint(&category_choices(manager))?;
+    let input = console.prompt(&format!("Choose (1-{}): ", manager.categories.len()))?;
+    let category = manager.find_category(&input)?;
+
+    let mut tasks: Vec<&Task> = manager.filter_by(category);
+    tasks.sort_by_key(|t| t.id);
+    writeln!(console.output, "\n--- {} Tasks ---", category)?;
+    for task in tasks {
+        writeln!(console.output, "{}", task)?;
+    }
+    Ok(false)
+}
 
-    let category = match input.trim() {
-        "1" => Category::Work,
-        "2" => Category::Personal,
-        "3" => Category::Learning,
-        "4" => Category::Health,
-        "5" => Category::Finance,
-        _ => return println!("Invalid category"),
+// Builds a filter one condition at a time; returns whether a view was saved.
+fn filter_tasks_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+) -> Result<bool, TaskError> {
+    console.hint("\n--- Filter Tasks ---")?;
+    console.hint("Conditions: priority:<level>, category:<name>, status:<state>, text:<words>")?;
+    console.hint("They can be combined with and, or, not and parentheses, e.g.")?;
+    console.hint("  priority:urgent and not status:completed")?;
+
+    let mut filter: Option<Filter> = None;
+    let mut saved = false;
+    loop {
+        match &filter {
+            Some(filter) => console.hint(&format!(
+                "\nFilter: {} ({} matching)",
+                filter,
+                manager.filter_by(filter).len()
+            ))?,
+            None => console.hint("\nFilter: all tasks")?,
+        }
+        console.hint("a=and, o=or, n=not, s=show tasks, v=save as view, c=clear, Enter=done")?;
+        let choice = console.prompt("Choose: ")?;
+        match choice.as_str() {
+            "" => return Ok(saved),
+            "a" | "o" => {
+                let condition = console.prompt("Condition: ")?;
+                let Some(condition) = console.recover(condition.parse::<Filter>().map(Some))?
+                else {
+                    continue;
+                };
+                filter = Some(match filter.take() {
+                    None => condition,
+                    Some(filter) if choice == "a" => filter.and(condition),
+                    Some(filter) => filter.or(condition),
+                });
+            }
+            "n" => match filter.take() {
+                Some(current) => filter = Some(current.negate()),
+                None => console.recover(no_filter_yet())?,
+            },
+            "s" => match &filter {
+                Some(filter) => print_tasks(&manager.filter_by(filter), console)?,
+                None => print_tasks(&manager.list_all(), console)?,
+            },
+            "v" => {
+                let Some(current) = filter.clone() else {
+                    console.recover(no_filter_yet())?;
+                    continue;
+                };
+                let name = console.prompt("View name: ")?;
+                if let Some(replaced) =
+                    console.recover(manager.save_view(&name, current).map(Some))?
+                {
+                    let verb = if replaced { "replaced" } else { "saved" };
+                    writeln!(console.output, "View '{}' {}.", name, verb)?;
+                    saved = true;
+                }
+            }
+            "c" => filter = None,
+            other => console.recover(Err(TaskError::InvalidInput(format!(
+                "unknown filter option '{}'",
+                other
+            ))))?,
+        }
+    }
+}
+
+fn no_filter_yet() -> Result<(), TaskError> {
+    Err(TaskError::InvalidInput("add a condition first".to_string()))
+}
+
+// Lists the saved views to run or delete; returns whether one was deleted.
+fn views_interactive(manager: &mut TaskManager, console: &mut Console) -> Result<bool, TaskError> {
+    console.hint("\n--- Saved Views ---")?;
+    if manager.views.is_empty() {
+        writeln!(
+            console.output,
+            "No saved views. Use \"Filter Tasks\" to create one."
+        )?;
+        return Ok(false);
+    }
+    let names: Vec<String> = manager.views.keys().cloned().collect();
+    for (i, name) in names.iter().enumerate() {
+        let filter = &manager.views[name];
+        console.hint(&format!(
+            "{}. {}: {} ({} matching)",
+            i + 1,
+            name,
+            filter,
+            manager.filter_by(filter).len()
+        ))?;
+    }
+
+    let input =
+        console.prompt("Enter a number to run, d<number> to delete, or Enter to go back: ")?;
+    if input.is_empty() {
+        return Ok(false);
+    }
+    let (delete, number) = match input.strip_prefix('d') {
+        Some(number) => (true, number.trim()),
+        None => (false, input.as_str()),
     };
+    let name = number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|i| names.get(i))
+        .ok_or_else(|| TaskError::InvalidInput(format!("no view number '{}'", number)))?;
+    if delete {
+        manager.delete_view(name)?;
+        writeln!(console.output, "View '{}' deleted.", name)?;
+        Ok(true)
+    } else {
+        writeln!(console.output, "\n--- {} ---", name)?;
+        print_tasks(&manager.filter_by(&manager.views[name]), console)?;
+        Ok(false)
+    }
+}
+
+// Adds, renames, merges and deletes categories and sets their colors and
+// descriptions; returns whether anything changed.
+fn categories_interactive(
+    manager: &mut TaskManager,
+    console: &mut Console,
+) -> Result<bool, TaskError> {
+    console.hint("\n--- Categories ---")?;
+    let mut changed = false;
+    loop {
+        for (i, category) in manager.categories.iter().enumerate() {
+            let name = match category.color {
+                Some(color) if console.color => color.paint(&category.name),
+                _ => category.name.clone(),
+            };
+            let count = manager.category_count(&category.name);
+            let tasks = if count == 1 { "task" } else { "tasks" };
+            let mut line = format!("{}. {} ({} {})", i + 1, name, count, tasks);
+            if let Some(color) = category.color {
+                line.push_str(&format!(" [{}]", color.name()));
+            }
+            if let Some(description) = &category.description {
+                line.push_str(&format!(" - {}", description));
+            }
+            console.hint(&line)?;
+        }
+        console.hint("a=add, r=rename, m=merge, d=delete, c=color, i=description, Enter=done")?;
+        match console.prompt("Choose: ")?.as_str() {
+            "" => return Ok(changed),
+            "a" => {
+                let name = console.prompt("New category: ")?;
+                if let Some(name) = console.recover(manager.add_category(&name).map(Some))? {
+                    writeln!(console.output, "Category '{}' added.", name)?;
+                    changed = true;
+                }
+            }
+            "r" => {
+                let category = console.prompt("Category to rename: ")?;
+                let new_name = console.prompt("New name: ")?;
+                if console.recover(manager.rename_category(&category, &new_name).map(|_| true))? {
+                    writeln!(console.output, "Category renamed.")?;
+                    changed = true;
+                }
+            }
+            "m" => {
+                let from = console.prompt("Merge category: ")?;
+                let into = console.prompt("Into category: ")?;
+                if let Some(moved) =
+                    console.recover(manager.merge_categories(&from, &into).map(Some))?
+                {
+                    writeln!(
+                        console.output,
+                        "Categories merged; {} task(s) moved.",
+                        moved
+                    )?;
+                    changed = true;
+                }
+            }
+            "d" => {
+                let input = console.prompt("Category to delete: ")?;
+                let found = manager.find_category(&input).map(|c| Some(c.name.clone()));
+                let Some(name) = console.recover(found)? else {
+                    continue;
+                };
+                let count = manager.category_count(&name);
+                let move_to = if count > 0 {
+                    Some(console.prompt(&format!("Move its {} task(s) to: ", count))?)
+                } else {
+                    None
+                };
+                let deleted = manager.delete_category(&name, move_to.as_deref());
+                if let Some(moved) = console.recover(deleted.map(Some))? {
+                    writeln!(
+                        console.output,
+                        "Category '{}' deleted; {} task(s) moved.",
+                        name, moved
+                    )?;
+                    changed = true;
+                }
+            }
+            "c" => {
+                let category = console.prompt("Category: ")?;
+                let names: Vec<&str> = Color::ALL.iter().map(|c| c.name()).collect();
+                let color =
+                    console.prompt(&format!("Color ({}; '-' for none): ", names.join(", ")))?;
+                let color = match color.as_str() {
+                    "-" => Ok(None),
+                    color => color.parse().map(Some),
+                };
+                let set = color.and_then(|color| manager.set_category_color(&category, color));
+                if console.recover(set.map(|_| true))? {
+                    writeln!(console.output, "Color set.")?;
+                    changed = true;
+                }
+            }
+            "i" => {
+                let category = console.prompt("Category: ")?;
+                let description = console.prompt("Description ('-' for none): ")?;
+                let description = Some(description.as_str()).filter(|d| *d != "-");
+                let set = manager.set_category_description(&category, description);
+                if console.recover(set.map(|_| true))? {
+                    writeln!(console.output, "Description set.")?;
+                    changed = true;
+                }
+            }
+            other => console.recover(Err(TaskError::InvalidInput(format!(
+                "unknown category option '{}'",
+                other
+            ))))?,
+        }
+    }
+}
 
-    let tasks: Vec<&Task> = manager.filter_by(category);
-    println!("\n--- {} Tasks ---", category);
+fn print_tasks(tasks: &[&Task], console: &mut Console) -> io::Result<()> {
+    if tasks.is_empty() {
+        writeln!(console.output, "No tasks found.")?;
+    }
     for task in tasks {
-        println!("{}", task);
+        writeln!(console.output, "{}", task)?;
     }
+    Ok(())
 }
 
-fn show_statistics(manager: &TaskManager) {
+fn show_statistics(manager: &TaskManager, console: &mut Console) -> Result<bool, TaskError> {
     let stats = manager.statistics();
+    writeln!(console.output, "\n--- Statistics ---")?;
+    write!(console.output, "{}", stats.report(board::terminal_width()))?;
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    // Adds #1 "Fix login bug" (Urgent, Work, with a description),
+    // #2 "Read chapter 10" (Low, Learning) and #3 "Gym", whose blank answers
+    // take the defaults (Medium, Work).
+    const SETUP: &str = "\
+1\nFix login bug\n4\n1\nBefore the release\n\
+1\nRead chapter 10\n1\n3\n\n\
+1\nGym\n\n\n\n";
+
+    fn temp_path() -> String {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        env::temp_dir()
+            .join(format!("day14-menu-{}-{}.txt", process::id(), n))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    fn settings(path: &str) -> Settings {
+        Settings {
+            path: path.to_string(),
+            autosave: false,
+            wip_limit: 1,
+        }
+    }
+
+    // Runs `script` in batch mode, as `--batch` does. Returns the output, or
+    // the error and the line of the script it stopped at.
+    fn run_script(manager: &mut TaskManager, script: &str) -> Result<String, (TaskError, usize)> {
+        let path = temp_path();
+        let mut output = Vec::new();
+        let mut console = Console::new(Cursor::new(script.to_string()), &mut output, true);
+        let result = run(manager, &settings(&path), &mut console);
+        let line = console.line;
+        drop(console);
+        let _ = fs::remove_file(&path);
+        result
+            .map(|()| String::from_utf8(output).unwrap())
+            .map_err(|e| (e, line))
+    }
+
+    // A manager with the SETUP tasks, and the output of the given script.
+    fn after(script: &str) -> (TaskManager, String) {
+        let mut manager = TaskManager::new();
+        run_script(&mut manager, SETUP).unwrap();
+        let output = run_script(&mut manager, script).unwrap();
+        (manager, output)
+    }
+
+    fn fails(script: &str) -> (TaskManager, TaskError, usize) {
+        let mut manager = TaskManager::new();
+        run_script(&mut manager, SETUP).unwrap();
+        let (error, line) = run_script(&mut manager, script).unwrap_err();
+        (manager, error, line)
+    }
+
+    #[test]
+    fn adds_tasks() {
+        let mut manager = TaskManager::new();
+        let output = run_script(&mut manager, SETUP).unwrap();
+        assert_eq!(
+            output,
+            "Task #1 added successfully!\n  Before the release\n\
+             Task #2 added successfully!\nTask #3 added successfully!\n"
+        );
+        let fix = &manager.tasks[&1];
+        assert_eq!(
+            (fix.priority, fix.category.as_str()),
+            (Priority::Urgent, "Work")
+        );
+        assert_eq!(fix.description.as_deref(), Some("Before the release"));
+        let gym = &manager.tasks[&3];
+        assert_eq!(
+            (gym.priority, gym.category.as_str()),
+            (Priority::Medium, "Work")
+        );
+        assert_eq!(gym.description, None);
+
+        // Choices can be given by name too.
+        run_script(&mut manager, "1\nSwim\nhigh\nhealth\n\n").unwrap();
+        let swim = &manager.tasks[&4];
+        assert_eq!(
+            (swim.priority, swim.category.as_str()),
+            (Priority::High, "Health")
+        );
+    }
+
+    #[test]
+    fn add_stops_at_an_unknown_priority_or_category() {
+        let (manager, error, line) = fails("1\nTypo\n9\n1\n\n");
+        assert!(matches!(error, TaskError::InvalidInput(_)), "{}", error);
+        assert_eq!(line, 3);
+        assert_eq!(manager.count(), 3);
+
+        let (manager, error, line) = fails("\n1\nTypo\n2\nNowhere\n\n");
+        assert!(error.to_string().contains("Nowhere"), "{}", error);
+        assert_eq!(line, 5);
+        assert_eq!(manager.count(), 3);
+    }
 
-    println!("\n--- Statistics ---");
-    println!("Total tasks: {}", stats.total);
-    println!("Completed: {}", stats.completed);
-    println!("In Progress: {}", stats.in_progress);
-    println!("Pending: {}", stats.pending);
+    #[test]
+    fn interactive_add_falls_back_to_the_defaults() {
+        let mut manager = TaskManager::new();
+        let mut output = Vec::new();
+        let mut console = Console::new(Cursor::new("1\nTypo\n9\nNowhere\n\n"), &mut output, false);
+        run(&mut manager, &settings(&temp_path()), &mut console).unwrap();
+        drop(console);
+        let task = &manager.tasks[&1];
+        assert_eq!(
+            (task.priority, task.category.as_str()),
+            (Priority::Medium, "Work")
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("--- Task Manager Menu ---"));
+        assert!(output.contains("Goodbye!"));
+    }
+
+    #[test]
+    fn interactive_errors_are_reported_and_the_menu_goes_on() {
+        let mut manager = TaskManager::new();
+        let mut output = Vec::new();
+        let mut console = Console::new(Cursor::new("3\n99\n42\n8\n"), &mut output, false);
+        run(&mut manager, &settings(&temp_path()), &mut console).unwrap();
+        drop(console);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Error: Task #99 not found"), "{}", output);
+        assert!(output.contains("Error: Invalid input: unknown option '42'"));
+    }
+
+    #[test]
+    fn lists_tasks() {
+        let (_, output) = after("2\n");
+        assert_eq!(
+            output,
+            "\n--- All Tasks ---\n\
+             [ ] #1: Fix login bug [URGENT] [Work]\n      Before the release\n\
+             [ ] #2: Read chapter 10 [Low] [Learning]\n\
+             [ ] #3: Gym [Medium] [Work]\n"
+        );
+        let mut empty = TaskManager::new();
+        assert_eq!(
+            run_script(&mut empty, "2\n").unwrap(),
+            "\n--- All Tasks ---\nNo tasks found.\n"
+        );
+    }
+
+    #[test]
+    fn completes_tasks() {
+        let (manager, output) = after("3\n2\n");
+        assert_eq!(output, "Task #2 completed!\n");
+        assert_eq!(manager.tasks[&2].status, Status::Completed);
+
+        let (_, error, line) = fails("3\n2\n3\n2\n");
+        assert!(matches!(error, TaskError::AlreadyCompleted(2)));
+        assert_eq!(line, 4);
+    }
+
+    #[test]
+    fn deletes_tasks() {
+        let (manager, output) = after("4\n3\n");
+        assert_eq!(output, "Deleted: #3: Gym (Pending)\n");
+        assert_eq!(manager.count(), 2);
+
+        let (_, error, _) = fails("4\n9\n");
+        assert!(matches!(error, TaskError::NotFound(9)));
+        let (_, error, _) = fails("4\nthree\n");
+        assert!(matches!(error, TaskError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn filters_by_priority_and_category() {
+        let (_, output) = after("5\n4\n");
+        assert_eq!(
+            output,
+            "\n--- URGENT Priority Tasks ---\n\
+             [ ] #1: Fix login bug [URGENT] [Work]\n      Before the release\n"
+        );
+        let (_, output) = after("6\nwork\n");
+        assert!(output.starts_with("\n--- Work Tasks ---\n[ ] #1"));
+        assert!(output.contains("#3: Gym"));
+        assert!(!output.contains("#2"));
+
+        assert!(fails("5\n7\n").1.to_string().contains("priority"));
+        assert!(fails("6\nGarden\n").1.to_string().contains("Garden"));
+    }
+
+    #[test]
+    fn shows_statistics() {
+        let (_, output) = after("3\n1\n7\n");
+        assert!(
+            output.contains("Total tasks: 3\nCompleted: 1\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("Completion rate: 33.3%"));
+        assert!(output.contains("\nBy Priority:\n"));
+        assert!(output.contains("\nBy Category:\n"));
+        assert!(output.contains("\nCompletion by Category:\n"));
+    }
+
+    #[test]
+    fn edits_tasks() {
+        let (manager, output) = after("9\n3\nSwim\nAt the pool\nhigh\nhealth\n2\n");
+        assert_eq!(
+            output,
+            "Task #3 updated:\n[~] #3: Swim [High] [Health]\n      At the pool\n"
+        );
+        let (manager, output) = {
+            let mut manager = manager;
+            let output = run_script(&mut manager, "9\n3\n\n-\n\n\n\n").unwrap();
+            (manager, output)
+        };
+        assert!(output.starts_with("Task #3 updated:"));
+        assert_eq!(manager.tasks[&3].description, None);
+
+        let (_, output) = after("9\n2\n\n\n\n\n\n");
+        assert_eq!(output, "No changes made.\n");
+        let (manager, error, line) = fails("9\n2\nNew title\n\nextreme\n\n\n");
+        assert!(error.to_string().contains("extreme"), "{}", error);
+        assert_eq!(line, 5);
+        assert_eq!(manager.tasks[&2].title, "New title");
+    }
+
+    #[test]
+    fn starts_tasks_and_warns_over_the_wip_limit() {
+        let (manager, output) = after("10\n1\n10\n2\n");
+        assert_eq!(
+            output,
+            "Task #1 started!\nTask #2 started!\n\
+             Warning: 2 tasks are in progress, over the WIP limit of 1.\n"
+        );
+        assert_eq!(manager.tasks[&2].status, Status::InProgress);
+        assert!(matches!(
+            fails("3\n1\n10\n1\n").1,
+            TaskError::AlreadyCompleted(1)
+        ));
+    }
+
+    #[test]
+    fn shows_the_board() {
+        let (_, output) = after("10\n2\n11\n");
+        assert!(output.contains("\n--- Board ---\n"));
+        assert!(output.contains("Pending (2)"), "{}", output);
+        assert!(output.contains("In Progress (1/1)"));
+        assert!(output.contains("Completed (0)"));
+        assert!(output.contains("#2 Read chapter 10"));
+    }
+
+    #[test]
+    fn builds_runs_and_deletes_views() {
+        let script = "12\na\npriority:urgent\no\ncategory:learning\nn\nn\ns\nv\nmine\n\n\
+                      13\n1\n13\nd1\n13\n";
+        let (manager, output) = after(script);
+        assert_eq!(
+            output,
+            "[ ] #1: Fix login bug [URGENT] [Work]\n      Before the release\n\
+             [ ] #2: Read chapter 10 [Low] [Learning]\n\
+             View 'mine' saved.\n\
+             \n--- mine ---\n\
+             [ ] #1: Fix login bug [URGENT] [Work]\n      Before the release\n\
+             [ ] #2: Read chapter 10 [Low] [Learning]\n\
+             View 'mine' deleted.\n\
+             No saved views. Use \"Filter Tasks\" to create one.\n"
+        );
+        assert!(manager.views.is_empty());
+
+        let (_, error, line) = fails("12\na\npriority:\n");
+        assert!(matches!(error, TaskError::InvalidInput(_)));
+        assert_eq!(line, 3);
+        assert!(fails("12\nv\n").1.to_string().contains("condition"));
+    }
 
-    if stats.total > 0 {
-        let completion_rate = (stats.completed as f64 / stats.total as f64) * 100.0;
-        println!("Completion rate: {:.1}%", completion_rate);
+    #[test]
+    fn manages_categories() {
+        let script = "14\na\nSide Project\nr\nwork\nJob\nc\njob\nred\ni\njob\nPaid work\n\
+                      m\nlearning\njob\nd\nhealth\nd\nside project\n\n";
+        let (manager, output) = after(script);
+        assert_eq!(
+            output,
+            "Category 'Side Project' added.\nCategory renamed.\nColor set.\nDescription set.\n\
+             Categories merged; 1 task(s) moved.\nCategory 'Health' deleted; 0 task(s) moved.\n\
+             Category 'Side Project' deleted; 0 task(s) moved.\n"
+        );
+        let names: Vec<&str> = manager.categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Job", "Personal", "Finance"]);
+        assert!(manager.tasks.values().all(|t| t.category == "Job"));
+        let job = &manager.categories[0];
+        assert_eq!(job.color, Some(categories::Color::Red));
+        assert_eq!(job.description.as_deref(), Some("Paid work"));
+
+        // A category in use needs somewhere to move its tasks.
+        let (manager, output) = after("14\nd\nwork\npersonal\n\n");
+        assert_eq!(output, "Category 'Work' deleted; 2 task(s) moved.\n");
+        assert_eq!(manager.tasks[&3].category, "Personal");
+        let (_, error, line) = fails("14\nd\nwork\nwork\n\n");
+        assert!(matches!(error, TaskError::InvalidInput(_)));
+        assert_eq!(line, 4);
+        assert!(fails("14\na\n42\n").1.to_string().contains("number"));
     }
 
-    println!("\nBy Priority:");
-    for (priority, count) in &stats.by_priority {
-        println!("  {}: {}", priority, count);
+    #[test]
+    fn skips_comments_and_stops_at_unknown_options() {
+        let (_, error, line) = fails("# a comment\n\n   \n2\n# another\n42\n2\n");
+        assert!(
+            error.to_string().contains("unknown option '42'"),
+            "{}",
+            error
+        );
+        assert_eq!(line, 6);
     }
 
-    println!("\nBy Category:");
-    for (category, count) in &stats.by_category {
-        println!("  {}: {}", category, count);
+    #[test]
+    fn exit_saves_and_stops_reading() {
+        let path = temp_path();
+        let mut manager = TaskManager::new();
+        let mut output = Vec::new();
+        let script = format!("{}8\n2\n", SETUP);
+        let mut console = Console::new(Cursor::new(script), &mut output, true);
+        run(&mut manager, &settings(&path), &mut console).unwrap();
+        drop(console);
+        assert!(!String::from_utf8(output).unwrap().contains("All Tasks"));
+        let saved = storage::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.tasks, manager.tasks);
+
+        // Nothing changed, nothing written.
+        let path = temp_path();
+        let mut console = Console::new(Cursor::new("2\n"), io::sink(), true);
+        run(&mut manager, &settings(&path), &mut console).unwrap();
+        assert!(fs::metadata(&path).is_err());
     }
 }
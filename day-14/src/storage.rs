@@ -0,0 +1,430 @@
+// Saving the task manager to a data file and loading it on the next run.
+//
+//   #tasks|<format version>
+//   #next_id|<id the next new task gets>
+//   #category|name|color|description                   (one line per category)
+//   id|title|description|priority|category|status      (one line per task)
+//   #view|name|filter expression                        (one line per view)
+//
+// An empty description field means no description. Backslashes, '|' and line
+// breaks inside the title, description and view fields are escaped.
+// Version 2 added the views and version 3 the categories; older files get the
+// default categories, plus any other category their tasks use.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+
+use crate::categories::Category;
+use crate::filter::Filter;
+use crate::{Task, TaskError, TaskManager};
+
+const FORMAT: u32 = 3;
+
+impl TaskManager {
+    pub(crate) fn to_text(&self) -> String {
+        let mut out = format!("#tasks|{}\n#next_id|{}\n", FORMAT, self.next_id);
+        for category in &self.categories {
+            out.push_str(&format!(
+                "#category|{}|{}|{}\n",
+                escape(&category.name),
+                category.color.map(|c| c.name()).unwrap_or_default(),
+                category
+                    .description
+                    .as_deref()
+                    .map(escape)
+                    .unwrap_or_default()
+            ));
+        }
+        let mut tasks: Vec<&Task> = self.tasks.values().collect();
+        tasks.sort_by_key(|t| t.id);
+        for task in tasks {
+            out.push_str(&format!(
+                "{}|{}|{}|{}|{}|{}\n",
+                task.id,
+                escape(&task.title),
+                task.description.as_deref().map(escape).unwrap_or_default(),
+                task.priority.name(),
+                escape(&task.category),
+                task.status.name()
+            ));
+        }
+        for (name, filter) in &self.views {
+            out.push_str(&format!(
+                "#view|{}|{}\n",
+                escape(name),
+                escape(&filter.to_string())
+            ));
+        }
+        out
+    }
+
+    // Reads as much of `text` as possible. Returns the tasks that could be
+    // read and a description of every line that could not.
+    pub(crate) fn from_text(text: &str) -> (TaskManager, Vec<String>) {
+        let mut manager = TaskManager::new();
+        let mut problems = Vec::new();
+        let mut has_categories = false;
+        for (n, line) in text.lines().enumerate() {
+            let line_no = n + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(version) = line.strip_prefix("#tasks|") {
+                if !version.parse().is_ok_and(|v| (1..=FORMAT).contains(&v)) {
+                    problems.push(format!(
+                        "line {}: unknown file format '{}'",
+                        line_no, version
+                    ));
+                }
+                continue;
+            }
+            if let Some(next_id) = line.strip_prefix("#next_id|") {
+                match next_id.parse::<u32>() {
+                    Ok(id) => manager.next_id = manager.next_id.max(id),
+                    Err(_) => problems.push(format!("line {}: bad next id '{}'", line_no, next_id)),
+                }
+                continue;
+            }
+            if let Some(category) = line.strip_prefix("#category|") {
+                // The file's categories replace the defaults.
+                if !has_categories {
+                    manager.categories.clear();
+                    has_categories = true;
+                }
+                match parse_category(category) {
+                    Ok(category) if manager.categories.iter().any(|c| c.is(&category.name)) => {
+                        problems.push(format!(
+                            "line {}: duplicate category '{}'",
+                            line_no, category.name
+                        ));
+                    }
+                    Ok(category) => manager.categories.push(category),
+                    Err(e) => problems.push(format!("line {}: {}", line_no, e)),
+                }
+                continue;
+            }
+            if let Some(view) = line.strip_prefix("#view|") {
+                match parse_view(view) {
+                    Ok((name, filter)) => {
+                        manager.views.insert(name, filter);
+                    }
+                    Err(e) => problems.push(format!("line {}: {}", line_no, e)),
+                }
+                continue;
+            }
+            match parse_task(line) {
+                Ok(task) if manager.tasks.contains_key(&task.id) => {
+                    problems.push(format!("line {}: duplicate task #{}", line_no, task.id));
+                }
+                Ok(task) => {
+                    manager.next_id = manager.next_id.max(task.id + 1);
+                    manager.tasks.insert(task.id, task);
+                }
+                Err(e) => problems.push(format!("line {}: {}", line_no, e)),
+            }
+        }
+        if manager.categories.is_empty() {
+            manager.categories = crate::categories::default_categories();
+        }
+        // A task keeps a category the file does not list.
+        let mut ids: Vec<u32> = manager.tasks.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            let name = manager.tasks[&id].category.clone();
+            match manager.categories.iter().find(|c| c.is(&name)) {
+                Some(category) => {
+                    manager.tasks.get_mut(&id).unwrap().category = category.name.clone()
+                }
+                None => manager.categories.push(Category::new(&name)),
+            }
+        }
+        (manager, problems)
+    }
+
+    // Writes to a temporary file first, so a crash while saving cannot leave
+    // a half-written data file behind.
+    pub(crate) fn save(&self, path: &str) -> Result<(), TaskError> {
+        let tmp = format!("{}.tmp", path);
+        let mut file = File::create(&tmp)?;
+        file.write_all(self.to_text().as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+// Loads the manager saved at `path`; a missing file means no tasks yet.
+// A damaged file is copied to "<path>.corrupt" before anything else can
+// overwrite it, and the tasks that could still be read are kept.
+pub(crate) fn open(path: &str) -> Result<TaskManager, TaskError> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(TaskManager::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let (manager, mut problems) = TaskManager::from_text(&String::from_utf8_lossy(&data));
+    if std::str::from_utf8(&data).is_err() {
+        problems.insert(0, "the file is not valid UTF-8".to_string());
+    }
+    if !problems.is_empty() {
+        let backup = format!("{}.corrupt", path);
+        fs::copy(path, &backup)?;
+        println!(
+            "Warning: {} is damaged; the original was kept as {}",
+            path, backup
+        );
+        for problem in &problems {
+            println!("  {}", problem);
+        }
+        println!("Recovered {} task(s).", manager.count());
+    }
+    Ok(manager)
+}
+
+fn parse_task(line: &str) -> Result<Task, TaskError> {
+    let fields: Vec<&str> = line.split('|').collect();
+    if fields.len() != 6 {
+        return Err(TaskError::InvalidInput(format!(
+            "expected 6 fields, found {}",
+            fields.len()
+        )));
+    }
+    let id = fields[0]
+        .parse()
+        .map_err(|_| TaskError::InvalidInput(format!("bad task id '{}'", fields[0])))?;
+    let category = unescape(fields[4]);
+    if category.trim().is_empty() {
+        return Err(TaskError::InvalidInput("missing category".to_string()));
+    }
+    let mut task = Task::new(id, unescape(fields[1]), fields[3].parse()?, category);
+    if !fields[2].is_empty() {
+        task = task.with_description(unescape(fields[2]));
+    }
+    task.status = fields[5].parse()?;
+    Ok(task)
+}
+
+fn parse_category(fields: &str) -> Result<Category, TaskError> {
+    let fields: Vec<&str> = fields.split('|').collect();
+    if fields.len() != 3 || fields[0].trim().is_empty() {
+        return Err(TaskError::InvalidInput(
+            "expected a category name, color and description".to_string(),
+        ));
+    }
+    let mut category = Category::new(&unescape(fields[0]));
+    if !fields[1].is_empty() {
+        category.color = Some(fields[1].parse()?);
+    }
+    if !fields[2].is_empty() {
+        category.description = Some(unescape(fields[2]));
+    }
+    Ok(category)
+}
+
+fn parse_view(fields: &str) -> Result<(String, Filter), TaskError> {
+    let Some((name, expression)) = fields.split_once('|') else {
+        return Err(TaskError::InvalidInput(
+            "expected a view name and a filter".to_string(),
+        ));
+    };
+    Ok((unescape(name), unescape(expression).parse()?))
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '|' => out.push_str("\\p"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('p') => out.push('|'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::categories::Color;
+    use crate::{Priority, Status};
+
+    const AWKWARD: &str = "pipe | backslash \\ \\p\nsecond line\r";
+
+    // A manager with a task for every priority and status, a category for
+    // every color, and views whose names and filters need escaping.
+    fn sample() -> TaskManager {
+        let mut manager = TaskManager::new();
+        manager.categories = Color::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| {
+                let mut category = Category::new(&format!("Category {} | {}", i, color.name()));
+                category.color = Some(color);
+                category.description = Some(format!("{} {}", AWKWARD, i));
+                category
+            })
+            .collect();
+        manager.categories.push(Category::new("Plain"));
+
+        let mut id = 1;
+        for priority in Priority::ALL {
+            for status in Status::ALL {
+                let category = &manager.categories[id as usize % manager.categories.len()];
+                let mut task = Task::new(
+                    id,
+                    format!("{} {}", AWKWARD, id),
+                    priority,
+                    category.name.clone(),
+                );
+                if id % 2 == 0 {
+                    task = task.with_description(AWKWARD.to_string());
+                }
+                task.status = status;
+                manager.tasks.insert(id, task);
+                id += 1;
+            }
+        }
+        // Ids of deleted tasks are not reused.
+        manager.next_id = id + 5;
+
+        for (name, expression) in [
+            ("urgent | open", "priority:urgent and not status:completed"),
+            (
+                "mixed",
+                "(category:\"Category 0 | red\" or text:\"a \\\\ b\") and status:in-progress",
+            ),
+        ] {
+            manager
+                .views
+                .insert(name.to_string(), expression.parse().unwrap());
+        }
+        manager
+    }
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("day14-{}-{}.txt", name, std::process::id()))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn every_priority_status_and_color_round_trips() {
+        let manager = sample();
+        let text = manager.to_text();
+        let (loaded, problems) = TaskManager::from_text(&text);
+        assert_eq!(problems, Vec::<String>::new());
+        assert_eq!(loaded.tasks, manager.tasks);
+        assert_eq!(loaded.categories, manager.categories);
+        assert_eq!(loaded.views, manager.views);
+        assert_eq!(loaded.next_id, manager.next_id);
+        assert_eq!(loaded.to_text(), text);
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        assert_eq!(unescape(&escape(AWKWARD)), AWKWARD);
+        assert!(!escape(AWKWARD).contains(['|', '\n', '\r']));
+    }
+
+    #[test]
+    fn older_files_get_the_default_categories() {
+        let text = "#tasks|2\n#next_id|4\n1|Report||High|Work|Pending\n\
+                    2|Dig||Low|garden|Completed\n3|Read||Medium|learning|InProgress\n";
+        let (manager, problems) = TaskManager::from_text(text);
+        assert!(problems.is_empty(), "{:?}", problems);
+        let names: Vec<&str> = manager.categories.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Work", "Personal", "Learning", "Health", "Finance", "garden"
+            ]
+        );
+        // Names are matched without regard to case.
+        assert_eq!(manager.tasks[&3].category, "Learning");
+        assert_eq!(manager.next_id, 4);
+    }
+
+    #[test]
+    fn reports_damaged_lines_and_keeps_the_rest() {
+        let text = "#tasks|9\n#next_id|x\n1|Good||High|Work|Pending\n\
+                    2|Too|few\n3|Bad||Extreme|Work|Pending\n1|Again||Low|Work|Pending\n\
+                    #category|Work|purple|\n#view|broken|priority:\n";
+        let (manager, problems) = TaskManager::from_text(text);
+        assert_eq!(manager.tasks.len(), 1);
+        assert_eq!(manager.tasks[&1].title, "Good");
+        let lines: Vec<&str> = problems
+            .iter()
+            .map(|p| p.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "line 1", "line 2", "line 4", "line 5", "line 6", "line 7", "line 8"
+            ]
+        );
+    }
+
+    #[test]
+    fn open_keeps_a_copy_of_a_damaged_file() {
+        let path = temp_path("corrupt");
+        let backup = format!("{}.corrupt", path);
+        let damaged = "#tasks|3\n#next_id|3\n1|Kept||High|Work|Pending\n2|Lost|\n";
+        fs::write(&path, damaged).unwrap();
+
+        let manager = open(&path).unwrap();
+        assert_eq!(manager.count(), 1);
+        assert_eq!(manager.tasks[&1].title, "Kept");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), damaged);
+        assert_eq!(fs::read_to_string(&path).unwrap(), damaged);
+        fs::remove_file(&backup).unwrap();
+
+        // Bytes that are not UTF-8 count as damage too.
+        fs::write(&path, b"1|Caf\xe9||Low|Work|Pending\n").unwrap();
+        let manager = open(&path).unwrap();
+        assert_eq!(manager.count(), 1);
+        assert!(fs::metadata(&backup).is_ok());
+        fs::remove_file(&backup).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn open_leaves_healthy_and_missing_files_alone() {
+        let path = temp_path("healthy");
+        let backup = format!("{}.corrupt", path);
+        assert_eq!(open(&path).unwrap().count(), 0);
+        assert!(fs::metadata(&path).is_err());
+
+        let manager = sample();
+        manager.save(&path).unwrap();
+        let loaded = open(&path).unwrap();
+        assert_eq!(loaded.tasks, manager.tasks);
+        assert!(fs::metadata(&backup).is_err());
+        assert!(fs::metadata(format!("{}.tmp", path)).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+// File attachments on tasks.
+// An attachment either references a file where it already is, or is a copy kept
+// in the app's data directory under <data dir>/<task uid>/.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::uid::TaskUid;
+use crate::{Task, TodoError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Attachment {
+    // Absolute path for references; path relative to the data directory for copies.
+    pub(crate) path: PathBuf,
+    pub(crate) copied: bool,
+}
+
+impl Attachment {
+    pub(crate) fn location(&self, data_dir: &Path) -> PathBuf {
+        if self.copied {
+            data_dir.join(&self.path)
+        } else {
+            self.path.clone()
+        }
+    }
+
+    // Serialized as "copy:<path>" or "ref:<path>".
+    pub(crate) fn encode(&self) -> String {
+        let kind = if self.copied { "copy" } else { "ref" };
+        format!("{}:{}", kind, self.path.display())
+    }
+
+    pub(crate) fn decode(s: &str) -> Result<Self, TodoError> {
+        match s.split_once(':') {
+            Some(("copy", path)) => Ok(Attachment {
+                path: PathBuf::from(path),
+                copied: true,
+            }),
+            Some(("ref", path)) => Ok(Attachment {
+                path: PathBuf::from(path),
+                copied: false,
+            }),
+            _ => Err(TodoError::InvalidInput(t!(
+                "error.bad_attachment",
+                value = s
+            ))),
+        }
+    }
+}
+
+// The data directory belonging to a todo file: "todos.txt" -> "todos.txt.files".
+pub(crate) fn data_dir(todo_file: &str) -> PathBuf {
+    PathBuf::from(format!("{}.files", todo_file))
+}
+
+// Prepares an attachment of `source` for `task`, copying the file into the
+// data directory if `copy` is set. The caller records it on the task.
+pub(crate) fn attach(
+    data_dir: &Path,
+    task: &Task,
+    source: &Path,
+    copy: bool,
+) -> Result<Attachment, TodoError> {
+    if !source.is_file() {
+        return Err(TodoError::InvalidInput(t!(
+            "error.not_a_file",
+            path = source.display()
+        )));
+    }
+
+    let attachment = if copy {
+        let name = source
+            .file_name()
+            .ok_or_else(|| TodoError::InvalidInput(t!("error.no_file_name")))?;
+        let task_dir = PathBuf::from(task.uid.to_string());
+        fs::create_dir_all(data_dir.join(&task_dir))?;
+        let relative = unique_name(data_dir, &task_dir, &name.to_string_lossy());
+        fs::copy(source, data_dir.join(&relative))?;
+        Attachment {
+            path: relative,
+            copied: true,
+        }
+    } else {
+        Attachment {
+            path: fs::canonicalize(source)?,
+            copied: false,
+        }
+    };
+    Ok(attachment)
+}
+
+// The attachment as it would belong to the task with `uid`: a copy is copied
+// into that task's directory, a reference stays as it is. A copy whose file
+// has gone missing is kept as it is too.
+pub(crate) fn copy_to(
+    data_dir: &Path,
+    attachment: &Attachment,
+    uid: TaskUid,
+) -> io::Result<Attachment> {
+    let source = attachment.location(data_dir);
+    let Some(name) = attachment.path.file_name() else {
+        return Ok(attachment.clone());
+    };
+    if !attachment.copied || !source.is_file() {
+        return Ok(attachment.clone());
+    }
+    let task_dir = PathBuf::from(uid.to_string());
+    fs::create_dir_all(data_dir.join(&task_dir))?;
+    let relative = unique_name(data_dir, &task_dir, &name.to_string_lossy());
+    fs::copy(&source, data_dir.join(&relative))?;
+    Ok(Attachment {
+        path: relative,
+        copied: true,
+    })
+}
+
+// Picks "name", "2-name", "3-name", ... so copies never overwrite each other.
+fn unique_name(data_dir: &Path, task_dir: &Path, name: &str) -> PathBuf {
+    let mut candidate = task_dir.join(name);
+    let mut n = 2;
+    while data_dir.join(&candidate).exists() {
+        candidate = task_dir.join(format!("{}-{}", n, name));
+        n += 1;
+    }
+    candidate
+}
+
+// Removes the copy of an attachment; references are left untouched.
+pub(crate) fn remove_file(data_dir: &Path, attachment: &Attachment) -> io::Result<()> {
+    if attachment.copied {
+        match fs::remove_file(attachment.location(data_dir)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Removes every copied attachment of a deleted task, and its directory.
+pub(crate) fn remove_task_files(data_dir: &Path, task: &Task) -> io::Result<()> {
+    for attachment in &task.attachments {
+        remove_file(data_dir, attachment)?;
+    }
+    remove_task_dir(data_dir, task.uid)
+}
+
+// Removes the directory of the task with `uid` if nothing is left in it.
+fn remove_task_dir(data_dir: &Path, uid: TaskUid) -> io::Result<()> {
+    let task_dir = data_dir.join(uid.to_string());
+    if task_dir.is_dir() && fs::read_dir(&task_dir)?.next().is_none() {
+        fs::remove_dir(&task_dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn only_empty_task_directories_are_removed() {
+        let data_dir = env::temp_dir().join(format!("day21-attach-{}", process::id()));
+        let (empty, used) = (TaskUid::generate(), TaskUid::generate());
+        fs::create_dir_all(data_dir.join(empty.to_string())).unwrap();
+        fs::create_dir_all(data_dir.join(used.to_string())).unwrap();
+        fs::write(data_dir.join(used.to_string()).join("note.txt"), "hi").unwrap();
+
+        remove_task_dir(&data_dir, empty).unwrap();
+        remove_task_dir(&data_dir, used).unwrap();
+        // A task that never had a directory is fine too.
+        remove_task_dir(&data_dir, TaskUid::generate()).unwrap();
+        let left = data_dir.join(used.to_string()).join("note.txt").is_file();
+        let gone = !data_dir.join(empty.to_string()).exists();
+        fs::remove_dir_all(&data_dir).unwrap();
+        assert!(left);
+        assert!(gone);
+    }
+}
@@ -0,0 +1,385 @@
+// Benchmark harness for large task lists.
+// `bench [--tasks N]` generates N tasks (a million by default), loads them the
+// way a todo file is loaded, and times listing, search, statistics and
+// selection through the indexes against a plain scan of every task. Each row
+// times the code its command runs, short of printing the result.
+// It never touches the todo file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::cli::take_option;
+use crate::events::{self, Event, EventKind};
+use crate::filter::TaskFilter;
+use crate::{Priority, Status, Task, TodoApp, TodoError};
+
+const DEFAULT_TASKS: u32 = 1_000_000;
+// Fast operations are repeated and the best time is reported, which hides
+// one-off effects such as page faults on first use.
+const RUNS: usize = 5;
+
+const VERBS: &[&str] = &[
+    "Review", "Write", "Update", "Plan", "Fix", "Test", "Call", "Book", "Clean", "Prepare",
+];
+const NOUNS: &[&str] = &[
+    "report",
+    "budget",
+    "slides",
+    "invoice",
+    "roadmap",
+    "backlog",
+    "newsletter",
+    "contract",
+    "survey",
+    "website",
+    "release",
+    "workshop",
+];
+const PLACES: &[&str] = &[
+    "Berlin", "Lisbon", "Nairobi", "Osaka", "Toronto", "Lima", "Oslo", "Delhi",
+];
+const TAGS: &[&str] = &["work", "home", "errands", "health", "finance", "learning"];
+// One task in RARE_EVERY mentions this word, to time a selective search.
+const RARE_WORD: &str = "escalation";
+const RARE_EVERY: u32 = 50_000;
+// The generated tasks are added over this many seconds before now (3 years).
+const HISTORY: u64 = 3 * 365 * 86_400;
+const DAY: u64 = 86_400;
+
+pub(crate) fn run(mut args: Vec<String>) -> Result<(), TodoError> {
+    let count = match take_option(&mut args, "--tasks")? {
+        Some(n) => n
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| TodoError::InvalidInput(t!("error.bad_task_count", value = n)))?,
+        None => DEFAULT_TASKS,
+    };
+
+    let now = events::now();
+    let start = Instant::now();
+    let text = generate(count, now).to_text();
+    println!(
+        "{}",
+        t!(
+            "bench.generated",
+            count = count,
+            time = millis(start.elapsed())
+        )
+    );
+
+    let start = Instant::now();
+    let app = TodoApp::from_text(&text, "bench")?;
+    drop(text);
+    println!("{}", t!("bench.loaded", time = millis(start.elapsed())));
+
+    println!(
+        "\n{:<36} {:>9} {:>12} {:>12}",
+        t!("bench.operation"),
+        t!("bench.results"),
+        t!("bench.indexed"),
+        t!("bench.full_scan")
+    );
+
+    report(
+        "list",
+        || app.task_lines(app.tasks.values().collect()).len(),
+        None::<fn() -> usize>,
+    );
+    report(
+        "list --status in-progress",
+        || list_status(&app),
+        Some(|| scan_list_status(&app)),
+    );
+    for query in [RARE_WORD, "newsletter osaka", "slid"] {
+        report(
+            &format!("search '{}'", query),
+            || search(&app, query),
+            Some(|| scan_search(&app, query)),
+        );
+    }
+    report(
+        "stats",
+        || app.stats(now).all.total,
+        Some(|| scan_stats(&app)),
+    );
+    let filter = TaskFilter {
+        tag: Some("finance".to_string()),
+        priority: Some(Priority::High),
+        ..Default::default()
+    };
+    report(
+        "bulk --tag finance --priority high",
+        || app.select(&filter).len(),
+        Some(|| {
+            let mut ids: Vec<u32> = app
+                .tasks
+                .values()
+                .filter(|t| filter.matches(t))
+                .map(|t| t.id)
+                .collect();
+            ids.sort_unstable();
+            ids.len()
+        }),
+    );
+    let prefix = app.tasks[&(count / 2 + 1)].uid.hex()[..8].to_string();
+    report(
+        &format!("complete {}", prefix),
+        || app.resolve(&prefix).map_or(0, |_| 1),
+        Some(|| {
+            app.tasks
+                .values()
+                .filter(|t| t.uid.hex().starts_with(&prefix))
+                .count()
+        }),
+    );
+    Ok(())
+}
+
+// Builds `count` tasks with varied titles, tags, priorities and statuses the
+// way a real list comes about: each task is added by an event in the log,
+// spread over the HISTORY before `now`, and those that are not pending are
+// started or completed by a later one.
+// A fixed-seed generator makes every run produce the same list.
+fn generate(count: u32, now: u64) -> TodoApp {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |n: usize| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) as usize % n
+    };
+
+    let start = now.saturating_sub(HISTORY);
+    let mut log = Vec::with_capacity(count as usize * 2);
+    for id in 1..=count {
+        let mut title = format!(
+            "{} {} {}",
+            VERBS[next(VERBS.len())],
+            NOUNS[next(NOUNS.len())],
+            PLACES[next(PLACES.len())]
+        );
+        if id % RARE_EVERY == 0 {
+            title = format!("{} {}", title, RARE_WORD);
+        }
+        let priority = [Priority::Low, Priority::Medium, Priority::High][next(3)];
+        let tags = vec![TAGS[next(TAGS.len())].to_string()];
+        let task = Task::new(id, &title, priority, tags);
+        let uid = task.uid;
+        let at = start + HISTORY * u64::from(id) / u64::from(count);
+        log.push(Event {
+            seq: 0,
+            at,
+            kind: EventKind::TaskAdded(task),
+        });
+        // Mostly finished work, as in a list that has been used for years.
+        let later = (at + next(30) as u64 * DAY).min(now);
+        match next(20) {
+            0 => log.push(Event {
+                seq: 0,
+                at: later,
+                kind: EventKind::StatusChanged(uid, Status::InProgress),
+            }),
+            1..=4 => {}
+            _ => log.push(Event {
+                seq: 0,
+                at: later,
+                kind: EventKind::TaskCompleted(uid),
+            }),
+        }
+    }
+    log.sort_by_key(|event| event.at);
+
+    let mut app = TodoApp::new();
+    let skipped = app.replay(log);
+    assert!(skipped.is_empty(), "generated events all apply");
+    app
+}
+
+// What `list --status in-progress` does.
+fn list_status(app: &TodoApp) -> usize {
+    app.task_lines(app.tasks_with_status(Status::InProgress))
+        .len()
+}
+
+// The same without the status index: check every task.
+fn scan_list_status(app: &TodoApp) -> usize {
+    let tasks = app
+        .tasks
+        .values()
+        .filter(|t| t.status == Status::InProgress)
+        .collect();
+    app.task_lines(tasks).len()
+}
+
+// What `search` does.
+fn search(app: &TodoApp, query: &str) -> usize {
+    let lines: Vec<String> = app
+        .search(query)
+        .into_iter()
+        .map(|t| t.render(Some(query)))
+        .collect();
+    lines.len()
+}
+
+// What `search` did before the word index: check every title.
+fn scan_search(app: &TodoApp, query: &str) -> usize {
+    let q = query.to_lowercase();
+    let mut results: Vec<&Task> = app
+        .tasks
+        .values()
+        .filter(|t| t.title.to_lowercase().contains(&q))
+        .collect();
+    app.sort.sort(&mut results);
+    let lines: Vec<String> = results.iter().map(|t| t.render(Some(query))).collect();
+    lines.len()
+}
+
+// What `stats` did before reading its counts from the index: count every task
+// by status, priority and tag.
+fn scan_stats(app: &TodoApp) -> usize {
+    let mut by_status: HashMap<Status, usize> = HashMap::new();
+    let mut by_priority: HashMap<(Priority, bool), usize> = HashMap::new();
+    let mut by_tag: BTreeMap<(String, bool), usize> = BTreeMap::new();
+    for task in app.tasks.values() {
+        let completed = task.status == Status::Completed;
+        *by_status.entry(task.status).or_default() += 1;
+        *by_priority.entry((task.priority, completed)).or_default() += 1;
+        for tag in &task.tags {
+            *by_tag.entry((tag.to_lowercase(), completed)).or_default() += 1;
+        }
+    }
+    by_status.values().sum()
+}
+
+fn report(label: &str, indexed: impl Fn() -> usize, scan: Option<impl Fn() -> usize>) {
+    let (results, indexed_time) = best_of(&indexed);
+    let scan_time = scan
+        .map(|scan| millis(best_of(&scan).1))
+        .unwrap_or_else(|| "-".to_string());
+    println!(
+        "{:<36} {:>9} {:>12} {:>12}",
+        label,
+        results,
+        millis(indexed_time),
+        scan_time
+    );
+}
+
+fn best_of(op: &impl Fn() -> usize) -> (usize, Duration) {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let results = op();
+            (results, start.elapsed())
+        })
+        .min_by_key(|&(_, time)| time)
+        .expect("at least one run")
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.1} ms", time.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASKS: u32 = 10_000;
+    const NOW: u64 = 1_790_000_000;
+
+    // A generated list, saved and loaded again like a todo file.
+    fn load() -> TodoApp {
+        TodoApp::from_text(&generate(TASKS, NOW).to_text(), "bench").unwrap()
+    }
+
+    #[test]
+    fn generated_lists_come_from_the_event_log() {
+        let app = load();
+        assert_eq!(app.tasks.len(), TASKS as usize);
+        let added = app
+            .log
+            .iter()
+            .filter(|e| matches!(e.kind, EventKind::TaskAdded(_)))
+            .count();
+        assert_eq!(added, TASKS as usize);
+        assert!(app.log.len() > added);
+        assert!(app.log.windows(2).all(|w| w[0].at <= w[1].at));
+        let completed = app.index.count(Status::Completed);
+        assert!(completed > TASKS as usize / 2, "{}", completed);
+        assert!(
+            app.tasks
+                .values()
+                .all(|t| app.index.created(t.uid).is_some())
+        );
+    }
+
+    #[test]
+    fn listing_and_search_only_look_at_candidates() {
+        let app = load();
+
+        let in_progress = app.index.count(Status::InProgress);
+        assert!(in_progress < TASKS as usize / 10, "{}", in_progress);
+        assert_eq!(list_status(&app), in_progress);
+        assert_eq!(scan_list_status(&app), in_progress);
+
+        for query in ["newsletter osaka", "slid", RARE_WORD] {
+            let candidates = app.index.search_candidates(query).unwrap();
+            let found = search(&app, query);
+            assert_eq!(found, scan_search(&app, query), "{}", query);
+            assert!(found <= candidates.len());
+            // The longest word of the query narrows the search to the titles
+            // with a word containing it.
+            assert!(
+                candidates.len() < TASKS as usize / 5,
+                "{}: {}",
+                query,
+                candidates.len()
+            );
+        }
+        assert!(app.index.search_candidates(RARE_WORD).unwrap().is_empty());
+
+        let prefix = &app.tasks[&(TASKS / 2)].uid.hex()[..8];
+        assert_eq!(app.index.ids_with_uid_prefix(prefix), [TASKS / 2]);
+    }
+
+    #[test]
+    fn stats_read_the_index_not_the_log() {
+        let mut app = load();
+        let before = app.stats(NOW);
+        let counts = (
+            before.all.total,
+            before.all.completed,
+            before.untagged.total,
+        );
+        let ages = (
+            before.average_open_age,
+            before
+                .oldest_open
+                .iter()
+                .map(|(t, _)| t.id)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(counts.0, scan_stats(&app));
+        assert!(ages.0.is_some());
+
+        // The same figures without the event log to scan.
+        app.log.clear();
+        let after = app.stats(NOW);
+        assert_eq!(
+            (after.all.total, after.all.completed, after.untagged.total),
+            counts
+        );
+        assert_eq!(
+            (
+                after.average_open_age,
+                after
+                    .oldest_open
+                    .iter()
+                    .map(|(t, _)| t.id)
+                    .collect::<Vec<_>>()
+            ),
+            ages
+        );
+    }
+}
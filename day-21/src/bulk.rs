@@ -0,0 +1,155 @@
+// Bulk operations: apply one action to every task selected by a filter.
+// The changes are recorded as one batch of events, committed only if every task succeeds.
+
+use std::fmt;
+
+use crate::events::EventKind;
+use crate::{Priority, Status, TodoApp, TodoError};
+
+#[derive(Debug, Clone)]
+pub(crate) enum BulkAction {
+    Complete,
+    Delete,
+    Retag {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    Reprioritize(Priority),
+    Move(Status),
+}
+
+impl fmt::Display for BulkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            BulkAction::Complete => t!("bulk.action.complete"),
+            BulkAction::Delete => t!("bulk.action.delete"),
+            BulkAction::Retag { add, remove } => {
+                let changes: Vec<String> = add
+                    .iter()
+                    .map(|t| format!("+{}", t))
+                    .chain(remove.iter().map(|t| format!("-{}", t)))
+                    .collect();
+                t!("bulk.action.retag", changes = changes.join(" "))
+            }
+            BulkAction::Reprioritize(priority) => {
+                t!("bulk.action.priority", priority = priority)
+            }
+            BulkAction::Move(status) => t!("bulk.action.move", status = status),
+        };
+        write!(f, "{}", description)
+    }
+}
+
+impl TodoApp {
+    // Applies `action` to every task in `ids` as a single transaction:
+    // if any id is missing, nothing is changed. Completing a task that is
+    // already completed is skipped. Returns the number of tasks changed and
+    // the ids of those skipped.
+    pub(crate) fn apply_bulk(
+        &mut self,
+        ids: &[u32],
+        action: &BulkAction,
+    ) -> Result<(usize, Vec<u32>), TodoError> {
+        let mut changes = Vec::with_capacity(ids.len());
+        let mut skipped = Vec::new();
+        for &id in ids {
+            let task = self.tasks.get(&id).ok_or(TodoError::TaskNotFound(id))?;
+            let uid = task.uid;
+            if matches!(action, BulkAction::Complete) && task.status == Status::Completed {
+                skipped.push(id);
+                continue;
+            }
+            changes.push(match action {
+                BulkAction::Delete => EventKind::TaskDeleted(uid),
+                BulkAction::Complete => EventKind::TaskCompleted(uid),
+                BulkAction::Move(status) => EventKind::StatusChanged(uid, *status),
+                BulkAction::Reprioritize(priority) => EventKind::PriorityChanged(uid, *priority),
+                BulkAction::Retag { add, remove } => {
+                    let mut tags = task.tags.clone();
+                    tags.retain(|t| !remove.iter().any(|r| r.eq_ignore_ascii_case(t)));
+                    for tag in add {
+                        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                            tags.push(tag.clone());
+                        }
+                    }
+                    EventKind::TagsChanged(uid, tags)
+                }
+            });
+        }
+
+        let count = changes.len();
+        self.record_all(changes)?;
+        Ok((count, skipped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> TodoApp {
+        let mut app = TodoApp::new();
+        app.add_task("First", Priority::Low, "a");
+        app.add_task("Second", Priority::Medium, "b");
+        app.add_task("Third", Priority::High, "A");
+        app
+    }
+
+    #[test]
+    fn missing_id_changes_nothing() {
+        let mut app = app();
+        let log = app.log.len();
+        let result = app.apply_bulk(&[1, 7, 2], &BulkAction::Delete);
+        assert!(matches!(result, Err(TodoError::TaskNotFound(7))));
+        assert_eq!(app.tasks.len(), 3);
+        assert_eq!(app.log.len(), log);
+    }
+
+    #[test]
+    fn completing_skips_completed_tasks() {
+        let mut app = app();
+        app.complete_task(2).unwrap();
+        let log = app.log.len();
+
+        let (count, skipped) = app.apply_bulk(&[1, 2, 3], &BulkAction::Complete).unwrap();
+        assert_eq!((count, skipped), (2, vec![2]));
+        assert_eq!(app.log.len(), log + 2);
+        assert!(app.tasks.values().all(|t| t.status == Status::Completed));
+
+        let (count, skipped) = app.apply_bulk(&[1, 3], &BulkAction::Complete).unwrap();
+        assert_eq!((count, skipped), (0, vec![1, 3]));
+        assert_eq!(app.log.len(), log + 2);
+    }
+
+    #[test]
+    fn retag_adds_and_removes_ignoring_case() {
+        let mut app = app();
+        let action = BulkAction::Retag {
+            add: vec!["urgent".to_string(), "B".to_string()],
+            remove: vec!["a".to_string()],
+        };
+        assert_eq!(app.apply_bulk(&[1, 2, 3], &action).unwrap(), (3, vec![]));
+        assert_eq!(app.tasks[&1].tags, ["urgent", "B"]);
+        assert_eq!(app.tasks[&2].tags, ["b", "urgent"]);
+        assert_eq!(app.tasks[&3].tags, ["urgent", "B"]);
+    }
+
+    #[test]
+    fn other_actions_apply_to_every_task() {
+        let mut app = app();
+        app.apply_bulk(&[1, 2], &BulkAction::Reprioritize(Priority::High))
+            .unwrap();
+        app.apply_bulk(&[2, 3], &BulkAction::Move(Status::InProgress))
+            .unwrap();
+        assert_eq!(app.tasks[&1].priority, Priority::High);
+        assert_eq!(app.tasks[&2].priority, Priority::High);
+        assert_eq!(app.tasks[&2].status, Status::InProgress);
+        assert_eq!(app.tasks[&1].status, Status::Pending);
+
+        assert_eq!(
+            app.apply_bulk(&[1, 3], &BulkAction::Delete).unwrap(),
+            (2, vec![])
+        );
+        assert_eq!(app.tasks.keys().copied().collect::<Vec<_>>(), vec![2]);
+    }
+}
@@ -0,0 +1,771 @@
+// Command-line interface for the todo app.
+// Running without arguments shows the demo; with arguments, commands operate
+// on the todo file named by TODO_FILE (default: /tmp/todos.txt).
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::attachment::{self, Attachment};
+use crate::bench;
+use crate::bulk::BulkAction;
+use crate::color::{self, ColorChoice};
+use crate::date::{self, Date};
+use crate::dedupe;
+use crate::effort::Effort;
+use crate::events;
+use crate::filter::{self, TaskFilter};
+use crate::forecast;
+use crate::i18n;
+use crate::issues::{self, Mapping};
+use crate::markdown;
+use crate::metrics;
+use crate::migrate;
+use crate::storage;
+use crate::template;
+use crate::uid::TaskUid;
+use crate::watch;
+use crate::{Priority, Status, Task, TodoApp, TodoError};
+
+const DEFAULT_FILE: &str = "/tmp/todos.txt";
+const DEFAULT_CONFIRM_THRESHOLD: usize = 10;
+
+pub(crate) fn run(args: &[String]) -> Result<(), TodoError> {
+    let mut args = args.to_vec();
+    let lang = take_option(&mut args, "--lang")?;
+    i18n::init(lang.as_deref());
+    let color = take_option(&mut args, "--color")?;
+    color::init(match color {
+        Some(choice) => choice.parse()?,
+        None => ColorChoice::Auto,
+    });
+    let sort = take_option(&mut args, "--sort")?.or_else(|| env::var("TODO_SORT").ok());
+    let group = take_option(&mut args, "--group")?.or_else(|| env::var("TODO_GROUP").ok());
+    let show_uids = take_flag(&mut args, "--uids");
+    let as_of = take_option(&mut args, "--as-of")?;
+    if args.is_empty() {
+        return Err(TodoError::InvalidInput(t!("error.missing_command")));
+    }
+    let command = args.remove(0);
+    if command == "help" || command == "--help" || command == "-h" {
+        println!("{}", t!("usage"));
+        return Ok(());
+    }
+    if command == "bench" {
+        return bench::run(args);
+    }
+    if command == "templates" {
+        return template::run(args);
+    }
+
+    let filename = data_file();
+    let data_dir = attachment::data_dir(&filename);
+    let mut removed: Vec<Task> = Vec::new();
+    let mut detached: Vec<Attachment> = Vec::new();
+    let mut write_back_files: Vec<String> = Vec::new();
+    let mut passphrase: Option<String> = None;
+    let exists = Path::new(&filename).exists();
+    if exists {
+        if storage::is_encrypted_file(&filename)? {
+            passphrase = Some(read_passphrase(
+                "TODO_PASSPHRASE",
+                &t!("prompt.passphrase"),
+            )?);
+        }
+        report_migration(
+            &filename,
+            migrate::migrate_file(&filename, passphrase.as_deref())?,
+        );
+    }
+    // Taken before loading, so that a change made while loading is noticed
+    // when saving.
+    let loaded = watch::FileState::read(&filename)?;
+    let opened_with = passphrase.clone();
+    let mut app = match &passphrase {
+        _ if !exists => TodoApp::new(),
+        Some(secret) => TodoApp::load_encrypted(&filename, secret)?,
+        None => TodoApp::load_from_file(&filename)?,
+    };
+    let base_seq = app.last_seq();
+    if let Some(spec) = sort {
+        app.sort = spec.parse()?;
+    }
+    if let Some(group) = group {
+        app.group = Some(group.parse()?);
+    }
+    app.show_uids = show_uids;
+    // Ages in statistics are measured from --as-of when it is given.
+    let mut now = events::now();
+    if let Some(time) = as_of {
+        if !matches!(
+            command.as_str(),
+            "list" | "search" | "stats" | "show" | "forecast"
+        ) {
+            return Err(TodoError::InvalidInput(t!(
+                "error.as_of_command",
+                command = command
+            )));
+        }
+        let at = date::parse_timestamp(&time)?;
+        app = app.as_of(at)?;
+        now = at;
+        println!("{}", t!("cli.as_of", time = date::format_timestamp(at)));
+    }
+    if command == "watch" {
+        return watch::run(app, &filename, passphrase.as_deref(), args);
+    }
+    if command == "metrics" {
+        return metrics::run(app, &filename, passphrase.as_deref(), args);
+    }
+
+    let changed = match command.as_str() {
+        "list" => {
+            match take_option(&mut args, "--status")? {
+                Some(status) => app.list_by_status(status.parse()?),
+                None => app.list_all(),
+            }
+            false
+        }
+        "add" => {
+            let priority = match take_option(&mut args, "--priority")? {
+                Some(p) => p.parse()?,
+                None => Priority::Medium,
+            };
+            let tag = take_option(&mut args, "--tag")?.unwrap_or_default();
+            let due = take_option(&mut args, "--due")?
+                .map(|d| d.parse::<Date>())
+                .transpose()?;
+            let estimate = take_option(&mut args, "--estimate")?
+                .map(|e| e.parse::<Effort>())
+                .transpose()?;
+            let title = args.join(" ");
+            if title.trim().is_empty() {
+                return Err(TodoError::InvalidInput(t!("error.empty_title")));
+            }
+            let id = app.add_task(title.trim(), priority, &tag);
+            if due.is_some() {
+                app.set_due(id, due)?;
+            }
+            if estimate.is_some() {
+                app.set_estimate(id, estimate)?;
+            }
+            true
+        }
+        "estimate" => {
+            let id = resolve(&app, args.first())?;
+            let estimate = match args.get(1).map(String::as_str) {
+                Some("none") => None,
+                Some(effort) => Some(effort.parse()?),
+                None => return Err(TodoError::InvalidInput(t!("error.missing_effort"))),
+            };
+            app.set_estimate(id, estimate)?;
+            true
+        }
+        "due" => {
+            let id = resolve(&app, args.first())?;
+            let due = match args.get(1).map(String::as_str) {
+                Some("none") => None,
+                Some(date) => Some(date.parse()?),
+                None => return Err(TodoError::InvalidInput(t!("error.missing_due_date"))),
+            };
+            app.set_due(id, due)?;
+            true
+        }
+        "complete" => {
+            let spent = take_option(&mut args, "--spent")?
+                .map(|e| e.parse::<Effort>())
+                .transpose()?;
+            let id = resolve(&app, args.first())?;
+            if spent.is_some() {
+                app.set_spent(id, spent)?;
+            }
+            app.complete_task(id)?;
+            true
+        }
+        "delete" => {
+            let task = app.delete_task(resolve(&app, args.first())?)?;
+            println!("{}", t!("task.deleted", task = task));
+            removed.push(task);
+            true
+        }
+        "show" => {
+            app.show_task(resolve(&app, args.first())?, &data_dir)?;
+            false
+        }
+        "note" => {
+            let append = take_option(&mut args, "--append")?;
+            let id = resolve(&app, args.first())?;
+            let current = &app.tasks[&id].notes;
+            let notes = match append {
+                Some(line) if current.is_empty() => line,
+                Some(line) => format!("{}\n{}", current, line),
+                None => edit_in_editor(current)?,
+            };
+            app.set_notes(id, notes)?;
+            println!("{}", t!("cli.notes_updated", id = id));
+            true
+        }
+        "attach" => {
+            let copy = take_flag(&mut args, "--copy");
+            let id = resolve(&app, args.first())?;
+            let source = args
+                .get(1)
+                .ok_or_else(|| TodoError::InvalidInput(t!("error.missing_attachment")))?;
+            let task = &app.tasks[&id];
+            let added = attachment::attach(&data_dir, task, Path::new(source), copy)?;
+            let mut attachments = task.attachments.clone();
+            attachments.push(added.clone());
+            app.set_attachments(id, attachments)?;
+            println!(
+                "{}",
+                t!(
+                    "cli.attached",
+                    file = added.location(&data_dir).display(),
+                    id = id
+                )
+            );
+            true
+        }
+        "detach" => {
+            let id = resolve(&app, args.first())?;
+            let mut attachments = app.tasks[&id].attachments.clone();
+            let n: usize = args
+                .get(1)
+                .and_then(|n| n.parse().ok())
+                .filter(|&n| n >= 1 && n <= attachments.len())
+                .ok_or_else(|| {
+                    TodoError::InvalidInput(t!(
+                        "error.bad_attachment_number",
+                        max = attachments.len()
+                    ))
+                })?;
+            detached.push(attachments.remove(n - 1));
+            app.set_attachments(id, attachments)?;
+            println!("{}", t!("cli.detached", n = n, id = id));
+            true
+        }
+        "search" => {
+            let query = args.join(" ");
+            if query.trim().is_empty() {
+                return Err(TodoError::InvalidInput(t!("error.missing_query")));
+            }
+            let results = app.search(&query);
+            println!("{}", tn!("cli.search_results", results.len()));
+            for task in results {
+                println!("  {}", task.render(Some(&query)));
+            }
+            false
+        }
+        "merge" => {
+            let other = args
+                .first()
+                .ok_or_else(|| TodoError::InvalidInput(t!("error.missing_merge_file")))?;
+            let (added, skipped) = app.merge(TodoApp::load_from_file(other)?)?;
+            println!(
+                "{}",
+                t!("cli.merged", file = other, added = added, skipped = skipped)
+            );
+            added > 0
+        }
+        "import" => {
+            let write_back = take_flag(&mut args, "--write-back");
+            let mapping = take_option(&mut args, "--mapping")?;
+            if args.is_empty() {
+                return Err(TodoError::InvalidInput(t!("error.missing_import_file")));
+            }
+            let (exports, checklists): (Vec<String>, Vec<String>) =
+                args.into_iter().partition(|file| {
+                    Path::new(file)
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+                });
+            let mut changed = false;
+            if !exports.is_empty() {
+                let mapping = Mapping::load(mapping.as_deref())?;
+                for file in &exports {
+                    let issues = issues::read_export(Path::new(file), &mapping)?;
+                    let result = app.import_issues(&issues, &mapping, file)?;
+                    println!(
+                        "{}",
+                        t!(
+                            "cli.imported_issues",
+                            file = file,
+                            added = result.added,
+                            updated = result.updated,
+                            closed = result.closed,
+                            unchanged = result.unchanged
+                        )
+                    );
+                    if result.deleted > 0 {
+                        println!("{}", tn!("cli.issues_deleted_locally", result.deleted));
+                    }
+                    changed |= result.added + result.updated + result.closed > 0;
+                }
+            }
+            for file in &checklists {
+                let path = Path::new(file);
+                let source = markdown::source_name(path)?;
+                let (added, skipped) = app.import_checklist(&fs::read_to_string(path)?, &source)?;
+                println!(
+                    "{}",
+                    t!(
+                        "cli.imported",
+                        file = file,
+                        added = added,
+                        skipped = skipped
+                    )
+                );
+                changed |= added > 0;
+            }
+            if write_back {
+                write_back_files = checklists;
+            }
+            changed
+        }
+        "apply" => {
+            let name = args
+                .first()
+                .ok_or_else(|| TodoError::InvalidInput(t!("error.missing_template")))?;
+            let template = template::load(&template::template_dir(), name)?;
+            let values = template::parse_values(&args[1..])?;
+            for id in app.apply_template(&template, &values)? {
+                println!("{}", t!("task.added", task = app.tasks[&id]));
+            }
+            true
+        }
+        "encrypt" => {
+            if passphrase.is_some() {
+                return Err(TodoError::InvalidInput(t!(
+                    "error.already_encrypted",
+                    file = filename
+                )));
+            }
+            passphrase = Some(read_new_passphrase("TODO_PASSPHRASE")?);
+            println!("{}", t!("cli.will_encrypt", file = filename));
+            true
+        }
+        "decrypt" => {
+            if passphrase.take().is_none() {
+                return Err(TodoError::InvalidInput(t!(
+                    "error.not_encrypted",
+                    file = filename
+                )));
+            }
+            println!("{}", t!("cli.will_decrypt", file = filename));
+            true
+        }
+        "rekey" => {
+            if passphrase.is_none() {
+                return Err(TodoError::InvalidInput(t!(
+                    "error.rekey_not_encrypted",
+                    file = filename
+                )));
+            }
+            passphrase = Some(read_new_passphrase("TODO_NEW_PASSPHRASE")?);
+            println!("{}", t!("cli.rekeyed"));
+            true
+        }
+        "history" => {
+            let reference = args
+                .first()
+                .ok_or_else(|| TodoError::InvalidInput(t!("error.missing_task_id")))?;
+            let uid = app.resolve_uid(reference)?;
+            match app.index.id_of(uid).map(|id| &app.tasks[&id]) {
+                Some(task) => println!("{}", t!("cli.history_of", task = task)),
+                None => println!("{}", t!("cli.history_of_deleted", uid = uid)),
+            }
+            app.print_history(uid);
+            false
+        }
+        "log" => {
+            let limit = match take_option(&mut args, "--limit")? {
+                Some(n) => n
+                    .parse()
+                    .map_err(|_| TodoError::InvalidInput(t!("error.bad_limit", value = n)))?,
+                None => 20,
+            };
+            app.print_log(limit);
+            false
+        }
+        "stats" => {
+            if take_flag(&mut args, "--json") {
+                app.print_stats_json(now);
+            } else {
+                app.print_stats(now);
+            }
+            false
+        }
+        "bulk" => run_bulk(&mut app, args, &mut removed)?,
+        "dedupe" => dedupe::run(&mut app, args, &data_dir, &mut removed)?,
+        "forecast" => {
+            forecast::run(&app, args, now)?;
+            false
+        }
+        other => {
+            return Err(TodoError::InvalidInput(t!(
+                "error.unknown_command",
+                command = other
+            )));
+        }
+    };
+
+    let app = if changed {
+        let app = watch::reconcile(app, &filename, opened_with.as_deref(), &loaded, base_seq)?;
+        match &passphrase {
+            Some(secret) => app.save_encrypted(&filename, secret)?,
+            None => app.save_to_file(&filename)?,
+        }
+        app
+    } else {
+        app
+    };
+    // Copied attachments are only cleaned up once the deletion or detachment
+    // has been saved.
+    for task in &removed {
+        attachment::remove_task_files(&data_dir, task)?;
+    }
+    for attachment in &detached {
+        attachment::remove_file(&data_dir, attachment)?;
+    }
+    // Likewise, checklists are only updated to match the saved tasks.
+    for file in &write_back_files {
+        let updated = app.write_back(Path::new(file))?;
+        println!("{}", tn!("cli.written_back", updated, file = file));
+    }
+    Ok(())
+}
+
+fn run_bulk(
+    app: &mut TodoApp,
+    mut args: Vec<String>,
+    removed: &mut Vec<Task>,
+) -> Result<bool, TodoError> {
+    let dry_run = take_flag(&mut args, "--dry-run");
+    let assume_yes = take_flag(&mut args, "--yes");
+    let select_all = take_flag(&mut args, "--all");
+    let threshold = match take_option(&mut args, "--threshold")? {
+        Some(n) => n
+            .parse()
+            .map_err(|_| TodoError::InvalidInput(t!("error.bad_threshold", value = n)))?,
+        None => DEFAULT_CONFIRM_THRESHOLD,
+    };
+    let filter = parse_filter(&mut args)?;
+    if filter.is_empty() && !select_all {
+        return Err(TodoError::InvalidInput(t!("error.bulk_needs_filter")));
+    }
+    let action = parse_bulk_action(&args)?;
+
+    let ids = app.select(&filter);
+    if ids.is_empty() {
+        println!("{}", t!("bulk.no_match"));
+        return Ok(false);
+    }
+
+    println!("{}", tn!("bulk.preview", ids.len(), action = action));
+    app.print_tasks(ids.iter().map(|id| &app.tasks[id]).collect());
+
+    if dry_run {
+        println!("{}", t!("bulk.dry_run"));
+        return Ok(false);
+    }
+    if ids.len() > threshold && !assume_yes {
+        let prompt = tn!("bulk.confirm", ids.len(), action = action);
+        if !confirm(&prompt)? {
+            println!("{}", t!("bulk.aborted"));
+            return Ok(false);
+        }
+    }
+
+    if let BulkAction::Delete = action {
+        removed.extend(ids.iter().map(|id| app.tasks[id].clone()));
+    }
+    let (count, skipped) = app.apply_bulk(&ids, &action)?;
+    if !skipped.is_empty() {
+        let ids: Vec<String> = skipped.iter().map(|id| format!("#{}", id)).collect();
+        println!(
+            "{}",
+            tn!(
+                "bulk.already_completed",
+                skipped.len(),
+                ids = ids.join(", ")
+            )
+        );
+    }
+    println!("{}", tn!("bulk.applied", count, action = action));
+    Ok(count > 0)
+}
+
+fn parse_bulk_action(args: &[String]) -> Result<BulkAction, TodoError> {
+    let (name, rest) = args
+        .split_first()
+        .ok_or_else(|| TodoError::InvalidInput(t!("error.missing_bulk_action")))?;
+
+    let action = match name.as_str() {
+        "complete" => BulkAction::Complete,
+        "delete" => BulkAction::Delete,
+        "priority" => BulkAction::Reprioritize(
+            rest.first()
+                .ok_or_else(|| TodoError::InvalidInput(t!("error.missing_priority")))?
+                .parse()?,
+        ),
+        "move" => BulkAction::Move(rest.join(" ").parse::<Status>()?),
+        "retag" => {
+            let mut add = Vec::new();
+            let mut remove = Vec::new();
+            for change in rest {
+                if let Some(tag) = change.strip_prefix('+').filter(|t| !t.is_empty()) {
+                    add.push(tag.to_string());
+                } else if let Some(tag) = change.strip_prefix('-').filter(|t| !t.is_empty()) {
+                    remove.push(tag.to_string());
+                } else {
+                    return Err(TodoError::InvalidInput(t!(
+                        "error.bad_retag",
+                        value = change
+                    )));
+                }
+            }
+            if add.is_empty() && remove.is_empty() {
+                return Err(TodoError::InvalidInput(t!("error.missing_tags")));
+            }
+            BulkAction::Retag { add, remove }
+        }
+        other => {
+            return Err(TodoError::InvalidInput(t!(
+                "error.unknown_bulk_action",
+                action = other
+            )));
+        }
+    };
+    Ok(action)
+}
+
+fn parse_filter(args: &mut Vec<String>) -> Result<TaskFilter, TodoError> {
+    let mut filter = TaskFilter::default();
+    if let Some(ids) = take_option(args, "--ids")? {
+        filter.ids = filter::parse_id_ranges(&ids)?;
+    }
+    if let Some(status) = take_option(args, "--status")? {
+        filter.status = Some(status.parse()?);
+    }
+    if let Some(priority) = take_option(args, "--priority")? {
+        filter.priority = Some(priority.parse()?);
+    }
+    filter.tag = take_option(args, "--tag")?;
+    filter.text = take_option(args, "--search")?;
+    if filter
+        .text
+        .as_ref()
+        .is_some_and(|text| text.trim().is_empty())
+    {
+        return Err(TodoError::InvalidInput(t!("error.missing_query")));
+    }
+    Ok(filter)
+}
+
+// Goes to stderr so that output meant for scripts stays clean.
+fn report_migration(filename: &str, migrated: Option<(u32, String)>) {
+    if let Some((version, backup)) = migrated {
+        eprintln!(
+            "{}",
+            t!(
+                "cli.migrated",
+                file = filename,
+                from = version,
+                to = migrate::SCHEMA_VERSION,
+                backup = backup
+            )
+        );
+    }
+}
+
+fn data_file() -> String {
+    env::var("TODO_FILE").unwrap_or_else(|_| DEFAULT_FILE.to_string())
+}
+
+fn resolve(app: &TodoApp, arg: Option<&String>) -> Result<u32, TodoError> {
+    let arg = arg.ok_or_else(|| TodoError::InvalidInput(t!("error.missing_task_id")))?;
+    app.resolve(arg)
+}
+
+// Removes `name` from `args`, returning whether it was present.
+pub(crate) fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+// Removes `name VALUE` or `name=VALUE` from `args`, returning the value.
+pub(crate) fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, TodoError> {
+    let prefix = format!("{}=", name);
+    if let Some(i) = args.iter().position(|a| a.starts_with(&prefix)) {
+        return Ok(Some(args.remove(i)[prefix.len()..].to_string()));
+    }
+    match args.iter().position(|a| a == name) {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            Ok(Some(args.remove(i)))
+        }
+        Some(_) => Err(TodoError::InvalidInput(t!(
+            "error.needs_value",
+            option = name
+        ))),
+        None => Ok(None),
+    }
+}
+
+// Opens the user's editor ($EDITOR, then $VISUAL, then vi) on a temporary
+// Markdown file holding `text`, and returns the edited contents. The file is
+// only readable by the user and removed however the edit ends, as the notes
+// may come from an encrypted todo file.
+fn edit_in_editor(text: &str) -> Result<String, TodoError> {
+    let editor = env::var("EDITOR")
+        .or_else(|_| env::var("VISUAL"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // The editor variable may carry arguments, e.g. "code --wait".
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| TodoError::InvalidInput(t!("error.empty_editor")))?;
+
+    let scratch = ScratchDir::create()?;
+    let path = scratch.write("notes.md", text)?;
+    let status = Command::new(program).args(words).arg(&path).status()?;
+    if !status.success() {
+        return Err(TodoError::InvalidInput(t!("error.editor_failed")));
+    }
+    Ok(fs::read_to_string(&path)?.trim_end().to_string())
+}
+
+// A directory of its own in the temporary directory, with an unguessable name
+// and only accessible by the user. It is removed, with everything in it, when
+// dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn create() -> io::Result<Self> {
+        let path = env::temp_dir().join(format!("todo-{}", TaskUid::generate().hex()));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        builder.mode(0o700);
+        // Fails rather than follows anything already at `path`.
+        builder.create(&path)?;
+        Ok(ScratchDir(path))
+    }
+
+    // Creates the file `name` holding `text`; it must not exist yet.
+    fn write(&self, name: &str, text: &str) -> io::Result<PathBuf> {
+        let path = self.0.join(name);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        options.open(&path)?.write_all(text.as_bytes())?;
+        Ok(path)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// Reads a passphrase from `env_var` if set, otherwise from the terminal with echo off.
+fn read_passphrase(env_var: &str, prompt: &str) -> Result<String, TodoError> {
+    if let Ok(secret) = env::var(env_var) {
+        return Ok(secret);
+    }
+    print!("{} ", prompt);
+    io::stdout().flush()?;
+    set_echo(false);
+    let mut secret = String::new();
+    let result = io::stdin().read_line(&mut secret);
+    set_echo(true);
+    println!();
+    result?;
+    Ok(secret.trim_end_matches(['\r', '\n']).to_string())
+}
+
+// Asks for a new passphrase twice, unless `env_var` provides it.
+fn read_new_passphrase(env_var: &str) -> Result<String, TodoError> {
+    if let Ok(secret) = env::var(env_var) {
+        return Ok(secret);
+    }
+    let secret = read_passphrase(env_var, &t!("prompt.new_passphrase"))?;
+    if secret.is_empty() {
+        return Err(TodoError::InvalidInput(t!("error.empty_passphrase")));
+    }
+    if read_passphrase(env_var, &t!("prompt.repeat_passphrase"))? != secret {
+        return Err(TodoError::InvalidInput(t!("error.passphrase_mismatch")));
+    }
+    Ok(secret)
+}
+
+// Turns terminal echo on or off through stty; does nothing where stty is unavailable.
+fn set_echo(on: bool) {
+    let _ = Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .status();
+}
+
+// Asks a yes/no question; the accepted answers come from the catalog.
+pub(crate) fn confirm(prompt: &str) -> Result<bool, TodoError> {
+    print!("{} ", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim().to_lowercase();
+    Ok(t!("prompt.yes_answers")
+        .split(',')
+        .any(|yes| yes.trim() == answer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratch_files_are_private_and_removed() {
+        let scratch = ScratchDir::create().unwrap();
+        let dir = scratch.0.clone();
+        let path = scratch.write("notes.md", "secret").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret");
+        // Never written through something already there.
+        assert!(scratch.write("notes.md", "again").is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&dir), 0o700);
+            assert_eq!(mode(&path), 0o600);
+        }
+        drop(scratch);
+        assert!(!dir.exists());
+        assert_ne!(ScratchDir::create().unwrap().0, dir);
+    }
+
+    #[test]
+    fn blank_search_text_matches_nothing() {
+        let mut app = TodoApp::new();
+        app.add_task("Two words", Priority::Low, "");
+        assert_eq!(app.search("two").len(), 1);
+        assert!(app.search("  ").is_empty());
+        assert!(app.search("").is_empty());
+
+        let mut args = vec!["--search".to_string(), " ".to_string()];
+        assert!(matches!(
+            parse_filter(&mut args),
+            Err(TodoError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+// Optional ANSI styling for terminal output.
+// Colors are used when stdout is a terminal and NO_COLOR is not set, unless
+// --color=always or --color=never says otherwise.
+
+use std::cell::Cell;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::TodoError;
+
+const RESET: &str = "\x1b[0m";
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+thread_local! {
+    // The choice set by `with_colors` on this thread, if any.
+    static SCOPED: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+    Always,
+    Never,
+    Auto,
+}
+
+impl FromStr for ColorChoice {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            "auto" => Ok(ColorChoice::Auto),
+            _ => Err(TodoError::InvalidInput(t!("error.bad_color", value = s))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+    Bold,
+    Dim,
+    Red,
+    Yellow,
+    Blue,
+    // Search matches: black on yellow.
+    Highlight,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "\x1b[1m",
+            Style::Dim => "\x1b[2m",
+            Style::Red => "\x1b[31m",
+            Style::Yellow => "\x1b[33m",
+            Style::Blue => "\x1b[34m",
+            Style::Highlight => "\x1b[30;43m",
+        }
+    }
+}
+
+// Decides whether to use colors for the rest of the run; later calls, and
+// calls after the first styled output, have no effect.
+pub(crate) fn init(choice: ColorChoice) {
+    let _ = ENABLED.set(decide(choice));
+}
+
+fn enabled() -> bool {
+    SCOPED
+        .get()
+        .unwrap_or_else(|| *ENABLED.get_or_init(|| decide(ColorChoice::Auto)))
+}
+
+// Runs `f` with colors on or off on this thread, whatever the run decided.
+#[cfg(test)]
+fn with_colors<R>(on: bool, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<bool>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.set(self.0);
+        }
+    }
+    let _restore = Restore(SCOPED.replace(Some(on)));
+    f()
+}
+
+fn decide(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    }
+}
+
+// Wraps `text` in `style`. Styles nest: text that is already styled keeps the
+// outer style after its own ends.
+pub(crate) fn paint(text: &str, style: Style) -> String {
+    if !enabled() {
+        return text.to_string();
+    }
+    let inner = text.replace(RESET, &format!("{}{}", RESET, style.code()));
+    format!("{}{}{}", style.code(), inner, RESET)
+}
+
+// Highlights every case-insensitive occurrence of `query` in `text`, matching
+// the way search compares titles.
+pub(crate) fn highlight(text: &str, query: &str) -> String {
+    let needle: Vec<char> = query.to_lowercase().chars().collect();
+    if needle.is_empty() || !enabled() {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match match_len(rest, &needle) {
+            Some(len) => {
+                out.push_str(&paint(&rest[..len], Style::Highlight));
+                rest = &rest[len..];
+            }
+            None => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    out
+}
+
+// The byte length of the prefix of `text` that lowercases to `needle`, if any.
+fn match_len(text: &str, needle: &[char]) -> Option<usize> {
+    let mut lowered = Vec::with_capacity(needle.len());
+    for (i, c) in text.char_indices() {
+        lowered.extend(c.to_lowercase());
+        if lowered.len() >= needle.len() {
+            return (lowered == needle).then_some(i + c.len_utf8());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOLD: &str = "\x1b[1m";
+    const RED: &str = "\x1b[31m";
+    const MARK: &str = "\x1b[30;43m";
+
+    fn marked(text: &str) -> String {
+        format!("{}{}{}", MARK, text, RESET)
+    }
+
+    #[test]
+    fn parses_choices() {
+        assert_eq!(
+            " Always ".parse::<ColorChoice>().unwrap(),
+            ColorChoice::Always
+        );
+        assert_eq!("never".parse::<ColorChoice>().unwrap(), ColorChoice::Never);
+        assert_eq!("AUTO".parse::<ColorChoice>().unwrap(), ColorChoice::Auto);
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn nothing_is_styled_without_colors() {
+        with_colors(false, || {
+            assert_eq!(paint("plain", Style::Bold), "plain");
+            assert_eq!(highlight("Write report", "report"), "Write report");
+        });
+    }
+
+    #[test]
+    fn nested_styles_resume_the_outer_one() {
+        with_colors(true, || {
+            assert_eq!(paint("x", Style::Red), format!("{}x{}", RED, RESET));
+            let inner = paint("x", Style::Red);
+            assert_eq!(
+                paint(&format!("a{}b", inner), Style::Bold),
+                format!("{}a{}x{}{}b{}", BOLD, RED, RESET, BOLD, RESET)
+            );
+        });
+    }
+
+    #[test]
+    fn highlights_every_match_ignoring_case() {
+        with_colors(true, || {
+            assert_eq!(
+                highlight("Report the REPORT", "report"),
+                format!("{} the {}", marked("Report"), marked("REPORT"))
+            );
+            assert_eq!(highlight("aaa", "aa"), format!("{}a", marked("aa")));
+            assert_eq!(highlight("Write report", ""), "Write report");
+            assert_eq!(highlight("Write report", "memo"), "Write report");
+        });
+    }
+
+    #[test]
+    fn highlights_multibyte_text() {
+        with_colors(true, || {
+            assert_eq!(
+                highlight("Äpfel und ÄRGER", "ä"),
+                format!("{}pfel und {}RGER", marked("Ä"), marked("Ä"))
+            );
+            assert_eq!(
+                highlight("日本語のメモ", "本語"),
+                format!("日{}のメモ", marked("本語"))
+            );
+        });
+    }
+
+    #[test]
+    fn match_len_counts_bytes_of_whole_characters() {
+        let needle = |s: &str| s.chars().collect::<Vec<char>>();
+        assert_eq!(match_len("Äpfel", &needle("äp")), Some(3));
+        assert_eq!(match_len("ÄPFEL", &needle("äpfel")), Some(6));
+        assert_eq!(match_len("Äp", &needle("äpf")), None);
+        assert_eq!(match_len("xÄ", &needle("ä")), None);
+        // 'İ' lowercases to two characters: both must match.
+        assert_eq!(match_len("İx", &needle("i\u{307}x")), Some(3));
+        assert_eq!(match_len("İx", &needle("ix")), None);
+    }
+}
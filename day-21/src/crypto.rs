@@ -0,0 +1,265 @@
+// Passphrase-based encryption for the todo file.
+//
+// Layout of an encrypted file (integers little-endian):
+//   magic "TODOENC" | version u8 | argon2 m_cost u32 | t_cost u32 | p_cost u32
+//   | salt [16] | nonce [12] | ciphertext + 16-byte Poly1305 tag
+//
+// The key is derived from the passphrase with Argon2id and the contents are
+// sealed with ChaCha20-Poly1305. The whole header is authenticated as
+// associated data, so changing any byte of the file makes decryption fail.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+
+use crate::TodoError;
+
+const MAGIC: &[u8; 7] = b"TODOENC";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN;
+
+// Argon2id settings for new files (19 MiB, 2 passes); older files keep
+// whatever settings are recorded in their header.
+const M_COST: u32 = 19 * 1024;
+const T_COST: u32 = 2;
+const P_COST: u32 = 1;
+// Upper bounds accepted from a file header, so a damaged or hostile file
+// cannot make key derivation allocate gigabytes or run for minutes.
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 64;
+
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub(crate) fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, TodoError> {
+    if passphrase.is_empty() {
+        return Err(TodoError::InvalidInput(t!("error.empty_passphrase")));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    for value in [M_COST, T_COST, P_COST] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, M_COST, T_COST, P_COST)?;
+    let cipher = ChaCha20Poly1305::new(&key);
+    let payload = Payload {
+        msg: plaintext,
+        aad: &header,
+    };
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| TodoError::InvalidInput(t!("error.encryption_failed")))?;
+
+    let mut out = header;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub(crate) fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, TodoError> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN {
+        return Err(TodoError::InvalidInput(t!("error.not_encrypted_file")));
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+
+    let version = header[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(TodoError::InvalidInput(t!(
+            "error.unsupported_version",
+            version = version
+        )));
+    }
+    let read_u32 = |offset: usize| {
+        let start = MAGIC.len() + 1 + offset * 4;
+        u32::from_le_bytes(header[start..start + 4].try_into().expect("4 bytes"))
+    };
+    let (m_cost, t_cost, p_cost) = (read_u32(0), read_u32(1), read_u32(2));
+    if m_cost > MAX_M_COST || t_cost > MAX_T_COST {
+        return Err(TodoError::InvalidInput(t!("error.kdf_out_of_range")));
+    }
+    let salt_start = MAGIC.len() + 1 + 12;
+    let salt = &header[salt_start..salt_start + SALT_LEN];
+    let nonce = &header[salt_start + SALT_LEN..];
+
+    let key = derive_key(passphrase, salt, m_cost, t_cost, p_cost)?;
+    let cipher = ChaCha20Poly1305::new(&key);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: header,
+    };
+    cipher
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| TodoError::WrongPassphrase)
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> Result<Key, TodoError> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| TodoError::InvalidInput(t!("error.bad_kdf_settings", error = e)))?;
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| TodoError::InvalidInput(t!("error.kdf_failed", error = e)))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use crate::events::EventKind;
+    use crate::storage;
+    use crate::{Priority, Task, TodoApp};
+
+    const SECRET: &str = "correct horse battery staple";
+    const TEXT: &[u8] = b"#schema|4\n#snapshot|0\nsecret plans\n";
+
+    fn sealed() -> Vec<u8> {
+        encrypt(TEXT, SECRET).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let data = sealed();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(12).any(|w| w == b"secret plans"));
+        assert_eq!(decrypt(&data, SECRET).unwrap(), TEXT);
+        assert_eq!(data.len(), HEADER_LEN + TEXT.len() + 16);
+        // A fresh salt and nonce every time.
+        assert_ne!(sealed(), data);
+        assert_eq!(
+            decrypt(&encrypt(b"", SECRET).unwrap(), SECRET).unwrap(),
+            b""
+        );
+    }
+
+    #[test]
+    fn refuses_an_empty_passphrase() {
+        assert!(encrypt(TEXT, "").is_err());
+    }
+
+    #[test]
+    fn rejects_the_wrong_passphrase() {
+        let result = decrypt(&sealed(), "correct horse battery stapler");
+        assert!(matches!(result, Err(TodoError::WrongPassphrase)));
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let data = sealed();
+        let salt = MAGIC.len() + 1 + 12;
+        // A KDF setting, the salt, the nonce, the ciphertext and the tag.
+        for offset in [
+            MAGIC.len() + 1 + 4,
+            salt,
+            salt + SALT_LEN,
+            HEADER_LEN,
+            data.len() - 1,
+        ] {
+            let mut tampered = data.clone();
+            tampered[offset] ^= 0x01;
+            let result = decrypt(&tampered, SECRET);
+            assert!(
+                matches!(result, Err(TodoError::WrongPassphrase)),
+                "byte {} changed: {:?}",
+                offset,
+                result
+            );
+        }
+
+        let mut version = data.clone();
+        version[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(matches!(
+            decrypt(&version, SECRET),
+            Err(TodoError::InvalidInput(_))
+        ));
+        let mut costly = data.clone();
+        costly[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            decrypt(&costly, SECRET),
+            Err(TodoError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let data = sealed();
+        for len in [0, MAGIC.len(), HEADER_LEN - 1] {
+            assert!(
+                matches!(
+                    decrypt(&data[..len], SECRET),
+                    Err(TodoError::InvalidInput(_))
+                ),
+                "accepted {} bytes",
+                len
+            );
+        }
+        // A header without the full tag.
+        for len in [HEADER_LEN, HEADER_LEN + 15, data.len() - 1] {
+            assert!(
+                decrypt(&data[..len], SECRET).is_err(),
+                "accepted {} bytes",
+                len
+            );
+        }
+        assert!(decrypt(TEXT, SECRET).is_err());
+    }
+
+    #[test]
+    fn converts_a_store_to_encrypted_and_back() {
+        let path = env::temp_dir().join(format!("day21-crypto-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut app = TodoApp::new();
+        let task = Task::new(
+            1,
+            "Renew passport",
+            Priority::High,
+            vec!["admin".to_string()],
+        );
+        app.record(EventKind::TaskAdded(task)).unwrap();
+        let text = app.to_text();
+
+        app.save_to_file(path).unwrap();
+        assert!(!storage::is_encrypted_file(path).unwrap());
+        let app = TodoApp::load_from_file(path).unwrap();
+
+        app.save_encrypted(path, SECRET).unwrap();
+        assert!(storage::is_encrypted_file(path).unwrap());
+        assert!(!fs::read(path).unwrap().windows(7).any(|w| w == b"passport"));
+        assert!(matches!(
+            TodoApp::load_from_file(path),
+            Err(TodoError::PassphraseRequired(_))
+        ));
+        assert!(matches!(
+            TodoApp::load_encrypted(path, "guess"),
+            Err(TodoError::WrongPassphrase)
+        ));
+        let app = TodoApp::load_encrypted(path, SECRET).unwrap();
+        assert_eq!(app.to_text(), text);
+
+        app.save_to_file(path).unwrap();
+        assert!(!storage::is_encrypted_file(path).unwrap());
+        assert_eq!(fs::read_to_string(path).unwrap(), text);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+// A minimal calendar date, enough for due dates without pulling in chrono.
+// Conversions use the days-from-civil algorithm on the proleptic Gregorian calendar.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::TodoError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    pub(crate) fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+
+    pub(crate) fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Date::from_timestamp(secs)
+    }
+
+    // The UTC day of a time in seconds since the epoch.
+    pub(crate) fn from_timestamp(secs: u64) -> Self {
+        Date::from_days((secs / 86_400) as i64)
+    }
+
+    // Days since 1970-01-01.
+    pub(crate) fn days(&self) -> i64 {
+        let y = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        } as i64;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub(crate) fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        Date { year, month, day }
+    }
+}
+
+// Formats seconds since the epoch as "YYYY-MM-DD HH:MM:SS" (UTC).
+pub(crate) fn format_timestamp(secs: u64) -> String {
+    let date = Date::from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        date,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// Parses "YYYY-MM-DD", "YYYY-MM-DD HH:MM[:SS]" (also with a 'T' separator)
+// or raw seconds since the epoch, all in UTC. A bare date means the end of
+// that day, so "--as-of 2026-10-16" includes everything done on the 16th.
+pub(crate) fn parse_timestamp(s: &str) -> Result<u64, TodoError> {
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(secs);
+    }
+    let bad = || TodoError::InvalidInput(t!("error.bad_time", value = s));
+    let (date_part, time_part) = match s.split_once(['T', ' ']) {
+        Some((d, t)) => (d, Some(t)),
+        None => (s, None),
+    };
+    let date: Date = date_part.parse()?;
+    let day_start = u64::try_from(date.days()).map_err(|_| bad())? * 86_400;
+    let Some(time) = time_part else {
+        return Ok(day_start + 86_399);
+    };
+    let parts: Vec<u64> = time
+        .split(':')
+        .map(|p| p.parse().map_err(|_| bad()))
+        .collect::<Result<_, _>>()?;
+    let (h, m, sec) = match parts.as_slice() {
+        [h, m] => (*h, *m, 0),
+        [h, m, sec] => (*h, *m, *sec),
+        _ => return Err(bad()),
+    };
+    if h > 23 || m > 59 || sec > 59 {
+        return Err(bad());
+    }
+    Ok(day_start + h * 3600 + m * 60 + sec)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = TodoError;
+
+    // Accepts YYYY-MM-DD, plus "today" and "tomorrow" for convenience.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "today" => return Ok(Date::today()),
+            "tomorrow" => return Ok(Date::from_days(Date::today().days() + 1)),
+            _ => {}
+        }
+        let bad = || TodoError::InvalidInput(t!("error.bad_date", value = s));
+        let parts: Vec<&str> = s.split('-').collect();
+        if parts.len() != 3 {
+            return Err(bad());
+        }
+        let year = parts[0].parse().map_err(|_| bad())?;
+        let month = parts[1].parse().map_err(|_| bad())?;
+        let day = parts[2].parse().map_err(|_| bad())?;
+        Date::new(year, month, day).ok_or_else(bad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_dates_and_rejects_impossible_ones() {
+        assert_eq!(date(" 2026-10-19 "), Date::new(2026, 10, 19).unwrap());
+        assert_eq!(date("2026-1-5").to_string(), "2026-01-05");
+        assert_eq!(date("2024-02-29").to_string(), "2024-02-29");
+        assert_eq!(date("2000-02-29").to_string(), "2000-02-29");
+        for bad in [
+            "2023-02-29",
+            "1900-02-29",
+            "2026-04-31",
+            "2026-13-01",
+            "2026-00-10",
+            "2026-10-00",
+            "2026-10",
+            "2026/10/19",
+            "2026-10-19-1",
+            "next week",
+            "",
+        ] {
+            assert!(
+                matches!(bad.parse::<Date>(), Err(TodoError::InvalidInput(_))),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn relative_dates_follow_today() {
+        let today = Date::today();
+        assert_eq!(date("Today"), today);
+        assert_eq!(date("tomorrow").days(), today.days() + 1);
+    }
+
+    #[test]
+    fn day_numbers_round_trip() {
+        assert_eq!(date("1970-01-01").days(), 0);
+        assert_eq!(date("1969-12-31").days(), -1);
+        assert_eq!(date("2000-03-01").days() - date("2000-02-28").days(), 2);
+        assert_eq!(date("2100-03-01").days() - date("2100-02-28").days(), 1);
+        for days in (-800_000..800_000).step_by(997) {
+            assert_eq!(Date::from_days(days).days(), days);
+        }
+        assert!(date("2026-12-31") < date("2027-01-01"));
+    }
+
+    #[test]
+    fn timestamps_are_utc() {
+        // 2026-10-19 00:00:00 UTC.
+        let midnight = 1_792_368_000;
+        assert_eq!(Date::from_timestamp(midnight), date("2026-10-19"));
+        assert_eq!(Date::from_timestamp(midnight - 1), date("2026-10-18"));
+        assert_eq!(format_timestamp(midnight + 3_723), "2026-10-19 01:02:03");
+
+        assert_eq!(parse_timestamp("2026-10-19").unwrap(), midnight + 86_399);
+        assert_eq!(
+            parse_timestamp("2026-10-19 09:30").unwrap(),
+            midnight + 34_200
+        );
+        assert_eq!(
+            parse_timestamp("2026-10-19T23:59:59").unwrap(),
+            midnight + 86_399
+        );
+        assert_eq!(parse_timestamp(" 12345 ").unwrap(), 12_345);
+        for bad in [
+            "2026-10-19 24:00",
+            "2026-10-19 12:60",
+            "2026-10-19 12",
+            "2026-10-19 12:00:00:00",
+            "2026-10-19 noon",
+            "1969-12-31",
+        ] {
+            assert!(parse_timestamp(bad).is_err(), "{:?}", bad);
+        }
+    }
+}
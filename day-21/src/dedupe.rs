@@ -0,0 +1,381 @@
+// Finding and merging near-identical tasks.
+// Titles are compared after normalizing them (case, punctuation and plural
+// endings are ignored), by edit distance relative to the longer title, so
+// "Review ownership concepts" and "review ownership concept." match.
+// `dedupe` lists the groups of likely duplicates; `dedupe --merge` keeps the
+// task with the lowest number in each group, with the highest priority, the
+// union of the tags, the earliest due date and every note and attachment of
+// the group, and deletes the others. Copied attachments are copied into the
+// kept task's directory; the originals go once the merge is saved, as those of
+// deleted tasks do.
+
+use std::path::Path;
+
+use crate::attachment;
+use crate::cli::{confirm, take_flag, take_option};
+use crate::events::EventKind;
+use crate::{Status, Task, TodoApp, TodoError};
+
+// Default minimum similarity, from 0 (anything) to 1 (same normalized title).
+const DEFAULT_THRESHOLD: f64 = 0.9;
+
+// The merged-away tasks are added to `removed`, so their attachment files can
+// be cleaned up once the merge has been saved.
+pub(crate) fn run(
+    app: &mut TodoApp,
+    mut args: Vec<String>,
+    data_dir: &Path,
+    removed: &mut Vec<Task>,
+) -> Result<bool, TodoError> {
+    let merge = take_flag(&mut args, "--merge");
+    let assume_yes = take_flag(&mut args, "--yes");
+    let include_completed = take_flag(&mut args, "--all");
+    let threshold = match take_option(&mut args, "--threshold")? {
+        Some(value) => value
+            .parse()
+            .ok()
+            .filter(|t| (0.0..=1.0).contains(t))
+            .ok_or_else(|| TodoError::InvalidInput(t!("error.bad_similarity", value = value)))?,
+        None => DEFAULT_THRESHOLD,
+    };
+
+    let groups = app.duplicate_groups(threshold, include_completed);
+    if groups.is_empty() {
+        println!("{}", t!("dedupe.none"));
+        return Ok(false);
+    }
+    for (n, group) in groups.iter().enumerate() {
+        println!(
+            "{}",
+            tn!("dedupe.group", group.len(), n = n + 1, id = group[0])
+        );
+        for id in group {
+            println!("  {}", app.task_line(&app.tasks[id]));
+        }
+    }
+
+    if !merge {
+        println!("{}", t!("dedupe.hint"));
+        return Ok(false);
+    }
+    if !assume_yes && !confirm(&tn!("dedupe.confirm", groups.len()))? {
+        println!("{}", t!("bulk.aborted"));
+        return Ok(false);
+    }
+    let deleted = app.merge_duplicates(&groups, data_dir)?;
+    println!(
+        "{}",
+        tn!("dedupe.merged", groups.len(), removed = deleted.len())
+    );
+    removed.extend(deleted);
+    Ok(true)
+}
+
+impl TodoApp {
+    // Groups of at least two tasks whose titles are at least `threshold`
+    // similar to another task of the group, each sorted by id. Completed
+    // tasks are only considered with `include_completed`.
+    pub(crate) fn duplicate_groups(
+        &self,
+        threshold: f64,
+        include_completed: bool,
+    ) -> Vec<Vec<u32>> {
+        let mut titles: Vec<(u32, Vec<char>)> = self
+            .tasks
+            .values()
+            .filter(|t| include_completed || t.status != Status::Completed)
+            .map(|t| (t.id, normalize(&t.title).chars().collect()))
+            .collect();
+        // Sorted by length, so each title is only compared with the slightly
+        // longer titles that can still be similar enough.
+        titles.sort_by_key(|(id, title)| (title.len(), *id));
+
+        let mut parent: Vec<usize> = (0..titles.len()).collect();
+        for i in 0..titles.len() {
+            for j in i + 1..titles.len() {
+                let (short, long) = (titles[i].1.len(), titles[j].1.len());
+                if (long as f64) * threshold > short as f64 {
+                    break;
+                }
+                if similarity(&titles[i].1, &titles[j].1) >= threshold {
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<u32>> = vec![Vec::new(); titles.len()];
+        for (i, (id, _)) in titles.iter().enumerate() {
+            let root = find(&mut parent, i);
+            groups[root].push(*id);
+        }
+        let mut groups: Vec<Vec<u32>> = groups.into_iter().filter(|g| g.len() > 1).collect();
+        for group in &mut groups {
+            group.sort_unstable();
+        }
+        groups.sort_unstable();
+        groups
+    }
+
+    // Merges each group into its first task as one transaction and returns
+    // the tasks deleted, as they were.
+    pub(crate) fn merge_duplicates(
+        &mut self,
+        groups: &[Vec<u32>],
+        data_dir: &Path,
+    ) -> Result<Vec<Task>, TodoError> {
+        let mut events = Vec::new();
+        let mut removed = Vec::new();
+        for group in groups {
+            let tasks: Vec<&Task> = group.iter().map(|id| &self.tasks[id]).collect();
+            let (keep, others) = tasks.split_first().expect("groups are not empty");
+            let uid = keep.uid;
+
+            let priority = tasks.iter().map(|t| t.priority).max().expect("not empty");
+            if priority != keep.priority {
+                events.push(EventKind::PriorityChanged(uid, priority));
+            }
+
+            let mut tags = keep.tags.clone();
+            for tag in others.iter().flat_map(|t| &t.tags) {
+                if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                    tags.push(tag.clone());
+                }
+            }
+            if tags != keep.tags {
+                events.push(EventKind::TagsChanged(uid, tags));
+            }
+
+            let due = tasks.iter().filter_map(|t| t.due).min();
+            if due != keep.due {
+                events.push(EventKind::DueChanged(uid, due));
+            }
+
+            let mut notes: Vec<&str> = Vec::new();
+            for task in &tasks {
+                let text = task.notes.trim();
+                if !text.is_empty() && !notes.contains(&text) {
+                    notes.push(text);
+                }
+            }
+            let notes = notes.join("\n\n");
+            if notes != keep.notes.trim() {
+                events.push(EventKind::NotesChanged(uid, notes));
+            }
+
+            let mut attachments = keep.attachments.clone();
+            for attachment in others.iter().flat_map(|t| &t.attachments) {
+                let attachment = attachment::copy_to(data_dir, attachment, uid)?;
+                if !attachments.contains(&attachment) {
+                    attachments.push(attachment);
+                }
+            }
+            if attachments != keep.attachments {
+                events.push(EventKind::AttachmentsChanged(uid, attachments));
+            }
+
+            for task in others {
+                events.push(EventKind::TaskDeleted(task.uid));
+                removed.push((*task).clone());
+            }
+        }
+        self.record_all(events)?;
+        Ok(removed)
+    }
+}
+
+// "Review the ownership-concepts!" -> "review the ownership concept"
+pub(crate) fn normalize(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| match w.strip_suffix('s') {
+            Some(stem) if stem.chars().count() >= 3 && !stem.ends_with('s') => stem,
+            _ => w,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 1 minus the edit distance divided by the length of the longer title.
+fn similarity(a: &[char], b: &[char]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / longest as f64
+}
+
+// Levenshtein distance, keeping one row of the table at a time.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::*;
+    use crate::Priority;
+    use crate::date::Date;
+
+    fn chars(text: &str) -> Vec<char> {
+        normalize(text).chars().collect()
+    }
+
+    #[test]
+    fn normalizes_case_punctuation_and_plurals() {
+        assert_eq!(
+            normalize("Review the ownership-concepts!"),
+            "review the ownership concept"
+        );
+        assert_eq!(normalize("  Fix   BUGS, again "), "fix bug again");
+        // Short words and double s are not plurals.
+        assert_eq!(normalize("Pass its class tests"), "pass its class test");
+        assert_eq!(normalize("Übungen prüfen"), "übungen prüfen");
+        assert_eq!(normalize("!!!"), "");
+    }
+
+    #[test]
+    fn scores_by_edit_distance_over_the_longer_title() {
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+        assert_eq!(similarity(&chars("Buy milk"), &chars("buy milk.")), 1.0);
+        assert_eq!(similarity(&chars(""), &chars("")), 1.0);
+        let score = similarity(&chars("Write report"), &chars("Write reports now"));
+        // "write report" and "write report now": 4 edits over 16 characters.
+        assert!((score - 0.75).abs() < 1e-9, "{}", score);
+    }
+
+    fn app_with(titles: &[&str]) -> TodoApp {
+        let mut app = TodoApp::new();
+        for title in titles {
+            app.add_task(title, Priority::Medium, "");
+        }
+        app
+    }
+
+    #[test]
+    fn groups_similar_open_tasks() {
+        let mut app = app_with(&[
+            "Review ownership concepts",
+            "Call the bank",
+            "review ownership concept.",
+            "Buy milk",
+            "Review ownership concpets",
+            "Call the bank!",
+        ]);
+        assert_eq!(
+            app.duplicate_groups(0.9, false),
+            [vec![1, 3, 5], vec![2, 6]]
+        );
+        assert_eq!(app.duplicate_groups(1.0, false), [vec![1, 3], vec![2, 6]]);
+
+        app.complete_task(6).unwrap();
+        assert_eq!(app.duplicate_groups(0.9, false), [vec![1, 3, 5]]);
+        assert_eq!(app.duplicate_groups(0.9, true).len(), 2);
+        assert!(app_with(&["a", "b"]).duplicate_groups(0.9, true).is_empty());
+    }
+
+    #[test]
+    fn merges_fields_into_the_first_task() {
+        let mut app = app_with(&["Write report", "write report", "Write report!"]);
+        let uid = |app: &TodoApp, id| app.uid_of(id).unwrap();
+        app.record_all(vec![
+            EventKind::PriorityChanged(uid(&app, 2), Priority::High),
+            EventKind::TagsChanged(uid(&app, 1), vec!["work".to_string()]),
+            EventKind::TagsChanged(uid(&app, 3), vec!["Work".to_string(), "q3".to_string()]),
+            EventKind::DueChanged(uid(&app, 2), Some("2026-11-01".parse::<Date>().unwrap())),
+            EventKind::DueChanged(uid(&app, 3), Some("2026-10-25".parse::<Date>().unwrap())),
+            EventKind::NotesChanged(uid(&app, 1), "Draft first".to_string()),
+            EventKind::NotesChanged(uid(&app, 3), " Draft first ".to_string()),
+            EventKind::NotesChanged(uid(&app, 2), "Ask Sam".to_string()),
+        ])
+        .unwrap();
+        let groups = app.duplicate_groups(0.9, false);
+        let deleted = app.merge_duplicates(&groups, &env::temp_dir()).unwrap();
+        assert_eq!(deleted.iter().map(|t| t.id).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(app.tasks.len(), 1);
+        let kept = &app.tasks[&1];
+        assert_eq!(kept.priority, Priority::High);
+        assert_eq!(kept.tags, ["work", "q3"]);
+        assert_eq!(kept.due.unwrap().to_string(), "2026-10-25");
+        assert_eq!(kept.notes, "Draft first\n\nAsk Sam");
+    }
+
+    #[test]
+    fn merging_moves_copied_attachments_to_the_kept_task() {
+        let dir = env::temp_dir().join(format!("day21-dedupe-{}", process::id()));
+        let data_dir = dir.join("todo.txt.files");
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("notes.txt");
+        fs::write(&source, "minutes").unwrap();
+
+        let mut app = app_with(&["Plan trip", "plan trip", "Plan trip."]);
+        for id in [1, 2] {
+            let copy = attachment::attach(&data_dir, &app.tasks[&id], &source, true).unwrap();
+            app.set_attachments(id, vec![copy]).unwrap();
+        }
+        let reference = attachment::attach(&data_dir, &app.tasks[&3], &source, false).unwrap();
+        app.set_attachments(3, vec![reference.clone()]).unwrap();
+        let old_dir = data_dir.join(app.tasks[&2].uid.to_string());
+
+        let groups = app.duplicate_groups(0.9, false);
+        let deleted = app.merge_duplicates(&groups, &data_dir).unwrap();
+        let kept = app.tasks[&1].clone();
+        let paths: Vec<String> = kept
+            .attachments
+            .iter()
+            .map(|a| a.path.display().to_string())
+            .collect();
+        let kept_dir = kept.uid.to_string();
+        assert_eq!(
+            paths,
+            [
+                format!("{}/notes.txt", kept_dir),
+                format!("{}/2-notes.txt", kept_dir),
+                source.display().to_string(),
+            ]
+        );
+        assert_eq!(kept.attachments[2], reference);
+        for attachment in &kept.attachments {
+            assert!(attachment.location(&data_dir).is_file());
+        }
+
+        // What the cli does once the merge is saved.
+        for task in &deleted {
+            attachment::remove_task_files(&data_dir, task).unwrap();
+        }
+        let old_dir_gone = !old_dir.exists();
+        let kept_files = kept
+            .attachments
+            .iter()
+            .all(|a| a.location(&data_dir).is_file());
+        let source_left = source.is_file();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(old_dir_gone);
+        assert!(kept_files);
+        assert!(source_left);
+    }
+}
@@ -0,0 +1,80 @@
+// Amounts of work, for estimates and the effort actually spent on a task.
+// Written like "45m", "2h", "1h30m", "1.5h" or "2d" (a day being eight
+// working hours); a bare number means hours. Stored as whole minutes.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::TodoError;
+
+const MINUTES_PER_HOUR: u32 = 60;
+const HOURS_PER_DAY: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub(crate) struct Effort(u32);
+
+impl Effort {
+    pub(crate) fn minutes(self) -> u32 {
+        self.0
+    }
+
+    pub(crate) fn from_minutes(minutes: u32) -> Self {
+        Effort(minutes)
+    }
+}
+
+impl fmt::Display for Effort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hours, minutes) = (self.0 / MINUTES_PER_HOUR, self.0 % MINUTES_PER_HOUR);
+        match (hours, minutes) {
+            (0, m) => write!(f, "{}m", m),
+            (h, 0) => write!(f, "{}h", h),
+            (h, m) => write!(f, "{}h{}m", h, m),
+        }
+    }
+}
+
+impl FromStr for Effort {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || TodoError::InvalidInput(t!("error.bad_effort", value = s));
+        let text = s.trim().to_lowercase();
+        if text.is_empty() {
+            return Err(bad());
+        }
+        if let Ok(hours) = text.parse::<f64>() {
+            return to_minutes(hours, MINUTES_PER_HOUR).ok_or_else(bad);
+        }
+
+        // A sequence of <number><unit> parts, e.g. "1h30m".
+        let mut total = 0u32;
+        let mut number = String::new();
+        for c in text.chars() {
+            if c.is_ascii_digit() || c == '.' {
+                number.push(c);
+                continue;
+            }
+            let per_unit = match c {
+                'd' => HOURS_PER_DAY * MINUTES_PER_HOUR,
+                'h' => MINUTES_PER_HOUR,
+                'm' => 1,
+                _ => return Err(bad()),
+            };
+            let value: f64 = number.parse().map_err(|_| bad())?;
+            let minutes = to_minutes(value, per_unit).ok_or_else(bad)?;
+            total = total.checked_add(minutes.0).ok_or_else(bad)?;
+            number.clear();
+        }
+        if !number.is_empty() {
+            return Err(bad());
+        }
+        Ok(Effort(total))
+    }
+}
+
+fn to_minutes(value: f64, per_unit: u32) -> Option<Effort> {
+    let minutes = (value * per_unit as f64).round();
+    (value.is_finite() && value >= 0.0 && minutes <= u32::MAX as f64)
+        .then_some(Effort(minutes as u32))
+}
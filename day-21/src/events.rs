@@ -0,0 +1,452 @@
+// Event sourcing for the task list.
+// Every change to `TodoApp::tasks` is recorded as an event in an append-only
+// log; the current state (or the state at any earlier moment) is rebuilt by
+// replaying those events in order.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::attachment::Attachment;
+use crate::date::{self, Date};
+use crate::effort::Effort;
+use crate::uid::TaskUid;
+use crate::{Priority, Status, Task, TodoApp, TodoError};
+
+#[derive(Debug, Clone)]
+pub(crate) enum EventKind {
+    TaskAdded(Task),
+    TaskCompleted(TaskUid),
+    TitleChanged(TaskUid, String),
+    StatusChanged(TaskUid, Status),
+    PriorityChanged(TaskUid, Priority),
+    TagsChanged(TaskUid, Vec<String>),
+    DueChanged(TaskUid, Option<Date>),
+    EstimateChanged(TaskUid, Option<Effort>),
+    SpentChanged(TaskUid, Option<Effort>),
+    NotesChanged(TaskUid, String),
+    AttachmentsChanged(TaskUid, Vec<Attachment>),
+    TaskDeleted(TaskUid),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Event {
+    pub(crate) seq: u64,
+    // Seconds since the Unix epoch.
+    pub(crate) at: u64,
+    pub(crate) kind: EventKind,
+}
+
+impl EventKind {
+    pub(crate) fn uid(&self) -> TaskUid {
+        match self {
+            EventKind::TaskAdded(task) => task.uid,
+            EventKind::TaskCompleted(uid)
+            | EventKind::TitleChanged(uid, _)
+            | EventKind::StatusChanged(uid, _)
+            | EventKind::PriorityChanged(uid, _)
+            | EventKind::TagsChanged(uid, _)
+            | EventKind::DueChanged(uid, _)
+            | EventKind::EstimateChanged(uid, _)
+            | EventKind::SpentChanged(uid, _)
+            | EventKind::NotesChanged(uid, _)
+            | EventKind::AttachmentsChanged(uid, _)
+            | EventKind::TaskDeleted(uid) => *uid,
+        }
+    }
+
+    // The name of the kind of change, as written to the todo file.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            EventKind::TaskAdded(_) => "added",
+            EventKind::TaskCompleted(_) => "completed",
+            EventKind::TitleChanged(..) => "title",
+            EventKind::StatusChanged(..) => "status",
+            EventKind::PriorityChanged(..) => "priority",
+            EventKind::TagsChanged(..) => "tags",
+            EventKind::DueChanged(..) => "due",
+            EventKind::EstimateChanged(..) => "estimate",
+            EventKind::SpentChanged(..) => "spent",
+            EventKind::NotesChanged(..) => "notes",
+            EventKind::AttachmentsChanged(..) => "attachments",
+            EventKind::TaskDeleted(_) => "deleted",
+        }
+    }
+
+    // The task field the change sets; None for adding and deleting tasks.
+    pub(crate) fn field(&self) -> Option<&'static str> {
+        match self {
+            EventKind::TaskAdded(_) | EventKind::TaskDeleted(_) => None,
+            EventKind::TaskCompleted(_) => Some("status"),
+            other => Some(other.name()),
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            EventKind::TaskAdded(task) => t!("event.added", task = task),
+            EventKind::TaskCompleted(_) => t!("event.completed"),
+            EventKind::TitleChanged(_, title) => t!("event.title", title = title),
+            EventKind::StatusChanged(_, status) => t!("event.status", status = status),
+            EventKind::PriorityChanged(_, priority) => t!("event.priority", priority = priority),
+            EventKind::TagsChanged(_, tags) if tags.is_empty() => t!("event.tags_cleared"),
+            EventKind::TagsChanged(_, tags) => t!("event.tags", tags = tags.join(", ")),
+            EventKind::DueChanged(_, Some(due)) => t!("event.due", date = due),
+            EventKind::DueChanged(_, None) => t!("event.due_cleared"),
+            EventKind::EstimateChanged(_, Some(effort)) => t!("event.estimate", effort = effort),
+            EventKind::EstimateChanged(_, None) => t!("event.estimate_cleared"),
+            EventKind::SpentChanged(_, Some(effort)) => t!("event.spent", effort = effort),
+            EventKind::SpentChanged(_, None) => t!("event.spent_cleared"),
+            EventKind::NotesChanged(_, notes) => tn!("event.notes", notes.lines().count()),
+            EventKind::AttachmentsChanged(_, attachments) => {
+                tn!("event.attachments", attachments.len())
+            }
+            EventKind::TaskDeleted(_) => t!("event.deleted"),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl TodoApp {
+    // Records a single change: applies it to the current state and appends it to the log.
+    pub(crate) fn record(&mut self, kind: EventKind) -> Result<(), TodoError> {
+        self.record_all(vec![kind])
+    }
+
+    // Records several changes as one transaction: if any of them cannot be
+    // applied, the state and the log are left exactly as they were.
+    pub(crate) fn record_all(&mut self, kinds: Vec<EventKind>) -> Result<(), TodoError> {
+        // Only the tasks an event touches are saved for the rollback, so a
+        // small change stays cheap however many tasks there are.
+        let next_id = self.next_id;
+        let at = now();
+        let mut undo: Vec<(u32, Option<Task>)> = Vec::new();
+        for kind in &kinds {
+            let id = match kind {
+                EventKind::TaskAdded(task) => Some(task.id),
+                other => self.index.id_of(other.uid()),
+            };
+            let before = id.map(|id| (id, self.tasks.get(&id).cloned()));
+            if let Err(e) = self.apply_event(kind, at) {
+                for (id, task) in undo.into_iter().rev() {
+                    self.remove_task(id);
+                    if let Some(task) = task {
+                        self.insert_task(task);
+                    }
+                }
+                self.next_id = next_id;
+                return Err(e);
+            }
+            undo.extend(before);
+        }
+
+        for kind in kinds {
+            let seq = self.last_seq() + 1;
+            self.log.push(Event { seq, at, kind });
+        }
+        Ok(())
+    }
+
+    // Replays `events`, recorded against an earlier copy of the list, on top of
+    // this one, e.g. after another program saved the file in the meantime.
+    // Added tasks get the next free numbers. Returns the events that no
+    // longer apply, such as changes to a task deleted elsewhere.
+    pub(crate) fn replay(&mut self, events: Vec<Event>) -> Vec<(Event, TodoError)> {
+        let mut skipped = Vec::new();
+        for mut event in events {
+            if let EventKind::TaskAdded(task) = &mut event.kind {
+                task.id = self.next_id;
+            }
+            match self.apply_event(&event.kind, event.at) {
+                Ok(()) => {
+                    event.seq = self.last_seq() + 1;
+                    self.log.push(event);
+                }
+                Err(e) => skipped.push((event, e)),
+            }
+        }
+        skipped
+    }
+
+    pub(crate) fn last_seq(&self) -> u64 {
+        self.log.last().map(|e| e.seq).unwrap_or(self.snapshot_seq)
+    }
+
+    // Applies one event, recorded at `at`, to the current state without
+    // logging it.
+    pub(crate) fn apply_event(&mut self, kind: &EventKind, at: u64) -> Result<(), TodoError> {
+        // Tasks added as already completed keep whatever date they carry:
+        // when they were really completed is not known.
+        if let EventKind::TaskAdded(task) = kind {
+            // Adding a task again would silently replace the one there.
+            if self.tasks.contains_key(&task.id) || self.index.id_of(task.uid).is_some() {
+                return Err(TodoError::InvalidInput(t!(
+                    "error.task_exists",
+                    id = task.id,
+                    uid = task.uid.short()
+                )));
+            }
+            self.index.record_created(task.uid, at);
+            self.insert_task(task.clone());
+            return Ok(());
+        }
+
+        let id = self
+            .index
+            .id_of(kind.uid())
+            .ok_or_else(|| TodoError::NoMatch(kind.uid().to_string()))?;
+        let mut task = self.remove_task(id).expect("indexed task exists");
+        match kind {
+            EventKind::TaskAdded(_) => unreachable!("handled above"),
+            EventKind::TaskDeleted(_) => return Ok(()),
+            EventKind::TaskCompleted(_) | EventKind::StatusChanged(_, Status::Completed) => {
+                if task.status != Status::Completed {
+                    task.completed = Some(Date::from_timestamp(at));
+                }
+                task.status = Status::Completed;
+            }
+            EventKind::StatusChanged(_, status) => {
+                task.status = *status;
+                task.completed = None;
+            }
+            EventKind::TitleChanged(_, title) => task.title = title.clone(),
+            EventKind::PriorityChanged(_, priority) => task.priority = *priority,
+            EventKind::TagsChanged(_, tags) => task.tags = tags.clone(),
+            EventKind::DueChanged(_, due) => task.due = *due,
+            EventKind::EstimateChanged(_, estimate) => task.estimate = *estimate,
+            EventKind::SpentChanged(_, spent) => task.spent = *spent,
+            EventKind::NotesChanged(_, notes) => task.notes = notes.clone(),
+            EventKind::AttachmentsChanged(_, attachments) => task.attachments = attachments.clone(),
+        }
+        self.insert_task(task);
+        Ok(())
+    }
+
+    // Adds or replaces a task, keeping the indexes in step.
+    pub(crate) fn insert_task(&mut self, task: Task) {
+        self.remove_task(task.id);
+        self.next_id = self.next_id.max(task.id + 1);
+        self.index.insert(&task);
+        self.tasks.insert(task.id, task);
+    }
+
+    pub(crate) fn remove_task(&mut self, id: u32) -> Option<Task> {
+        let task = self.tasks.remove(&id)?;
+        self.index.remove(&task);
+        Some(task)
+    }
+
+    // Rebuilds the task list as it was at `at` (seconds since the epoch) by
+    // replaying the log from the beginning.
+    pub(crate) fn as_of(&self, at: u64) -> Result<TodoApp, TodoError> {
+        let mut past = TodoApp::new();
+        past.sort = self.sort.clone();
+        past.group = self.group;
+        past.show_uids = self.show_uids;
+        for event in self.log.iter().take_while(|e| e.at <= at) {
+            past.apply_event(&event.kind, event.at)?;
+            past.log.push(event.clone());
+        }
+        Ok(past)
+    }
+
+    // Prints every event recorded for the task with `uid`, oldest first.
+    pub(crate) fn print_history(&self, uid: TaskUid) {
+        let mut found = false;
+        for event in self.log.iter().filter(|e| e.kind.uid() == uid) {
+            println!(
+                "  #{:<5} {}  {}",
+                event.seq,
+                date::format_timestamp(event.at),
+                event.kind
+            );
+            found = true;
+        }
+        if !found {
+            println!("  {}", t!("event.no_history", uid = uid));
+        }
+    }
+
+    // Prints the last `limit` events of the whole log, oldest first.
+    pub(crate) fn print_log(&self, limit: usize) {
+        let start = self.log.len().saturating_sub(limit);
+        for event in &self.log[start..] {
+            let task = match &event.kind {
+                EventKind::TaskAdded(task) => format!("#{}", task.id),
+                other => other.uid().short(),
+            };
+            println!(
+                "  #{:<5} {}  {:<8} {}",
+                event.seq,
+                date::format_timestamp(event.at),
+                task,
+                event.kind
+            );
+        }
+    }
+
+    // Finds the uid of a task by display number or uid prefix, including
+    // tasks that have since been deleted.
+    pub(crate) fn resolve_uid(&self, reference: &str) -> Result<TaskUid, TodoError> {
+        if let Ok(id) = self.resolve(reference) {
+            return Ok(self.tasks[&id].uid);
+        }
+        let prefix: String = reference
+            .chars()
+            .filter(|&c| c != '-')
+            .collect::<String>()
+            .to_lowercase();
+        let mut matches: HashSet<TaskUid> = HashSet::new();
+        if prefix.len() >= 4 {
+            for event in &self.log {
+                let uid = event.kind.uid();
+                if uid.hex().starts_with(&prefix) {
+                    matches.insert(uid);
+                }
+            }
+        }
+        match matches.len() {
+            0 => Err(TodoError::NoMatch(reference.to_string())),
+            1 => Ok(matches.into_iter().next().expect("one match")),
+            n => Err(TodoError::AmbiguousId(reference.to_string(), n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32, title: &str, uid: &str) -> Task {
+        let mut task = Task::new(id, title, Priority::Medium, Vec::new());
+        task.uid = uid.parse().unwrap();
+        task
+    }
+
+    fn event(seq: u64, at: u64, kind: EventKind) -> Event {
+        Event { seq, at, kind }
+    }
+
+    const FIRST: &str = "aaaa1111-0000-4000-8000-000000000001";
+    const SECOND: &str = "aaaa2222-0000-4000-8000-000000000002";
+    const THIRD: &str = "bbbb3333-0000-4000-8000-000000000003";
+
+    #[test]
+    fn failed_batch_leaves_state_and_log_unchanged() {
+        let mut app = TodoApp::new();
+        app.record(EventKind::TaskAdded(task(1, "first", FIRST)))
+            .unwrap();
+        let uid = app.tasks[&1].uid;
+
+        let result = app.record_all(vec![
+            EventKind::TitleChanged(uid, "renamed".to_string()),
+            EventKind::TaskAdded(task(2, "second", SECOND)),
+            EventKind::TaskCompleted(THIRD.parse().unwrap()),
+        ]);
+
+        assert!(matches!(result, Err(TodoError::NoMatch(_))));
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.tasks[&1].title, "first");
+        assert_eq!(app.log.len(), 1);
+        assert_eq!(app.next_id, 2);
+        assert!(app.index.id_of(SECOND.parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn adding_an_existing_task_again_is_rejected() {
+        let mut app = TodoApp::new();
+        app.record(EventKind::TaskAdded(task(1, "first", FIRST)))
+            .unwrap();
+
+        let same_id = app.record(EventKind::TaskAdded(task(1, "other", SECOND)));
+        let same_uid = app.record(EventKind::TaskAdded(task(2, "other", FIRST)));
+
+        assert!(matches!(same_id, Err(TodoError::InvalidInput(_))));
+        assert!(matches!(same_uid, Err(TodoError::InvalidInput(_))));
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(app.tasks[&1].title, "first");
+        assert_eq!(app.log.len(), 1);
+    }
+
+    #[test]
+    fn replay_renumbers_added_tasks_and_returns_stale_events() {
+        let mut app = TodoApp::new();
+        app.record(EventKind::TaskAdded(task(1, "mine", FIRST)))
+            .unwrap();
+        let gone: TaskUid = THIRD.parse().unwrap();
+
+        // Recorded elsewhere against a list where #1 was a different task.
+        let skipped = app.replay(vec![
+            event(1, 10, EventKind::TaskAdded(task(1, "theirs", SECOND))),
+            event(2, 20, EventKind::TaskCompleted(SECOND.parse().unwrap())),
+            event(3, 30, EventKind::TaskCompleted(gone)),
+        ]);
+
+        assert_eq!(app.tasks[&1].title, "mine");
+        assert_eq!(app.tasks[&2].title, "theirs");
+        assert_eq!(app.tasks[&2].status, Status::Completed);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0.kind.uid(), gone);
+        let seqs: Vec<u64> = app.log.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn as_of_rebuilds_the_list_at_a_past_time() {
+        let mut app = TodoApp::new();
+        let skipped = app.replay(vec![
+            event(1, 100, EventKind::TaskAdded(task(1, "first", FIRST))),
+            event(2, 200, EventKind::TaskAdded(task(2, "second", SECOND))),
+            event(
+                3,
+                300,
+                EventKind::TitleChanged(FIRST.parse().unwrap(), "renamed".into()),
+            ),
+            event(4, 400, EventKind::TaskDeleted(SECOND.parse().unwrap())),
+        ]);
+        assert!(skipped.is_empty());
+
+        assert!(app.as_of(50).unwrap().tasks.is_empty());
+        let past = app.as_of(250).unwrap();
+        assert_eq!(past.tasks.len(), 2);
+        assert_eq!(past.tasks[&1].title, "first");
+        assert_eq!(past.log.len(), 2);
+        let later = app.as_of(400).unwrap();
+        assert_eq!(later.tasks.len(), 1);
+        assert_eq!(later.tasks[&1].title, "renamed");
+    }
+
+    #[test]
+    fn resolve_uid_finds_deleted_tasks_by_prefix() {
+        let mut app = TodoApp::new();
+        for (id, uid) in [(1, FIRST), (2, SECOND), (3, THIRD)] {
+            app.record(EventKind::TaskAdded(task(id, "task", uid)))
+                .unwrap();
+        }
+        let third: TaskUid = THIRD.parse().unwrap();
+        app.record(EventKind::TaskDeleted(third)).unwrap();
+
+        assert_eq!(app.resolve_uid("1").unwrap(), FIRST.parse().unwrap());
+        assert_eq!(app.resolve_uid("bbbb").unwrap(), third);
+        assert_eq!(app.resolve_uid(THIRD).unwrap(), third);
+        assert!(matches!(
+            app.resolve_uid("aaaa"),
+            Err(TodoError::AmbiguousId(_, 2))
+        ));
+        // Prefixes shorter than four digits are not looked up in the log.
+        assert!(matches!(app.resolve_uid("bbb"), Err(TodoError::NoMatch(_))));
+        assert!(matches!(
+            app.resolve_uid("cccc"),
+            Err(TodoError::NoMatch(_))
+        ));
+    }
+}
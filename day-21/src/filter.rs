@@ -0,0 +1,188 @@
+// Task selection used by the bulk commands.
+// A filter is a set of optional criteria; a task matches when it satisfies all of them.
+
+use std::ops::RangeInclusive;
+
+use crate::{Priority, Status, Task, TodoApp, TodoError};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TaskFilter {
+    pub(crate) ids: Vec<RangeInclusive<u32>>,
+    pub(crate) status: Option<Status>,
+    pub(crate) tag: Option<String>,
+    pub(crate) priority: Option<Priority>,
+    pub(crate) text: Option<String>,
+}
+
+impl TaskFilter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+            && self.status.is_none()
+            && self.tag.is_none()
+            && self.priority.is_none()
+            && self.text.is_none()
+    }
+
+    pub(crate) fn matches(&self, task: &Task) -> bool {
+        if !self.ids.is_empty() && !self.ids.iter().any(|r| r.contains(&task.id)) {
+            return false;
+        }
+        if self.status.is_some_and(|s| s != task.status) {
+            return false;
+        }
+        if self.priority.is_some_and(|p| p != task.priority) {
+            return false;
+        }
+        if let Some(tag) = &self.tag
+            && !task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+        {
+            return false;
+        }
+        if let Some(text) = &self.text
+            && !task.title.to_lowercase().contains(&text.to_lowercase())
+        {
+            return false;
+        }
+        true
+    }
+}
+
+// Parses id lists such as "1-5,8,10-12". Ranges may overlap; backwards ranges
+// and 0, which no task has, are rejected.
+pub(crate) fn parse_id_ranges(input: &str) -> Result<Vec<RangeInclusive<u32>>, TodoError> {
+    let bad = |part: &str| TodoError::InvalidInput(t!("error.bad_id_range", value = part));
+    let mut ranges = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let range = match part.split_once('-') {
+            Some((start, end)) => {
+                let start: u32 = start.trim().parse().map_err(|_| bad(part))?;
+                let end: u32 = end.trim().parse().map_err(|_| bad(part))?;
+                if start == 0 || start > end {
+                    return Err(bad(part));
+                }
+                start..=end
+            }
+            None => {
+                let id: u32 = part.parse().map_err(|_| bad(part))?;
+                if id == 0 {
+                    return Err(bad(part));
+                }
+                id..=id
+            }
+        };
+        ranges.push(range);
+    }
+    Ok(ranges)
+}
+
+impl TodoApp {
+    // Returns the ids of every task matching `filter`, in ascending order.
+    // The status, priority or tag index narrows down the tasks to check.
+    pub(crate) fn select(&self, filter: &TaskFilter) -> Vec<u32> {
+        let candidates: Vec<u32> = if let Some(status) = filter.status {
+            self.index.with_status(status).collect()
+        } else if let Some(tag) = &filter.tag {
+            self.index.with_tag(tag).collect()
+        } else if let Some(priority) = filter.priority {
+            self.index.with_priority(priority).collect()
+        } else {
+            let mut ids: Vec<u32> = self.tasks.keys().copied().collect();
+            ids.sort_unstable();
+            ids
+        };
+        candidates
+            .into_iter()
+            .filter(|id| filter.matches(&self.tasks[id]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> TodoApp {
+        let mut app = TodoApp::new();
+        app.add_task("Write report", Priority::High, "work");
+        app.add_task("Buy milk", Priority::Low, "home");
+        app.add_task("Review report", Priority::Medium, "Work");
+        app.add_task("Call plumber", Priority::High, "");
+        app.add_task("File report", Priority::High, "work");
+        app.complete_task(5).unwrap();
+        app
+    }
+
+    #[test]
+    fn parses_ids_and_ranges() {
+        assert_eq!(
+            parse_id_ranges("1-3, 5,,7-7").unwrap(),
+            vec![1..=3, 5..=5, 7..=7]
+        );
+        // Overlapping ranges are kept as given; a task in both matches once.
+        assert_eq!(parse_id_ranges("1-4,3-6").unwrap(), vec![1..=4, 3..=6]);
+        assert!(parse_id_ranges("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        for bad in [
+            "3-1",
+            "0",
+            "0-2",
+            "a",
+            "1-",
+            "-2",
+            "1-2-3",
+            "-1",
+            "4294967296",
+        ] {
+            assert!(
+                matches!(parse_id_ranges(bad), Err(TodoError::InvalidInput(_))),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn select_combines_criteria() {
+        let app = app();
+        let select = |filter: TaskFilter| app.select(&filter);
+
+        assert_eq!(select(TaskFilter::default()), vec![1, 2, 3, 4, 5]);
+        let tag = TaskFilter {
+            tag: Some("WORK".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(select(tag.clone()), vec![1, 3, 5]);
+        assert_eq!(
+            select(TaskFilter {
+                status: Some(Status::Pending),
+                ..tag.clone()
+            }),
+            vec![1, 3]
+        );
+        assert_eq!(
+            select(TaskFilter {
+                priority: Some(Priority::High),
+                text: Some("REPORT".to_string()),
+                ..Default::default()
+            }),
+            vec![1, 5]
+        );
+        assert_eq!(
+            select(TaskFilter {
+                ids: parse_id_ranges("2-3,3-4").unwrap(),
+                ..Default::default()
+            }),
+            vec![2, 3, 4]
+        );
+        assert!(
+            select(TaskFilter {
+                ids: vec![6..=9],
+                ..tag
+            })
+            .is_empty()
+        );
+    }
+}
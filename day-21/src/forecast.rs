@@ -0,0 +1,368 @@
+// Forecasting when the open tasks will be done.
+// Throughput is the number of tasks completed in each of the last weeks, by
+// their recorded completion dates. A Monte Carlo simulation then replays
+// randomly picked past weeks until the open tasks run out, many times over,
+// and the spread of the results gives the confidence ranges. Estimates are
+// summed for the open tasks and corrected by how far the actual effort of
+// completed tasks was from their estimates.
+// `forecast [--tag T] [--weeks N] [--runs N]` prints the result.
+
+use crate::cli::take_option;
+use crate::date::Date;
+use crate::effort::Effort;
+use crate::{Status, TodoApp, TodoError};
+
+const DEFAULT_WEEKS: usize = 12;
+const DEFAULT_RUNS: usize = 10_000;
+// Upper bounds for --weeks and --runs; the history and the simulation results
+// are held in memory, one entry per week and per run.
+const MAX_HISTORY_WEEKS: usize = 520;
+const MAX_RUNS: usize = 1_000_000;
+// Runs that have not finished after this many weeks are given up on.
+const MAX_WEEKS: usize = 520;
+const PERCENTILES: [usize; 3] = [50, 85, 95];
+
+#[derive(Debug)]
+pub(crate) struct Forecast {
+    pub(crate) open: usize,
+    // Tasks completed in each past week, the most recent first.
+    pub(crate) weekly: Vec<usize>,
+    pub(crate) remaining: Effort,
+    pub(crate) unestimated: usize,
+    // Actual effort divided by the estimate, over the completed tasks that
+    // have both, and the number of those tasks.
+    pub(crate) accuracy: Option<(f64, usize)>,
+    // Weeks needed for each of PERCENTILES, or None if beyond MAX_WEEKS;
+    // empty when there is nothing to simulate.
+    pub(crate) weeks_needed: Vec<(usize, Option<usize>)>,
+}
+
+impl Forecast {
+    // The remaining estimate corrected by the estimation accuracy.
+    pub(crate) fn adjusted(&self) -> Option<Effort> {
+        let (ratio, _) = self.accuracy?;
+        let minutes = (self.remaining.minutes() as f64 * ratio).round();
+        Some(Effort::from_minutes(minutes.min(u32::MAX as f64) as u32))
+    }
+
+    // The weeks needed for each of PERCENTILES together with the day they end,
+    // counted from the day of `now`.
+    pub(crate) fn done_by(&self, now: u64) -> Vec<(usize, Option<(usize, Date)>)> {
+        let today = Date::from_timestamp(now).days();
+        self.weeks_needed
+            .iter()
+            .map(|&(percent, weeks)| {
+                let end = weeks.map(|weeks| (weeks, Date::from_days(today + 7 * weeks as i64)));
+                (percent, end)
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn run(app: &TodoApp, mut args: Vec<String>, now: u64) -> Result<(), TodoError> {
+    let tag = take_option(&mut args, "--tag")?;
+    let weeks = match take_option(&mut args, "--weeks")? {
+        Some(value) => within(&value, MAX_HISTORY_WEEKS).ok_or_else(|| {
+            TodoError::InvalidInput(t!(
+                "error.bad_weeks",
+                value = value,
+                max = MAX_HISTORY_WEEKS
+            ))
+        })?,
+        None => DEFAULT_WEEKS,
+    };
+    let runs = match take_option(&mut args, "--runs")? {
+        Some(value) => within(&value, MAX_RUNS).ok_or_else(|| {
+            TodoError::InvalidInput(t!("error.bad_runs", value = value, max = MAX_RUNS))
+        })?,
+        None => DEFAULT_RUNS,
+    };
+
+    let forecast = app.forecast(tag.as_deref(), weeks, runs, now);
+    if forecast.open == 0 {
+        match &tag {
+            Some(tag) => println!("{}", t!("forecast.nothing_open_tag", tag = tag)),
+            None => println!("{}", t!("forecast.nothing_open")),
+        }
+        return Ok(());
+    }
+    match &tag {
+        Some(tag) => println!("{}", tn!("forecast.scope_tag", forecast.open, tag = tag)),
+        None => println!("{}", tn!("forecast.scope", forecast.open)),
+    }
+
+    let completed: usize = forecast.weekly.iter().sum();
+    if completed == 0 {
+        println!("{}", tn!("forecast.no_history", weeks));
+    } else {
+        println!(
+            "{}",
+            tn!(
+                "forecast.throughput",
+                weeks,
+                average = format!("{:.1}", completed as f64 / weeks as f64),
+                min = forecast.weekly.iter().min().expect("weeks > 0"),
+                max = forecast.weekly.iter().max().expect("weeks > 0")
+            )
+        );
+    }
+
+    if forecast.remaining > Effort::default() {
+        println!("{}", t!("forecast.remaining", effort = forecast.remaining));
+    }
+    if forecast.unestimated > 0 {
+        println!("{}", tn!("forecast.unestimated", forecast.unestimated));
+    }
+    if let Some((ratio, count)) = forecast.accuracy {
+        println!(
+            "{}",
+            tn!("forecast.accuracy", count, ratio = format!("{:.2}", ratio))
+        );
+        if let Some(adjusted) = forecast
+            .adjusted()
+            .filter(|_| forecast.remaining.minutes() > 0)
+        {
+            println!("{}", t!("forecast.adjusted", effort = adjusted));
+        }
+    }
+
+    if forecast.weeks_needed.is_empty() {
+        return Ok(());
+    }
+    println!("\n{}", tn!("forecast.simulated", runs));
+    for (percent, end) in forecast.done_by(now) {
+        match end {
+            Some((weeks, date)) => println!(
+                "  {}",
+                tn!("forecast.percentile", weeks, percent = percent, date = date)
+            ),
+            None => println!(
+                "  {}",
+                t!("forecast.beyond", percent = percent, weeks = MAX_WEEKS)
+            ),
+        }
+    }
+    Ok(())
+}
+
+impl TodoApp {
+    // Forecasts the open tasks, or those tagged `tag`, from the `weeks` weeks
+    // before `now` with `runs` simulated runs.
+    pub(crate) fn forecast(
+        &self,
+        tag: Option<&str>,
+        weeks: usize,
+        runs: usize,
+        now: u64,
+    ) -> Forecast {
+        let today = Date::from_timestamp(now).days();
+        let in_scope = self
+            .tasks
+            .values()
+            .filter(|t| tag.is_none_or(|tag| t.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))));
+
+        let mut open = 0;
+        let mut weekly = vec![0; weeks];
+        let mut remaining = 0u32;
+        let mut unestimated = 0;
+        let (mut estimated, mut spent, mut measured) = (0u64, 0u64, 0);
+        for task in in_scope {
+            if task.status != Status::Completed {
+                open += 1;
+                match task.estimate {
+                    Some(estimate) => remaining = remaining.saturating_add(estimate.minutes()),
+                    None => unestimated += 1,
+                }
+                continue;
+            }
+            if let Some(day) = task.completed {
+                let age = today - day.days();
+                if (0..7 * weeks as i64).contains(&age) {
+                    weekly[age as usize / 7] += 1;
+                }
+            }
+            if let (Some(estimate), Some(actual)) = (task.estimate, task.spent)
+                && estimate.minutes() > 0
+            {
+                estimated += estimate.minutes() as u64;
+                spent += actual.minutes() as u64;
+                measured += 1;
+            }
+        }
+
+        let weeks_needed = if open > 0 && weekly.iter().any(|&n| n > 0) {
+            simulate(open, &weekly, runs)
+        } else {
+            Vec::new()
+        };
+        Forecast {
+            open,
+            weekly,
+            remaining: Effort::from_minutes(remaining),
+            unestimated,
+            accuracy: (measured > 0).then(|| (spent as f64 / estimated as f64, measured)),
+            weeks_needed,
+        }
+    }
+}
+
+// Runs the simulation and reads the weeks needed at each of PERCENTILES.
+// A fixed seed makes the forecast the same every time for the same data.
+fn simulate(open: usize, weekly: &[usize], runs: usize) -> Vec<(usize, Option<usize>)> {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = |n: usize| {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) as usize % n
+    };
+
+    let mut results: Vec<usize> = (0..runs)
+        .map(|_| {
+            let mut left = open;
+            let mut weeks = 0;
+            while left > 0 && weeks <= MAX_WEEKS {
+                left = left.saturating_sub(weekly[next(weekly.len())]);
+                weeks += 1;
+            }
+            weeks
+        })
+        .collect();
+    results.sort_unstable();
+    PERCENTILES
+        .iter()
+        .map(|&percent| {
+            let weeks = results[(runs * percent).div_ceil(100) - 1];
+            (percent, (weeks <= MAX_WEEKS).then_some(weeks))
+        })
+        .collect()
+}
+
+// A count from 1 to `max`.
+fn within(value: &str, max: usize) -> Option<usize> {
+    value.parse().ok().filter(|n| (1..=max).contains(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+    use crate::{Priority, Task};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    // Monday, 2026-10-19.
+    fn now() -> u64 {
+        Date::new(2026, 10, 19).unwrap().days() as u64 * 86_400
+    }
+
+    // `open` open tasks, and `weekly[n]` tasks completed n weeks ago.
+    fn app_with(open: usize, weekly: &[usize]) -> TodoApp {
+        let today = Date::from_timestamp(now()).days();
+        let mut app = TodoApp::new();
+        let mut id = 1;
+        let mut add = |done: Option<Date>| {
+            let mut task = Task::new(id, "task", Priority::Medium, vec!["work".to_string()]);
+            if done.is_some() {
+                task.status = Status::Completed;
+                task.completed = done;
+            }
+            app.record(EventKind::TaskAdded(task)).unwrap();
+            id += 1;
+        };
+        for _ in 0..open {
+            add(None);
+        }
+        for (week, &count) in weekly.iter().enumerate() {
+            for _ in 0..count {
+                add(Some(Date::from_days(today - 7 * week as i64 - 1)));
+            }
+        }
+        app
+    }
+
+    fn dates(forecast: &Forecast) -> Vec<(usize, Option<String>)> {
+        forecast
+            .done_by(now())
+            .into_iter()
+            .map(|(percent, end)| (percent, end.map(|(_, date)| date.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn steady_history_gives_one_date() {
+        let app = app_with(5, &[2, 2, 2, 2]);
+        let forecast = app.forecast(None, 4, 1_000, now());
+        assert_eq!(forecast.open, 5);
+        assert_eq!(forecast.weekly, vec![2, 2, 2, 2]);
+        let expected = Some("2026-11-09".to_string());
+        assert_eq!(
+            dates(&forecast),
+            vec![
+                (50, expected.clone()),
+                (85, expected.clone()),
+                (95, expected)
+            ]
+        );
+    }
+
+    #[test]
+    fn uneven_history_gives_fixed_dates() {
+        // Two tasks a week on average: about five weeks for ten tasks.
+        let mut app = app_with(10, &[4, 0, 1, 3]);
+        let forecast = app.forecast(None, 4, 10_000, now());
+        let expected = vec![
+            (50, Some("2026-11-23".to_string())),
+            (85, Some("2026-12-07".to_string())),
+            (95, Some("2026-12-21".to_string())),
+        ];
+        assert_eq!(dates(&forecast), expected);
+        // The fixed seed gives the same answer on every run.
+        assert_eq!(dates(&app.forecast(None, 4, 10_000, now())), expected);
+
+        // Tasks outside the tag are neither open nor history.
+        let other = Task::new(100, "other", Priority::Low, Vec::new());
+        app.record(EventKind::TaskAdded(other)).unwrap();
+        let tagged = app.forecast(Some("WORK"), 4, 10_000, now());
+        assert_eq!(tagged.open, 10);
+        assert_eq!(dates(&tagged), expected);
+    }
+
+    #[test]
+    fn no_history_gives_no_dates() {
+        let app = app_with(3, &[]);
+        let forecast = app.forecast(None, 12, 1_000, now());
+        assert_eq!(forecast.open, 3);
+        assert!(forecast.weekly.iter().all(|&n| n == 0));
+        assert!(forecast.weeks_needed.is_empty());
+        assert!(forecast.done_by(now()).is_empty());
+        // Completions older than the window do not count either.
+        let old = app_with(3, &[0, 0, 5]);
+        assert!(old.forecast(None, 2, 1_000, now()).weeks_needed.is_empty());
+        run(&app, Vec::new(), now()).unwrap();
+    }
+
+    #[test]
+    fn weeks_and_runs_are_bounded() {
+        let app = TodoApp::new();
+        for bad in [
+            &["--weeks", "0"][..],
+            &["--weeks", "521"],
+            &["--weeks", "18446744073709551615"],
+            &["--weeks", "many"],
+            &["--runs", "0"],
+            &["--runs", "1000001"],
+        ] {
+            let result = run(&app, args(bad), 0);
+            assert!(
+                matches!(result, Err(TodoError::InvalidInput(_))),
+                "{:?}: {:?}",
+                bad,
+                result
+            );
+        }
+        run(&app, args(&["--weeks", "520", "--runs", "1000000"]), 0).unwrap();
+    }
+}
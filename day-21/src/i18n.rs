@@ -0,0 +1,407 @@
+// Message catalogs for everything the app prints.
+// Messages are looked up by key in the catalog of the selected language,
+// falling back to English for keys it does not translate. A catalog file
+// holds one message per line, with {name} placeholders:
+//   # comment
+//   task.added = Added: {task}
+//   search.results.one = {count} result       (plural forms: zero, one, two,
+//   search.results.other = {count} results     few, many and other)
+//   usage <<<                                  (multi-line message, ended by
+//   ...                                         a line holding only >>>)
+//   >>>
+//
+// English and German are built in. The language comes from --lang, TODO_LANG,
+// LC_ALL, LC_MESSAGES or LANG, in that order; catalogs in TODO_LOCALE_DIR
+// (named like "de.txt" or "pt_BR.txt") take precedence over the built-in ones.
+// Renderers that take a `&Catalog` use it instead of the run's language.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+const BUILT_IN: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.txt")),
+    ("de", include_str!("../locales/de.txt")),
+];
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+thread_local! {
+    // The catalog set by `with_catalog` on this thread, if any.
+    static SCOPED: Cell<Option<&'static Catalog>> = const { Cell::new(None) };
+}
+
+// Looks up a message and fills in its placeholders.
+macro_rules! t {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::message(
+            $key,
+            None,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+// Like t!, but picks the plural form for `count`, which is also available
+// to the message as {count}.
+macro_rules! tn {
+    ($key:literal, $count:expr $(, $name:ident = $value:expr)* $(,)?) => {{
+        let count = $count;
+        $crate::i18n::message(
+            $key,
+            Some(count as u64),
+            &[
+                ("count", &count as &dyn ::std::fmt::Display)
+                $(, (stringify!($name), &$value as &dyn ::std::fmt::Display))*
+            ],
+        )
+    }};
+}
+
+pub(crate) struct Catalog {
+    // Most specific first, e.g. [de_AT, de, en]; English is always last.
+    layers: Vec<HashMap<String, String>>,
+    plural: fn(u64) -> &'static str,
+}
+
+// Selects the language for the rest of the run. `lang` overrides the
+// environment; later calls, and calls after the first message, have no effect.
+// To render in another language, pass a catalog from `Catalog::load`.
+pub(crate) fn init(lang: Option<&str>) {
+    let _ = CATALOG.set(Catalog::load(lang));
+}
+
+// The catalog of the run's language.
+pub(crate) fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::load(None))
+}
+
+// Runs `f` with every message on this thread, including those of Display
+// impls, taken from `catalog`.
+pub(crate) fn with_catalog<R>(catalog: &'static Catalog, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<&'static Catalog>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED.set(self.0);
+        }
+    }
+    let _restore = Restore(SCOPED.replace(Some(catalog)));
+    f()
+}
+
+pub(crate) fn message(key: &str, count: Option<u64>, args: &[(&str, &dyn fmt::Display)]) -> String {
+    SCOPED
+        .get()
+        .unwrap_or_else(catalog)
+        .message(key, count, args)
+}
+
+impl Catalog {
+    pub(crate) fn message(
+        &self,
+        key: &str,
+        count: Option<u64>,
+        args: &[(&str, &dyn fmt::Display)],
+    ) -> String {
+        let template = match count {
+            Some(n) => self
+                .lookup(&format!("{}.{}", key, (self.plural)(n)))
+                .or_else(|| self.lookup(&format!("{}.other", key)))
+                .or_else(|| self.lookup(key)),
+            None => self.lookup(key),
+        };
+        match template {
+            Some(template) => fill(template, args),
+            // A key missing from the English catalog as well is a bug; showing
+            // the key is more useful than showing nothing.
+            None => key.to_string(),
+        }
+    }
+
+    pub(crate) fn load(lang: Option<&str>) -> Self {
+        let requested = lang
+            .map(String::from)
+            .or_else(language_from_env)
+            .unwrap_or_default();
+        let mut names = candidates(&requested);
+        names.push("en".to_string());
+        names.dedup();
+
+        let custom_dir = env::var("TODO_LOCALE_DIR").ok();
+        let layers = names
+            .iter()
+            .map(|name| {
+                let mut messages = BUILT_IN
+                    .iter()
+                    .find(|(built_in, _)| built_in == name)
+                    .map(|(_, text)| parse(text))
+                    .unwrap_or_default();
+                if let Some(dir) = &custom_dir
+                    && let Ok(text) =
+                        fs::read_to_string(Path::new(dir).join(format!("{}.txt", name)))
+                {
+                    messages.extend(parse(&text));
+                }
+                messages
+            })
+            .collect();
+        Catalog {
+            layers,
+            plural: plural_rule(&names[0]),
+        }
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        self.layers
+            .iter()
+            .find_map(|messages| messages.get(key))
+            .map(String::as_str)
+    }
+}
+
+fn language_from_env() -> Option<String> {
+    ["TODO_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+// "de_AT.UTF-8@euro" -> ["de_AT", "de"]; "C" and "POSIX" mean English.
+fn candidates(locale: &str) -> Vec<String> {
+    let name = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or("")
+        .replace('-', "_");
+    if name.is_empty() || name == "C" || name == "POSIX" {
+        return Vec::new();
+    }
+    let mut names = vec![name.clone()];
+    if let Some((language, _)) = name.split_once('_') {
+        names.push(language.to_string());
+    }
+    names
+}
+
+// Plural categories as in the Unicode CLDR rules, for the languages likely
+// to be asked for; everything else uses the English rule.
+fn plural_rule(locale: &str) -> fn(u64) -> &'static str {
+    let language = locale.split('_').next().unwrap_or("");
+    match language {
+        "fr" | "pt" => |n| if n <= 1 { "one" } else { "other" },
+        "ja" | "ko" | "zh" | "vi" | "th" => |_| "other",
+        "ru" | "uk" | "be" => |n| match (n % 10, n % 100) {
+            (1, r) if r != 11 => "one",
+            (2..=4, r) if !(12..=14).contains(&r) => "few",
+            _ => "many",
+        },
+        "pl" => |n| match (n % 10, n % 100) {
+            _ if n == 1 => "one",
+            (2..=4, r) if !(12..=14).contains(&r) => "few",
+            _ => "many",
+        },
+        _ => |n| if n == 1 { "one" } else { "other" },
+    }
+}
+
+// Parses a catalog file. Lines that are neither comments, "key = text" nor
+// the start of a multi-line message are ignored.
+fn parse(text: &str) -> HashMap<String, String> {
+    let mut messages = HashMap::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(key) = line.trim_end().strip_suffix("<<<") {
+            let body: Vec<&str> = lines
+                .by_ref()
+                .take_while(|l| l.trim_end() != ">>>")
+                .collect();
+            messages.insert(key.trim().to_string(), body.join("\n"));
+        } else if let Some((key, value)) = line.split_once('=') {
+            messages.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    messages
+}
+
+// Replaces each {name} in `template` with its argument; "{{" and "}}" stand
+// for literal braces and unknown placeholders are left as they are.
+fn fill(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let value = tail
+            .strip_prefix('{')
+            .and_then(|inner| inner.find('}'))
+            .and_then(|len| {
+                let name = &tail[1..=len];
+                let (_, value) = args.iter().find(|(arg, _)| *arg == name)?;
+                Some((value.to_string(), len + 1))
+            });
+        match value {
+            Some((value, end)) => {
+                out.push_str(&value);
+                rest = &tail[end + 1..];
+            }
+            None => {
+                out.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::{Priority, TodoApp};
+
+    fn german() -> &'static Catalog {
+        static GERMAN: OnceLock<Catalog> = OnceLock::new();
+        GERMAN.get_or_init(|| Catalog::load(Some("de")))
+    }
+
+    fn english() -> &'static Catalog {
+        static ENGLISH: OnceLock<Catalog> = OnceLock::new();
+        ENGLISH.get_or_init(|| Catalog::load(Some("en")))
+    }
+
+    fn keys(text: &str) -> BTreeSet<String> {
+        parse(text).into_keys().collect()
+    }
+
+    #[test]
+    fn german_translates_every_english_key() {
+        let (en, de) = (keys(BUILT_IN[0].1), keys(BUILT_IN[1].1));
+        let untranslated: Vec<&String> = en.difference(&de).collect();
+        let unknown: Vec<&String> = de.difference(&en).collect();
+        assert!(
+            untranslated.is_empty(),
+            "missing in de.txt: {:?}",
+            untranslated
+        );
+        assert!(unknown.is_empty(), "not in en.txt: {:?}", unknown);
+    }
+
+    fn sample() -> TodoApp {
+        let mut app = TodoApp::new();
+        app.add_task("Steuererklärung", Priority::High, "finanzen");
+        app.add_task("Milch kaufen", Priority::Low, "");
+        app.complete_task(2).unwrap();
+        app
+    }
+
+    #[test]
+    fn lists_tasks_in_german() {
+        let app = sample();
+        let lines = app.list_lines(german()).join("\n");
+        assert!(
+            lines.contains("#1: Steuererklärung [Hoch] [finanzen]"),
+            "{}",
+            lines
+        );
+        assert!(lines.contains("#2: Milch kaufen [Niedrig]"), "{}", lines);
+
+        // The run's own language is left alone.
+        let lines = app.list_lines(english()).join("\n");
+        assert!(
+            lines.contains("#1: Steuererklärung [High] [finanzen]"),
+            "{}",
+            lines
+        );
+    }
+
+    #[test]
+    fn prints_statistics_in_german() {
+        let app = sample();
+        let report = app.stats_report(0, german());
+        for expected in [
+            "Gesamt: 2, Erledigt: 1, Offen: 1",
+            "Erledigungsquote: 50.0 %",
+            "Nach Status:",
+            "  Offen ",
+            "  In Arbeit ",
+            "Nach Priorität:",
+            "  Hoch ",
+            "Nach Tag:",
+            "(ohne Tag)",
+            "Aufgaben",
+            "Quote",
+        ] {
+            assert!(
+                report.contains(expected),
+                "{:?} not in\n{}",
+                expected,
+                report
+            );
+        }
+        assert!(!report.contains("Total"), "{}", report);
+        assert!(
+            app.stats_report(0, english())
+                .starts_with("Total: 2, Completed: 1")
+        );
+    }
+
+    #[test]
+    fn picks_plural_forms() {
+        let results = |catalog: &Catalog, n: u64| {
+            catalog.message("cli.search_results", Some(n), &[("count", &n)])
+        };
+        assert_eq!(results(english(), 1), "1 result");
+        assert_eq!(results(english(), 0), "0 results");
+        assert_eq!(results(german(), 3), "3 Treffer");
+        let russian = plural_rule("ru_RU");
+        assert_eq!(
+            [1, 2, 5, 11, 21, 22, 25].map(russian),
+            ["one", "few", "many", "many", "one", "few", "many"]
+        );
+        assert_eq!(plural_rule("fr")(0), "one");
+        assert_eq!(plural_rule("ja")(1), "other");
+    }
+
+    #[test]
+    fn falls_back_to_english_and_then_the_key() {
+        let catalog = Catalog::load(Some("pt_BR.UTF-8"));
+        assert_eq!(catalog.layers.len(), 3);
+        assert_eq!(
+            catalog.message("stats.by_tag", None, &[]),
+            english().message("stats.by_tag", None, &[])
+        );
+        assert_eq!(catalog.message("no.such.key", None, &[]), "no.such.key");
+        assert_eq!(candidates("de_AT.UTF-8@euro"), ["de_AT", "de"]);
+        assert_eq!(candidates("en-GB"), ["en_GB", "en"]);
+        assert!(candidates("POSIX").is_empty());
+    }
+
+    #[test]
+    fn parses_catalogs_and_fills_placeholders() {
+        let messages =
+            parse("# comment\n  a = One {x}\nusage <<<\nline 1\n  line 2\n>>>\nnonsense\n");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages["a"], "One {x}");
+        assert_eq!(messages["usage"], "line 1\n  line 2");
+
+        let args: [(&str, &dyn fmt::Display); 2] = [("x", &42), ("name", &"Ünïcode")];
+        assert_eq!(fill("{x} and {name}", &args), "42 and Ünïcode");
+        assert_eq!(fill("{{x}} {missing} {x", &args), "{x} {missing} {x");
+        assert_eq!(fill("}} {x}}", &args), "} 42}");
+    }
+}
@@ -0,0 +1,338 @@
+// Secondary indexes over `TodoApp::tasks`.
+// Every task is entered under its uid, status, priority, tags and title words
+// when it is added and taken out again before it changes, so listings,
+// searches and statistics only look at the tasks they need. Completed tasks
+// are also counted by priority and tag, and untagged tasks by status, so the
+// statistics need not look at the tasks at all for those figures, and the time
+// each task was added is kept so they need not scan the event log either.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
+
+use crate::uid::TaskUid;
+use crate::{Priority, Status, Task};
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TaskIndex {
+    by_uid: BTreeMap<TaskUid, u32>,
+    by_status: HashMap<Status, BTreeSet<u32>>,
+    by_priority: HashMap<Priority, BTreeSet<u32>>,
+    // Keyed by the lowercased tag, as tag filters ignore case.
+    by_tag: HashMap<String, BTreeSet<u32>>,
+    // Keyed by each whitespace-separated word of the lowercased title.
+    words: HashMap<String, BTreeSet<u32>>,
+    completed_by_priority: HashMap<Priority, usize>,
+    completed_by_tag: HashMap<String, usize>,
+    untagged: HashMap<Status, usize>,
+    // From the first event that added each task, deleted tasks included.
+    created: HashMap<TaskUid, u64>,
+}
+
+impl TaskIndex {
+    pub(crate) fn insert(&mut self, task: &Task) {
+        self.by_uid.insert(task.uid, task.id);
+        self.by_status
+            .entry(task.status)
+            .or_default()
+            .insert(task.id);
+        self.by_priority
+            .entry(task.priority)
+            .or_default()
+            .insert(task.id);
+        let tags = task_tags(task);
+        if task.status == Status::Completed {
+            *self.completed_by_priority.entry(task.priority).or_default() += 1;
+            for tag in &tags {
+                *self.completed_by_tag.entry(tag.clone()).or_default() += 1;
+            }
+        }
+        if tags.is_empty() {
+            *self.untagged.entry(task.status).or_default() += 1;
+        }
+        for tag in tags {
+            self.by_tag.entry(tag).or_default().insert(task.id);
+        }
+        for word in title_words(&task.title) {
+            self.words.entry(word).or_default().insert(task.id);
+        }
+    }
+
+    // Removes `task`, which must be exactly as it was when it was inserted.
+    pub(crate) fn remove(&mut self, task: &Task) {
+        self.by_uid.remove(&task.uid);
+        remove_id(&mut self.by_status, &task.status, task.id);
+        remove_id(&mut self.by_priority, &task.priority, task.id);
+        let tags = task_tags(task);
+        if task.status == Status::Completed {
+            uncount(&mut self.completed_by_priority, &task.priority);
+            for tag in &tags {
+                uncount(&mut self.completed_by_tag, tag);
+            }
+        }
+        if tags.is_empty() {
+            uncount(&mut self.untagged, &task.status);
+        }
+        for tag in &tags {
+            remove_id(&mut self.by_tag, tag, task.id);
+        }
+        for word in title_words(&task.title) {
+            remove_id(&mut self.words, &word, task.id);
+        }
+    }
+
+    // Notes that the task with `uid` was added at `at`, unless it already was.
+    pub(crate) fn record_created(&mut self, uid: TaskUid, at: u64) {
+        self.created.entry(uid).or_insert(at);
+    }
+
+    pub(crate) fn created(&self, uid: TaskUid) -> Option<u64> {
+        self.created.get(&uid).copied()
+    }
+
+    pub(crate) fn id_of(&self, uid: TaskUid) -> Option<u32> {
+        self.by_uid.get(&uid).copied()
+    }
+
+    // Ids of the tasks whose uid starts with `prefix` (lowercase hex digits).
+    // Uids sort like their hex digits, so the matches form one range.
+    pub(crate) fn ids_with_uid_prefix(&self, prefix: &str) -> Vec<u32> {
+        let fill = 32usize.saturating_sub(prefix.len());
+        let low = format!("{}{}", prefix, "0".repeat(fill));
+        let high = format!("{}{}", prefix, "f".repeat(fill));
+        let (Ok(low), Ok(high)) = (low.parse::<TaskUid>(), high.parse::<TaskUid>()) else {
+            return Vec::new();
+        };
+        self.by_uid.range(low..=high).map(|(_, &id)| id).collect()
+    }
+
+    pub(crate) fn with_status(&self, status: Status) -> impl Iterator<Item = u32> + '_ {
+        self.by_status.get(&status).into_iter().flatten().copied()
+    }
+
+    pub(crate) fn count(&self, status: Status) -> usize {
+        self.by_status.get(&status).map_or(0, BTreeSet::len)
+    }
+
+    pub(crate) fn with_priority(&self, priority: Priority) -> impl Iterator<Item = u32> + '_ {
+        self.by_priority
+            .get(&priority)
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    // The number of tasks with `priority`, and of those completed.
+    pub(crate) fn priority_counts(&self, priority: Priority) -> (usize, usize) {
+        (
+            self.by_priority.get(&priority).map_or(0, BTreeSet::len),
+            self.completed_by_priority
+                .get(&priority)
+                .copied()
+                .unwrap_or(0),
+        )
+    }
+
+    // Each tag (lowercased) with the number of its tasks and of those
+    // completed, in no particular order.
+    pub(crate) fn tag_counts(&self) -> impl Iterator<Item = (&str, usize, usize)> + '_ {
+        self.by_tag.iter().map(|(tag, ids)| {
+            let completed = self.completed_by_tag.get(tag).copied().unwrap_or(0);
+            (tag.as_str(), ids.len(), completed)
+        })
+    }
+
+    // The number of untagged tasks, and of those completed.
+    pub(crate) fn untagged_counts(&self) -> (usize, usize) {
+        (
+            self.untagged.values().sum(),
+            self.untagged.get(&Status::Completed).copied().unwrap_or(0),
+        )
+    }
+
+    pub(crate) fn with_tag(&self, tag: &str) -> impl Iterator<Item = u32> + '_ {
+        self.by_tag
+            .get(&tag.to_lowercase())
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    // Ids of the tasks whose title may contain `query` (already lowercased),
+    // or None if every task may. A title containing the query contains its
+    // longest word, and that word lies within a single title word, so only
+    // tasks with such a word are candidates; callers still check the title.
+    pub(crate) fn search_candidates(&self, query: &str) -> Option<BTreeSet<u32>> {
+        let piece = query.split_whitespace().max_by_key(|w| w.len())?;
+        Some(
+            self.words
+                .iter()
+                .filter(|(word, _)| word.contains(piece))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect(),
+        )
+    }
+}
+
+// The task's tags, lowercased and without duplicates.
+fn task_tags(task: &Task) -> BTreeSet<String> {
+    task.tags.iter().map(|tag| tag.to_lowercase()).collect()
+}
+
+fn title_words(title: &str) -> BTreeSet<String> {
+    title
+        .to_lowercase()
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+fn remove_id<K, Q>(map: &mut HashMap<K, BTreeSet<u32>>, key: &Q, id: u32)
+where
+    K: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+{
+    if let Some(ids) = map.get_mut(key) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+fn uncount<K, Q>(map: &mut HashMap<K, usize>, key: &Q)
+where
+    K: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+{
+    if let Some(count) = map.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            map.remove(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32, uid: &str, title: &str, tags: &[&str]) -> Task {
+        let tags = tags.iter().map(|t| t.to_string()).collect();
+        let mut task = Task::new(id, title, Priority::Medium, tags);
+        task.uid = uid.parse().unwrap();
+        task
+    }
+
+    fn ids(ids: impl Iterator<Item = u32>) -> Vec<u32> {
+        ids.collect()
+    }
+
+    fn sample() -> (TaskIndex, Vec<Task>) {
+        let mut done = task(
+            2,
+            "a1b20000-0000-4000-8000-000000000002",
+            "Buy oat milk",
+            &["Home", "home", "errands"],
+        );
+        done.status = Status::Completed;
+        done.priority = Priority::High;
+        let tasks = vec![
+            task(
+                1,
+                "a1b10000-0000-4000-8000-000000000001",
+                "Write report",
+                &["work"],
+            ),
+            done,
+            task(
+                3,
+                "ffff0000-0000-4000-8000-000000000003",
+                "Call the bank",
+                &[],
+            ),
+        ];
+        let mut index = TaskIndex::default();
+        for task in &tasks {
+            index.insert(task);
+        }
+        (index, tasks)
+    }
+
+    #[test]
+    fn finds_tasks_by_status_priority_and_tag() {
+        let (index, _) = sample();
+        assert_eq!(ids(index.with_status(Status::Pending)), [1, 3]);
+        assert_eq!(index.count(Status::Completed), 1);
+        assert_eq!(index.count(Status::InProgress), 0);
+        assert_eq!(ids(index.with_priority(Priority::High)), [2]);
+        assert_eq!(ids(index.with_tag("HOME")), [2]);
+        assert_eq!(ids(index.with_tag("nothing")), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn counts_for_the_statistics() {
+        let (index, _) = sample();
+        assert_eq!(index.priority_counts(Priority::Medium), (2, 0));
+        assert_eq!(index.priority_counts(Priority::High), (1, 1));
+        assert_eq!(index.priority_counts(Priority::Low), (0, 0));
+        let mut tags: Vec<(&str, usize, usize)> = index.tag_counts().collect();
+        tags.sort();
+        // A tag given twice in different case counts once.
+        assert_eq!(tags, [("errands", 1, 1), ("home", 1, 1), ("work", 1, 0)]);
+        assert_eq!(index.untagged_counts(), (1, 0));
+    }
+
+    #[test]
+    fn removing_every_task_empties_the_index() {
+        let (mut index, tasks) = sample();
+        index.record_created(tasks[0].uid, 100);
+        for task in &tasks {
+            index.remove(task);
+        }
+        assert!(index.by_uid.is_empty());
+        assert!(index.by_status.is_empty());
+        assert!(index.by_priority.is_empty());
+        assert!(index.by_tag.is_empty());
+        assert!(index.words.is_empty());
+        assert!(index.completed_by_priority.is_empty());
+        assert!(index.completed_by_tag.is_empty());
+        assert!(index.untagged.is_empty());
+        // Creation times outlive the task, for the history.
+        assert_eq!(index.created(tasks[0].uid), Some(100));
+    }
+
+    #[test]
+    fn keeps_the_first_creation_time() {
+        let (mut index, tasks) = sample();
+        index.record_created(tasks[1].uid, 100);
+        index.record_created(tasks[1].uid, 200);
+        assert_eq!(index.created(tasks[1].uid), Some(100));
+        assert_eq!(index.created(tasks[0].uid), None);
+    }
+
+    #[test]
+    fn looks_up_uids_by_prefix() {
+        let (index, tasks) = sample();
+        assert_eq!(index.id_of(tasks[2].uid), Some(3));
+        assert_eq!(index.ids_with_uid_prefix("a1b"), [1, 2]);
+        assert_eq!(index.ids_with_uid_prefix("a1b2"), [2]);
+        assert_eq!(index.ids_with_uid_prefix(&tasks[0].uid.hex()), [1]);
+        assert_eq!(index.ids_with_uid_prefix("ffff"), [3]);
+        assert!(index.ids_with_uid_prefix("0").is_empty());
+        assert!(index.ids_with_uid_prefix("xyz").is_empty());
+    }
+
+    #[test]
+    fn search_candidates_share_the_longest_word() {
+        let (index, _) = sample();
+        assert_eq!(index.search_candidates("report"), Some(BTreeSet::from([1])));
+        // Part of a word, and a query spanning words.
+        assert_eq!(index.search_candidates("ank"), Some(BTreeSet::from([3])));
+        assert_eq!(
+            index.search_candidates("oat mil"),
+            Some(BTreeSet::from([2]))
+        );
+        assert_eq!(index.search_candidates("zebra"), Some(BTreeSet::new()));
+    }
+}
@@ -0,0 +1,534 @@
+// Importing issues from JSON exports of an issue tracker.
+// Each issue becomes a task that remembers "issue:<tracker>#<issue id>" as
+// its source, so importing a newer export updates the task instead of adding it
+// again: title, priority and tags follow the issue, and a closed issue
+// completes its task. Tasks completed locally stay completed, and tasks
+// deleted locally are not brought back. Labels become tags, except those
+// that name a priority, and the assignee becomes an "assignee:<name>" tag.
+//
+// Where the fields are found is set by a mapping file (`import --mapping
+// FILE`, or TODO_ISSUE_MAPPING) of "key: value" lines; paths are dotted:
+//
+//   tracker: jira                    names the tracker in task sources
+//   issues: issues                   path to the list of issues
+//   id: key
+//   title: fields.summary
+//   state: fields.status.name
+//   labels: fields.labels
+//   assignee: fields.assignee.displayName
+//   priority: fields.priority.name   matched against "high" and "low"
+//   closed: done, closed, won't do   states that count as closed
+//   in progress: in progress, in review
+//   high: blocker, critical          labels or priorities meaning high
+//   low: minor, trivial
+//
+// Every key is optional; the defaults fit exports shaped like GitHub's.
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::events::EventKind;
+use crate::json::{self, Json};
+use crate::uid::TaskUid;
+use crate::{Priority, Status, Task, TodoApp, TodoError};
+
+// Starts the source of every task imported from a tracker, telling them apart
+// from tasks imported from other kinds of files.
+const ISSUE_SOURCE: &str = "issue:";
+
+#[derive(Debug, Clone)]
+pub(crate) struct Mapping {
+    tracker: String,
+    // Empty: the document itself, or its "issues" field if it is an object.
+    issues: String,
+    id: String,
+    title: String,
+    state: String,
+    labels: String,
+    assignee: String,
+    priority: String,
+    // Lowercased state and label names.
+    closed: Vec<String>,
+    in_progress: Vec<String>,
+    high: Vec<String>,
+    low: Vec<String>,
+}
+
+impl Default for Mapping {
+    fn default() -> Self {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Mapping {
+            tracker: "issues".to_string(),
+            issues: String::new(),
+            id: "id".to_string(),
+            title: "title".to_string(),
+            state: "state".to_string(),
+            labels: "labels".to_string(),
+            assignee: "assignee".to_string(),
+            priority: String::new(),
+            closed: list(&["closed", "done", "resolved"]),
+            in_progress: list(&["in progress", "in_progress", "doing", "started"]),
+            high: list(&[
+                "priority/high",
+                "priority: high",
+                "high",
+                "critical",
+                "urgent",
+                "p0",
+                "p1",
+            ]),
+            low: list(&["priority/low", "priority: low", "low", "minor", "p3", "p4"]),
+        }
+    }
+}
+
+impl Mapping {
+    // The mapping from `path`, else from TODO_ISSUE_MAPPING, else the default.
+    pub(crate) fn load(path: Option<&str>) -> Result<Self, TodoError> {
+        match path
+            .map(String::from)
+            .or_else(|| env::var("TODO_ISSUE_MAPPING").ok())
+        {
+            Some(path) => Mapping::parse(&fs::read_to_string(&path)?, &path),
+            None => Ok(Mapping::default()),
+        }
+    }
+
+    pub(crate) fn parse(text: &str, source: &str) -> Result<Self, TodoError> {
+        let mut mapping = Mapping::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let location = format!("{}:{}", source, n + 1);
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(TodoError::InvalidInput(t!(
+                    "error.bad_mapping_line",
+                    location = location,
+                    line = line
+                )));
+            };
+            let value = value.trim().to_string();
+            let list = || {
+                value
+                    .split(',')
+                    .map(|s| s.trim().to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            };
+            match key.trim() {
+                "tracker" => mapping.tracker = value,
+                "issues" => mapping.issues = value,
+                "id" => mapping.id = value,
+                "title" => mapping.title = value,
+                "state" => mapping.state = value,
+                "labels" => mapping.labels = value,
+                "assignee" => mapping.assignee = value,
+                "priority" => mapping.priority = value,
+                "closed" => mapping.closed = list(),
+                "in progress" => mapping.in_progress = list(),
+                "high" => mapping.high = list(),
+                "low" => mapping.low = list(),
+                key => {
+                    return Err(TodoError::InvalidInput(t!(
+                        "error.bad_mapping_key",
+                        location = location,
+                        key = key
+                    )));
+                }
+            }
+        }
+        if mapping.tracker.is_empty() || mapping.id.is_empty() || mapping.title.is_empty() {
+            return Err(TodoError::InvalidInput(t!(
+                "error.incomplete_mapping",
+                file = source
+            )));
+        }
+        Ok(mapping)
+    }
+
+    // Maps the `n`th issue of `file`.
+    fn issue(&self, issue: &Json, file: &str, n: usize) -> Result<Issue, TodoError> {
+        let field = |path: &str| {
+            issue.path(path).and_then(Json::text).ok_or_else(|| {
+                TodoError::InvalidInput(t!("error.bad_issue", file = file, n = n + 1, field = path))
+            })
+        };
+        // An empty path leaves the field unmapped.
+        let at = |path: &str| issue.path(path).filter(|_| !path.is_empty());
+        let id = field(&self.id)?;
+        let title = field(&self.title)?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if title.is_empty() {
+            return Err(TodoError::InvalidInput(t!(
+                "error.bad_issue",
+                file = file,
+                n = n + 1,
+                field = self.title
+            )));
+        }
+
+        let status = match at(&self.state) {
+            Some(Json::Bool(true)) => Status::Completed,
+            Some(state) => match state.text().map(|s| s.to_lowercase()) {
+                Some(s) if self.closed.contains(&s) => Status::Completed,
+                Some(s) if self.in_progress.contains(&s) => Status::InProgress,
+                _ => Status::Pending,
+            },
+            None => Status::Pending,
+        };
+
+        let labels: Vec<String> = match at(&self.labels) {
+            Some(Json::Array(labels)) => labels.iter().filter_map(name_of).collect(),
+            Some(label) => name_of(label).into_iter().collect(),
+            None => Vec::new(),
+        };
+        let mut priorities: Vec<String> = labels.iter().map(|l| l.to_lowercase()).collect();
+        if let Some(priority) = at(&self.priority).and_then(name_of) {
+            priorities.push(priority.to_lowercase());
+        }
+        let priority = if priorities.iter().any(|p| self.high.contains(p)) {
+            Priority::High
+        } else if priorities.iter().any(|p| self.low.contains(p)) {
+            Priority::Low
+        } else {
+            Priority::Medium
+        };
+
+        let mut tags: Vec<String> = Vec::new();
+        for label in &labels {
+            let lower = label.to_lowercase();
+            let tag = tag(label);
+            if !self.high.contains(&lower)
+                && !self.low.contains(&lower)
+                && !tag.is_empty()
+                && !tags.contains(&tag)
+            {
+                tags.push(tag);
+            }
+        }
+        if let Some(assignee) = at(&self.assignee).and_then(name_of)
+            && !tag(&assignee).is_empty()
+        {
+            tags.push(format!("assignee:{}", tag(&assignee)));
+        }
+
+        Ok(Issue {
+            source: format!("{}{}#{}", ISSUE_SOURCE, self.tracker, id),
+            title,
+            status,
+            priority,
+            tags,
+        })
+    }
+}
+
+// One issue, mapped onto task fields.
+#[derive(Debug, Clone)]
+struct Issue {
+    source: String,
+    title: String,
+    status: Status,
+    priority: Priority,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct IssueImport {
+    pub(crate) added: usize,
+    pub(crate) updated: usize,
+    pub(crate) closed: usize,
+    pub(crate) unchanged: usize,
+    // Issues whose task was deleted locally.
+    pub(crate) deleted: usize,
+}
+
+// Reads the issues of the export at `path`.
+pub(crate) fn read_export(path: &Path, mapping: &Mapping) -> Result<Vec<Json>, TodoError> {
+    let file = path.display().to_string();
+    let document = json::parse(&fs::read_to_string(path)?, &file)?;
+    let list = match (mapping.issues.as_str(), &document) {
+        ("", Json::Array(_)) => Some(&document),
+        ("", _) => document.get("issues"),
+        (issues, _) => document.path(issues),
+    };
+    match list {
+        Some(Json::Array(issues)) => Ok(issues.clone()),
+        _ => Err(TodoError::InvalidInput(t!(
+            "error.no_issue_list",
+            file = file,
+            path = if mapping.issues.is_empty() {
+                "issues"
+            } else {
+                &mapping.issues
+            }
+        ))),
+    }
+}
+
+// A label or person, written as a string or as an object with a name.
+fn name_of(value: &Json) -> Option<String> {
+    match value {
+        Json::Object(_) => ["name", "login", "username", "displayName"]
+            .iter()
+            .find_map(|key| value.get(key).and_then(Json::text)),
+        other => other.text(),
+    }
+    .filter(|name| !name.trim().is_empty())
+}
+
+// Tags cannot hold the separators of the todo file or spaces.
+fn tag(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || matches!(c, ',' | '|' | ';'))
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl TodoApp {
+    // Maps the source of every task ever imported from a tracker, including
+    // tasks deleted since, to its uid.
+    fn imported_issues(&self) -> HashMap<String, TaskUid> {
+        self.log
+            .iter()
+            .filter_map(|event| match &event.kind {
+                EventKind::TaskAdded(task) => task.source.clone().map(|source| (source, task.uid)),
+                _ => None,
+            })
+            .filter(|(source, _)| source.starts_with(ISSUE_SOURCE))
+            .collect()
+    }
+
+    // Adds or updates a task for every issue, as one transaction. `file`
+    // names the export in errors.
+    pub(crate) fn import_issues(
+        &mut self,
+        issues: &[Json],
+        mapping: &Mapping,
+        file: &str,
+    ) -> Result<IssueImport, TodoError> {
+        let imported = self.imported_issues();
+        let mut seen = HashSet::new();
+        let mut result = IssueImport::default();
+        let mut events = Vec::new();
+
+        for (n, issue) in issues.iter().enumerate() {
+            let issue = mapping.issue(issue, file, n)?;
+            // An export listing an issue twice: the first one counts.
+            if !seen.insert(issue.source.clone()) {
+                continue;
+            }
+            let Some(&uid) = imported.get(&issue.source) else {
+                let mut task = Task::new(
+                    self.next_id + result.added as u32,
+                    &issue.title,
+                    issue.priority,
+                    issue.tags,
+                );
+                task.status = issue.status;
+                task.source = Some(issue.source);
+                events.push(EventKind::TaskAdded(task));
+                result.added += 1;
+                continue;
+            };
+            let Some(task) = self.index.id_of(uid).map(|id| &self.tasks[&id]) else {
+                result.deleted += 1;
+                continue;
+            };
+
+            let before = events.len();
+            if task.title != issue.title {
+                events.push(EventKind::TitleChanged(uid, issue.title));
+            }
+            if task.priority != issue.priority {
+                events.push(EventKind::PriorityChanged(uid, issue.priority));
+            }
+            if task.tags != issue.tags {
+                events.push(EventKind::TagsChanged(uid, issue.tags));
+            }
+            let closes = task.status != Status::Completed && issue.status == Status::Completed;
+            if closes {
+                events.push(EventKind::TaskCompleted(uid));
+                result.closed += 1;
+            } else if task.status != Status::Completed && task.status != issue.status {
+                events.push(EventKind::StatusChanged(uid, issue.status));
+            }
+            match events.len() - before {
+                0 => result.unchanged += 1,
+                _ if !closes => result.updated += 1,
+                _ => {}
+            }
+        }
+        self.record_all(events)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"[
+        {"id": 1, "title": "Crash on | in titles; really, it does", "state": "open",
+         "labels": [{"name": "bug"}, "priority/high"], "assignee": {"login": "ana"}},
+        {"id": 2, "title": "Write docs", "state": "in progress", "labels": []},
+        {"id": 1, "title": "Listed twice", "state": "open"}
+    ]"#;
+
+    fn issues(text: &str) -> Vec<Json> {
+        match json::parse(text, "test").unwrap() {
+            Json::Array(issues) => issues,
+            other => panic!("expected a list, got {:?}", other),
+        }
+    }
+
+    // Saves and loads the app, as between two runs.
+    fn reload(app: &TodoApp) -> TodoApp {
+        TodoApp::from_text(&app.to_text(), "test").unwrap()
+    }
+
+    fn task<'a>(app: &'a TodoApp, source: &str) -> &'a Task {
+        app.tasks
+            .values()
+            .find(|t| t.source.as_deref() == Some(source))
+            .unwrap()
+    }
+
+    #[test]
+    fn maps_issues_onto_tasks() {
+        let mut app = TodoApp::new();
+        let result = app
+            .import_issues(&issues(EXPORT), &Mapping::default(), "test")
+            .unwrap();
+        assert_eq!(result.added, 2);
+        assert_eq!(app.tasks.len(), 2);
+
+        let crash = task(&app, "issue:issues#1");
+        assert_eq!(crash.title, "Crash on | in titles; really, it does");
+        assert_eq!(crash.priority, Priority::High);
+        assert_eq!(crash.status, Status::Pending);
+        assert_eq!(crash.tags, ["bug", "assignee:ana"]);
+        assert_eq!(task(&app, "issue:issues#2").status, Status::InProgress);
+    }
+
+    #[test]
+    fn tasks_from_other_sources_are_not_taken_for_issues() {
+        let mut app = TodoApp::new();
+        let mut note = Task::new(1, "From a checklist", Priority::Low, Vec::new());
+        note.source = Some("issues#1".to_string());
+        app.record(EventKind::TaskAdded(note)).unwrap();
+
+        let result = app
+            .import_issues(&issues(EXPORT), &Mapping::default(), "test")
+            .unwrap();
+        assert_eq!(result.added, 2);
+        assert_eq!(app.tasks[&1].title, "From a checklist");
+        assert_eq!(task(&app, "issue:issues#1").id, 2);
+    }
+
+    #[test]
+    fn reimporting_the_same_file_changes_nothing() {
+        let path = env::temp_dir().join(format!("day21-issues-{}.json", std::process::id()));
+        fs::write(&path, EXPORT).unwrap();
+        let mapping = Mapping::default();
+        let mut app = TodoApp::new();
+        app.import_issues(&read_export(&path, &mapping).unwrap(), &mapping, "test")
+            .unwrap();
+        let text = reload(&app).to_text();
+
+        let mut app = reload(&app);
+        let events = app.log.len();
+        let result = app
+            .import_issues(&read_export(&path, &mapping).unwrap(), &mapping, "test")
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((result.added, result.updated, result.unchanged), (0, 0, 2));
+        assert_eq!(app.log.len(), events);
+        assert_eq!(app.to_text(), text);
+    }
+
+    #[test]
+    fn a_newer_export_updates_and_closes_tasks() {
+        let mut app = TodoApp::new();
+        app.import_issues(&issues(EXPORT), &Mapping::default(), "test")
+            .unwrap();
+        let newer = r#"[
+            {"id": 1, "title": "Crash on pipes", "state": "closed", "labels": ["bug"]},
+            {"id": 2, "title": "Write docs", "state": "open", "labels": ["p4"]}
+        ]"#;
+        let mut app = reload(&app);
+        let result = app
+            .import_issues(&issues(newer), &Mapping::default(), "test")
+            .unwrap();
+        assert_eq!((result.updated, result.closed), (1, 1));
+
+        let app = reload(&app);
+        let crash = task(&app, "issue:issues#1");
+        assert_eq!(crash.title, "Crash on pipes");
+        assert_eq!(crash.status, Status::Completed);
+        assert_eq!(crash.tags, ["bug"]);
+        let docs = task(&app, "issue:issues#2");
+        assert_eq!(docs.status, Status::Pending);
+        assert_eq!(docs.priority, Priority::Low);
+    }
+
+    #[test]
+    fn local_completions_and_deletions_are_kept() {
+        let mut app = TodoApp::new();
+        app.import_issues(&issues(EXPORT), &Mapping::default(), "test")
+            .unwrap();
+        let done = task(&app, "issue:issues#1").uid;
+        let gone = task(&app, "issue:issues#2").uid;
+        app.record(EventKind::TaskCompleted(done)).unwrap();
+        app.record(EventKind::TaskDeleted(gone)).unwrap();
+
+        let mut app = reload(&app);
+        let result = app
+            .import_issues(&issues(EXPORT), &Mapping::default(), "test")
+            .unwrap();
+        assert_eq!((result.added, result.deleted, result.unchanged), (0, 1, 1));
+        assert_eq!(app.tasks.len(), 1);
+        assert_eq!(task(&app, "issue:issues#1").status, Status::Completed);
+    }
+
+    #[test]
+    fn custom_mappings_follow_nested_fields() {
+        let mapping = Mapping::parse(
+            "tracker: jira\nissues: issues\nid: key\ntitle: fields.summary\n\
+             state: fields.status.name\nlabels: fields.labels\npriority: fields.priority.name\n\
+             closed: done\nhigh: blocker\n",
+            "mapping",
+        )
+        .unwrap();
+        let export = r#"{"issues": [{"key": "APP-7", "fields": {"summary": "Fix login",
+            "status": {"name": "Done"}, "labels": ["auth"], "priority": {"name": "Blocker"}}}]}"#;
+        let path = env::temp_dir().join(format!("day21-jira-{}.json", std::process::id()));
+        fs::write(&path, export).unwrap();
+        let issues = read_export(&path, &mapping).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut app = TodoApp::new();
+        app.import_issues(&issues, &mapping, "test").unwrap();
+        let task = task(&app, "issue:jira#APP-7");
+        assert_eq!(task.title, "Fix login");
+        assert_eq!(task.status, Status::Completed);
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.tags, ["auth"]);
+    }
+
+    #[test]
+    fn rejects_issues_without_an_id_or_title() {
+        let mut app = TodoApp::new();
+        for export in [r#"[{"title": "No id"}]"#, r#"[{"id": 3, "title": "  "}]"#] {
+            let result = app.import_issues(&issues(export), &Mapping::default(), "test");
+            assert!(result.is_err(), "accepted {}", export);
+        }
+        assert!(app.tasks.is_empty());
+        assert!(Mapping::parse("colour: red", "mapping").is_err());
+    }
+}
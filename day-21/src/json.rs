@@ -0,0 +1,380 @@
+// A small JSON reader, enough for the exports of other tools.
+// Objects keep their keys in file order; numbers are read as f64.
+
+use crate::TodoError;
+
+// Deeper nesting is refused instead of overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    // The value of `key` if this is an object that has it.
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // Follows a dotted path such as "fields.status.name"; "" is the value
+    // itself.
+    pub(crate) fn path(&self, path: &str) -> Option<&Json> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        path.split('.').try_fold(self, |value, key| value.get(key))
+    }
+
+    // Strings, numbers and booleans as text, e.g. for ids that some tools
+    // write as numbers; None for anything else, including null.
+    pub(crate) fn text(&self) -> Option<String> {
+        match self {
+            Json::String(s) => Some(s.clone()),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => Some(format!("{}", *n as i64)),
+            Json::Number(n) => Some(n.to_string()),
+            Json::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+}
+
+// Parses a whole document; `source` names it in errors, which point at the
+// line and column where reading stopped.
+pub(crate) fn parse(text: &str, source: &str) -> Result<Json, TodoError> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value().and_then(|value| {
+        parser.skip_whitespace();
+        (parser.pos == parser.text.len()).then_some(value)
+    });
+    value.ok_or_else(|| {
+        let before = &text.as_bytes()[..parser.pos.min(text.len())];
+        let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&b| b != b'\n').count() + 1;
+        TodoError::InvalidInput(t!(
+            "error.bad_json",
+            location = format!("{}:{}:{}", source, line, column)
+        ))
+    })
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+        match *self.text.get(self.pos)? {
+            b'{' => self.nested(Parser::object),
+            b'[' => self.nested(Parser::array),
+            b'"' => self.string().map(Json::String),
+            b't' => self.literal("true", Json::Bool(true)),
+            b'f' => self.literal("false", Json::Bool(false)),
+            b'n' => self.literal("null", Json::Null),
+            _ => self.number(),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Option<Json>) -> Option<Json> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Option<Json> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Some(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.text.get(self.pos) != Some(&b'"') {
+                return None;
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return None;
+            }
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Some(Json::Object(fields));
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Json> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Some(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat(b']') {
+                return Some(Json::Array(items));
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            // Copies the run up to the next quote or backslash in one go.
+            let start = self.pos;
+            while let Some(&b) = self.text.get(self.pos) {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.text[start..self.pos]).ok()?);
+            match *self.text.get(self.pos)? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(out);
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    let c = match *self.text.get(self.pos)? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            self.pos += 1;
+                            let c = self.unicode_escape()?;
+                            out.push(c);
+                            continue;
+                        }
+                        _ => return None,
+                    };
+                    self.pos += 1;
+                    out.push(c);
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    // The character of a "\u" escape, whose "\u" has been read; a surrogate
+    // pair takes two escapes.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high);
+        }
+        if self.text.get(self.pos..self.pos + 2)? != b"\\u" {
+            return None;
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return None;
+        }
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let digits = std::str::from_utf8(self.text.get(self.pos..self.pos + 4)?).ok()?;
+        let value = u32::from_str_radix(digits, 16).ok()?;
+        self.pos += 4;
+        Some(value)
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.pos;
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.eE".contains(b))
+        {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.text[start..self.pos]).ok()?;
+        // Rust accepts a few forms JSON does not, such as "+1" and "1.".
+        let digits_around_point = text.split_once('.').is_none_or(|(a, b)| {
+            a.ends_with(|c: char| c.is_ascii_digit()) && b.starts_with(|c: char| c.is_ascii_digit())
+        });
+        if text.starts_with('+') || !digits_around_point {
+            return None;
+        }
+        text.parse()
+            .ok()
+            .filter(|n: &f64| n.is_finite())
+            .map(Json::Number)
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Option<Json> {
+        if self.text.get(self.pos..self.pos + word.len())? != word.as_bytes() {
+            return None;
+        }
+        self.pos += word.len();
+        Some(value)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.text.get(self.pos) == Some(&byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|b| b" \t\r\n".contains(b))
+        {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(text: &str) -> Json {
+        parse(text, "test").unwrap()
+    }
+
+    fn string(text: &str) -> String {
+        match ok(text) {
+            Json::String(s) => s,
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_every_kind_of_value() {
+        let value = ok(r#" {"a": [1, -2.5e3, true, false, null], "b": {"c": "d"}, "e": {}} "#);
+        assert_eq!(
+            value.get("a"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-2500.0),
+                Json::Bool(true),
+                Json::Bool(false),
+                Json::Null,
+            ]))
+        );
+        assert_eq!(value.path("b.c").and_then(Json::text).as_deref(), Some("d"));
+        assert_eq!(value.get("e"), Some(&Json::Object(Vec::new())));
+        assert_eq!(value.path("b.missing"), None);
+        assert_eq!(ok("[]"), Json::Array(Vec::new()));
+    }
+
+    #[test]
+    fn objects_keep_their_key_order() {
+        let Json::Object(fields) = ok(r#"{"z": 1, "a": 2, "m": 3}"#) else {
+            panic!("expected an object");
+        };
+        let keys: Vec<&str> = fields.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["z", "a", "m"]);
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        assert_eq!(
+            string(r#""quote \" backslash \\ slash \/ \b\f\n\r\t end""#),
+            "quote \" backslash \\ slash / \u{8}\u{c}\n\r\t end"
+        );
+        assert_eq!(string(r#""caf\u00e9 \u00C9""#), "café É");
+        assert_eq!(string(r#""\ud83d\ude00""#), "😀");
+    }
+
+    #[test]
+    fn keeps_unicode_as_written() {
+        assert_eq!(string("\"日本語 — ünïcödé 😀\""), "日本語 — ünïcödé 😀");
+    }
+
+    #[test]
+    fn numbers_as_text() {
+        assert_eq!(ok("42").text().as_deref(), Some("42"));
+        assert_eq!(ok("4.5").text().as_deref(), Some("4.5"));
+        assert_eq!(ok("true").text().as_deref(), Some("true"));
+        assert_eq!(ok("null").text(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for text in [
+            "",
+            "{",
+            "[1, 2",
+            "[1, 2,]",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            "{a: 1}",
+            r#""unterminated"#,
+            "\"raw\nline break\"",
+            r#""bad escape \x""#,
+            r#""\u12""#,
+            r#""\ud83d""#,
+            r#""\ud83dA""#,
+            "+1",
+            "1.",
+            ".5",
+            "1e999",
+            "tru",
+            "nul",
+            "[1] [2]",
+            "{} x",
+        ] {
+            assert!(parse(text, "test").is_err(), "accepted {:?}", text);
+        }
+    }
+
+    #[test]
+    fn refuses_deep_nesting() {
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert!(parse(&deep, "test").is_err());
+        let allowed = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse(&allowed, "test").is_ok());
+    }
+
+    #[test]
+    fn errors_name_the_line_and_column() {
+        let Err(TodoError::InvalidInput(message)) = parse("[1,\n  2,\n  x]", "export.json") else {
+            panic!("expected an error");
+        };
+        assert!(message.contains("export.json:3:3"), "{}", message);
+    }
+}
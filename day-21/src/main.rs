@@ -1,88 +1,279 @@
 // Day 21: Final Project - Todo CLI Application
 // This file contains the final project combining all 21 days of learning
 
+#[macro_use]
+mod i18n;
+
+mod attachment;
+mod bench;
+mod bulk;
+mod cli;
+mod color;
+mod crypto;
+mod date;
+mod dedupe;
+mod effort;
+mod events;
+mod filter;
+mod forecast;
+mod index;
+mod issues;
+mod json;
+mod markdown;
+mod metrics;
+mod migrate;
+mod sort;
+mod stats;
+mod storage;
+mod template;
+mod uid;
+mod watch;
+
 use std::collections::HashMap;
+use std::env;
 use std::fmt;
-use std::fs::File;
-use std::io::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+use attachment::Attachment;
+use color::Style;
+use date::Date;
+use effort::Effort;
+use events::{Event, EventKind};
+use i18n::Catalog;
+use index::TaskIndex;
+use sort::{GroupBy, SortSpec};
+use uid::TaskUid;
 
 fn main() {
-    println!("=== Day 21: Final Project - Todo CLI Application ===\n");
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        run_demo();
+        return;
+    }
+
+    if let Err(e) = cli::run(&args) {
+        eprintln!("{}", t!("error", error = e));
+        process::exit(1);
+    }
+}
+
+fn run_demo() {
+    println!("{}\n", t!("demo.title"));
 
     let mut app = TodoApp::new();
 
     // Demo all features
-    println!("--- Creating Tasks ---");
+    println!("{}", t!("demo.creating"));
     app.add_task("Learn Rust basics", Priority::High, "learning");
     app.add_task("Complete Day 21 project", Priority::High, "learning");
     app.add_task("Review ownership concepts", Priority::Medium, "learning");
     app.add_task("Exercise", Priority::Low, "health");
     app.add_task("Read a book", Priority::Low, "personal");
 
-    println!("\n--- All Tasks ---");
+    println!("\n{}", t!("demo.all_tasks"));
     app.list_all();
 
-    println!("\n--- Completing Tasks ---");
-    app.complete_task(1);
-    app.complete_task(4);
+    println!("\n{}", t!("demo.completing"));
+    for id in [1, 4] {
+        if let Err(e) = app.complete_task(id) {
+            println!("{}", t!("error", error = e));
+        }
+    }
 
-    println!("\n--- Tasks by Status ---");
-    println!("Pending:");
+    println!("\n{}", t!("demo.by_status"));
+    println!("{}:", Status::Pending);
     app.list_by_status(Status::Pending);
-    println!("\nCompleted:");
+    println!("\n{}:", Status::Completed);
     app.list_by_status(Status::Completed);
 
-    println!("\n--- Search ---");
+    println!("\n{}", t!("demo.search"));
     let results = app.search("Rust");
-    println!("Search 'Rust': {} result(s)", results.len());
+    println!(
+        "{}",
+        tn!("demo.search_results", results.len(), query = "Rust")
+    );
     for task in results {
-        println!("  {}", task);
+        println!("  {}", task.render(Some("Rust")));
+    }
+
+    println!("\n{}", t!("demo.statistics"));
+    app.print_stats(events::now());
+
+    println!("\n{}", t!("demo.sorting"));
+    let today = Date::today();
+    for (id, days) in [(2, 3), (3, 1), (5, 7)] {
+        if let Err(e) = app.set_due(id, Some(Date::from_days(today.days() + days))) {
+            println!("{}", t!("error", error = e));
+        }
+    }
+    app.sort = "priority desc, due, id".parse().expect("valid sort spec");
+    app.group = Some(GroupBy::Status);
+    println!("{}", t!("demo.sorted_by", spec = app.sort));
+    app.list_all();
+    app.sort = SortSpec::default();
+    app.group = None;
+
+    println!("\n{}", t!("demo.uids"));
+    let uid = app.tasks[&3].uid;
+    let prefix = &uid.hex()[..6];
+    println!("{}", t!("demo.uid_of", id = 3, uid = uid));
+    match app.resolve(prefix) {
+        Ok(id) => println!("{}", t!("demo.prefix_resolves", prefix = prefix, id = id)),
+        Err(e) => println!("{}", t!("error", error = e)),
+    }
+
+    println!("\n{}", t!("demo.notes"));
+    let notes = "Steps:\n- build the CLI\n- write the README".to_string();
+    if let Err(e) = app
+        .set_notes(2, notes)
+        .and_then(|_| app.show_task(2, &attachment::data_dir("/tmp/todos.txt")))
+    {
+        println!("{}", t!("error", error = e));
+    }
+
+    println!("\n{}", t!("demo.bulk"));
+    let filter = filter::TaskFilter {
+        tag: Some("learning".to_string()),
+        status: Some(Status::Pending),
+        ..Default::default()
+    };
+    let ids = app.select(&filter);
+    println!("{}", t!("demo.selected", ids = format!("{:?}", ids)));
+    match app.apply_bulk(&ids, &bulk::BulkAction::Move(Status::InProgress)) {
+        Ok((count, _)) => println!("{}", tn!("demo.moved", count, status = Status::InProgress)),
+        Err(e) => println!("{}", t!("error", error = e)),
     }
 
-    println!("\n--- Statistics ---");
-    app.print_stats();
+    println!("\n{}", t!("demo.history"));
+    println!("{}", t!("demo.history_of", id = 2));
+    app.print_history(app.tasks[&2].uid);
+    println!("{}", tn!("demo.events_total", app.log.len()));
 
-    println!("\n--- Save and Load ---");
+    println!("\n{}", t!("demo.save"));
     let filename = "/tmp/todos.txt";
     match app.save_to_file(filename) {
-        Ok(_) => println!("Saved to {}", filename),
-        Err(e) => println!("Error saving: {}", e),
+        Ok(_) => println!("{}", t!("demo.saved", file = filename)),
+        Err(e) => println!("{}", t!("demo.save_failed", error = e)),
+    }
+
+    println!("\n{}", t!("demo.encrypted"));
+    let secret_file = "/tmp/todos.enc";
+    match app.save_encrypted(secret_file, "correct horse battery staple") {
+        Ok(_) => println!("{}", t!("demo.saved_encrypted", file = secret_file)),
+        Err(e) => println!("{}", t!("demo.save_failed", error = e)),
+    }
+    for passphrase in ["correct horse battery staple", "wrong guess"] {
+        match TodoApp::load_encrypted(secret_file, passphrase) {
+            Ok(loaded) => println!(
+                "{}",
+                tn!("demo.loaded", loaded.tasks.len(), passphrase = passphrase)
+            ),
+            Err(e) => println!("'{}': {}", passphrase, e),
+        }
     }
 
-    println!("\n=== Congratulations! You've completed 21 Days of Rust! ===");
+    println!("\n{}", t!("demo.congratulations"));
     print_completion_message();
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Priority {
     Low,
     Medium,
     High,
 }
 
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Low, Priority::Medium, Priority::High];
+
+    // The English name written to the todo file, whatever the display language.
+    fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+}
+
 impl fmt::Display for Priority {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Priority::Low => write!(f, "Low"),
-            Priority::Medium => write!(f, "Medium"),
-            Priority::High => write!(f, "High"),
+        let name = match self {
+            Priority::Low => t!("priority.low"),
+            Priority::Medium => t!("priority.medium"),
+            Priority::High => t!("priority.high"),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Accepts the English names and abbreviations, and the localized names.
+impl FromStr for Priority {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "med" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Priority::ALL
+                .into_iter()
+                .find(|p| p.to_string().to_lowercase() == s)
+                .ok_or_else(|| TodoError::InvalidInput(t!("priority.unknown", value = s))),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Status {
     Pending,
     InProgress,
     Completed,
 }
 
+impl Status {
+    const ALL: [Status; 3] = [Status::Pending, Status::InProgress, Status::Completed];
+
+    // The English name written to the todo file, whatever the display language.
+    fn name(self) -> &'static str {
+        match self {
+            Status::Pending => "Pending",
+            Status::InProgress => "In Progress",
+            Status::Completed => "Completed",
+        }
+    }
+}
+
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Status::Pending => write!(f, "Pending"),
-            Status::InProgress => write!(f, "In Progress"),
-            Status::Completed => write!(f, "Completed"),
+        let name = match self {
+            Status::Pending => t!("status.pending"),
+            Status::InProgress => t!("status.in_progress"),
+            Status::Completed => t!("status.completed"),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Accepts the English names and aliases, and the localized names.
+impl FromStr for Status {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "pending" | "todo" => Ok(Status::Pending),
+            "in progress" | "in-progress" | "inprogress" | "doing" => Ok(Status::InProgress),
+            "completed" | "done" => Ok(Status::Completed),
+            _ => Status::ALL
+                .into_iter()
+                .find(|status| status.to_string().to_lowercase() == s)
+                .ok_or_else(|| TodoError::InvalidInput(t!("status.unknown", value = s))),
         }
     }
 }
@@ -90,56 +281,124 @@ impl fmt::Display for Status {
 #[derive(Debug, Clone)]
 struct Task {
     id: u32,
+    uid: TaskUid,
     title: String,
     priority: Priority,
     status: Status,
     tags: Vec<String>,
+    due: Option<Date>,
+    notes: String,
+    attachments: Vec<Attachment>,
+    // The Markdown file the task was imported from, if any.
+    source: Option<String>,
+    // Estimated and actually spent effort, when known.
+    estimate: Option<Effort>,
+    spent: Option<Effort>,
+    // The day the task was completed.
+    completed: Option<Date>,
 }
 
 impl Task {
     fn new(id: u32, title: &str, priority: Priority, tags: Vec<String>) -> Self {
         Task {
             id,
+            uid: TaskUid::generate(),
             title: title.to_string(),
             priority,
             status: Status::Pending,
             tags,
+            due: None,
+            notes: String::new(),
+            attachments: Vec::new(),
+            source: None,
+            estimate: None,
+            spent: None,
+            completed: None,
         }
     }
 }
 
-impl fmt::Display for Task {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Task {
+    fn is_overdue(&self) -> bool {
+        self.status != Status::Completed && self.due.is_some_and(|due| due < Date::today())
+    }
+
+    // The one-line form used in listings, with matches of `query` in the
+    // title highlighted. Markers are colored by priority, completed tasks are
+    // dimmed and overdue ones bold (when colors are enabled).
+    fn render(&self, query: Option<&str>) -> String {
         let icon = match self.status {
             Status::Pending => "[ ]",
             Status::InProgress => "[~]",
             Status::Completed => "[x]",
         };
+        let marker_style = match self.priority {
+            Priority::High => Style::Red,
+            Priority::Medium => Style::Yellow,
+            Priority::Low => Style::Blue,
+        };
+        let title = match query {
+            Some(query) => color::highlight(&self.title, query),
+            None => self.title.clone(),
+        };
         let tags = if self.tags.is_empty() {
             String::new()
         } else {
             format!(" [{}]", self.tags.join(", "))
         };
-        write!(
-            f,
+        let mut line = format!(
             "{} #{}: {} [{}]{}",
-            icon, self.id, self.title, self.priority, tags
-        )
+            color::paint(icon, marker_style),
+            self.id,
+            title,
+            self.priority,
+            tags
+        );
+        if let Some(due) = self.due {
+            line = format!("{} {}", line, t!("task.due", date = due));
+        }
+
+        if self.status == Status::Completed {
+            color::paint(&line, Style::Dim)
+        } else if self.is_overdue() {
+            color::paint(&line, Style::Bold)
+        } else {
+            line
+        }
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(None))
     }
 }
 
 #[derive(Debug)]
 enum TodoError {
     TaskNotFound(u32),
+    NoMatch(String),
+    AmbiguousId(String, usize),
+    PassphraseRequired(String),
+    WrongPassphrase,
+    InvalidInput(String),
     IoError(io::Error),
 }
 
 impl fmt::Display for TodoError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            TodoError::TaskNotFound(id) => write!(f, "Task #{} not found", id),
-            TodoError::IoError(e) => write!(f, "IO error: {}", e),
-        }
+        let message = match self {
+            TodoError::TaskNotFound(id) => t!("error.task_not_found", id = id),
+            TodoError::NoMatch(reference) => t!("error.no_match", reference = reference),
+            TodoError::AmbiguousId(prefix, count) => {
+                tn!("error.ambiguous_id", *count, prefix = prefix)
+            }
+            TodoError::PassphraseRequired(file) => t!("error.passphrase_required", file = file),
+            TodoError::WrongPassphrase => t!("error.wrong_passphrase"),
+            TodoError::InvalidInput(msg) => t!("error.invalid_input", message = msg),
+            TodoError::IoError(e) => t!("error.io", error = e),
+        };
+        write!(f, "{}", message)
     }
 }
 
@@ -151,14 +410,32 @@ impl From<io::Error> for TodoError {
 
 struct TodoApp {
     tasks: HashMap<u32, Task>,
+    // Kept in step with `tasks` by insert_task and remove_task.
+    index: TaskIndex,
     next_id: u32,
+    sort: SortSpec,
+    group: Option<GroupBy>,
+    show_uids: bool,
+    // Every change ever made, oldest first; `tasks` is the result of replaying it.
+    log: Vec<Event>,
+    // The last event included in the snapshot read from disk, and that
+    // snapshot's task lines (None until a snapshot has been loaded).
+    snapshot_seq: u64,
+    snapshot_text: Option<String>,
 }
 
 impl TodoApp {
     fn new() -> Self {
         TodoApp {
             tasks: HashMap::new(),
+            index: TaskIndex::default(),
             next_id: 1,
+            sort: SortSpec::default(),
+            group: None,
+            show_uids: false,
+            log: Vec::new(),
+            snapshot_seq: 0,
+            snapshot_text: None,
         }
     }
 
@@ -168,102 +445,269 @@ impl TodoApp {
         } else {
             vec![tag.to_string()]
         };
-        let task = Task::new(self.next_id, title, priority, tags);
-        println!("Added: {}", task);
-        self.tasks.insert(self.next_id, task);
-        self.next_id += 1;
-        self.next_id - 1
+        let id = self.next_id;
+        let task = Task::new(id, title, priority, tags);
+        println!("{}", t!("task.added", task = task));
+        self.record(EventKind::TaskAdded(task))
+            .expect("adding a new task cannot fail");
+        id
     }
 
     fn complete_task(&mut self, id: u32) -> Result<(), TodoError> {
-        match self.tasks.get_mut(&id) {
-            Some(task) => {
-                task.status = Status::Completed;
-                println!("Completed: {}", task);
-                Ok(())
+        let uid = self.uid_of(id)?;
+        self.record(EventKind::TaskCompleted(uid))?;
+        println!("{}", t!("task.completed", task = self.tasks[&id]));
+        Ok(())
+    }
+
+    fn uid_of(&self, id: u32) -> Result<TaskUid, TodoError> {
+        self.tasks
+            .get(&id)
+            .map(|t| t.uid)
+            .ok_or(TodoError::TaskNotFound(id))
+    }
+
+    // Resolves a task reference: a display number such as "3", or a unique
+    // prefix (at least 4 hex digits) of the task's uid.
+    fn resolve(&self, reference: &str) -> Result<u32, TodoError> {
+        let reference = reference.trim().trim_start_matches('#');
+        if let Ok(id) = reference.parse::<u32>()
+            && self.tasks.contains_key(&id)
+        {
+            return Ok(id);
+        }
+
+        let prefix: String = reference
+            .chars()
+            .filter(|&c| c != '-')
+            .collect::<String>()
+            .to_lowercase();
+        if prefix.len() < 4 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(TodoError::NoMatch(reference.to_string()));
+        }
+        let matches = self.index.ids_with_uid_prefix(&prefix);
+        match matches.as_slice() {
+            [id] => Ok(*id),
+            [] => Err(TodoError::NoMatch(reference.to_string())),
+            _ => Err(TodoError::AmbiguousId(reference.to_string(), matches.len())),
+        }
+    }
+
+    // Adds every task from `other` whose uid is not already present, giving it
+    // a fresh display number. Returns (added, already present).
+    fn merge(&mut self, other: TodoApp) -> Result<(usize, usize), TodoError> {
+        let mut incoming: Vec<Task> = other.tasks.into_values().collect();
+        incoming.sort_by_key(|t| t.id);
+        let mut added = Vec::new();
+        let mut skipped = 0;
+        for mut task in incoming {
+            if self.index.id_of(task.uid).is_some() {
+                skipped += 1;
+                continue;
+            }
+            task.id = self.next_id + added.len() as u32;
+            added.push(EventKind::TaskAdded(task));
+        }
+        let count = added.len();
+        self.record_all(added)?;
+        Ok((count, skipped))
+    }
+
+    fn set_due(&mut self, id: u32, due: Option<Date>) -> Result<(), TodoError> {
+        let uid = self.uid_of(id)?;
+        self.record(EventKind::DueChanged(uid, due))
+    }
+
+    fn set_estimate(&mut self, id: u32, estimate: Option<Effort>) -> Result<(), TodoError> {
+        let uid = self.uid_of(id)?;
+        self.record(EventKind::EstimateChanged(uid, estimate))
+    }
+
+    fn set_spent(&mut self, id: u32, spent: Option<Effort>) -> Result<(), TodoError> {
+        let uid = self.uid_of(id)?;
+        self.record(EventKind::SpentChanged(uid, spent))
+    }
+
+    fn set_notes(&mut self, id: u32, notes: String) -> Result<(), TodoError> {
+        let uid = self.uid_of(id)?;
+        self.record(EventKind::NotesChanged(uid, notes))
+    }
+
+    fn set_attachments(&mut self, id: u32, attachments: Vec<Attachment>) -> Result<(), TodoError> {
+        let uid = self.uid_of(id)?;
+        self.record(EventKind::AttachmentsChanged(uid, attachments))
+    }
+
+    // Prints every field of a task, including its notes and attachments.
+    fn show_task(&self, id: u32, data_dir: &Path) -> Result<(), TodoError> {
+        let task = self.tasks.get(&id).ok_or(TodoError::TaskNotFound(id))?;
+        println!("{}", t!("show.title", id = task.id, title = task.title));
+        let mut fields = vec![
+            (t!("show.uid"), task.uid.to_string()),
+            (t!("show.priority"), task.priority.to_string()),
+            (t!("show.status"), task.status.to_string()),
+        ];
+        if !task.tags.is_empty() {
+            fields.push((t!("show.tags"), task.tags.join(", ")));
+        }
+        if let Some(due) = task.due {
+            fields.push((t!("show.due"), due.to_string()));
+        }
+        if let Some(estimate) = task.estimate {
+            fields.push((t!("show.estimate"), estimate.to_string()));
+        }
+        if let Some(spent) = task.spent {
+            fields.push((t!("show.spent"), spent.to_string()));
+        }
+        if let Some(completed) = task.completed {
+            fields.push((t!("show.completed"), completed.to_string()));
+        }
+        if let Some(source) = &task.source {
+            fields.push((t!("show.source"), source.clone()));
+        }
+        // Labels differ in length between languages, so align on the longest.
+        let width = fields
+            .iter()
+            .map(|(label, _)| label.chars().count() + 1)
+            .max()
+            .unwrap_or(0);
+        for (label, value) in fields {
+            println!(
+                "  {:<width$} {}",
+                format!("{}:", label),
+                value,
+                width = width
+            );
+        }
+
+        if !task.notes.is_empty() {
+            println!("\n{}", t!("show.notes"));
+            for line in task.notes.lines() {
+                println!("  {}", line);
+            }
+        }
+
+        if !task.attachments.is_empty() {
+            println!("\n{}", t!("show.attachments"));
+            for (n, attachment) in task.attachments.iter().enumerate() {
+                let location = attachment.location(data_dir);
+                let kind = if attachment.copied {
+                    t!("show.attachment_copy")
+                } else {
+                    t!("show.attachment_link")
+                };
+                let size = match fs::metadata(&location) {
+                    Ok(meta) => tn!("show.attachment_size", meta.len()),
+                    Err(_) => t!("show.attachment_missing"),
+                };
+                println!("  {}. {} ({}, {})", n + 1, location.display(), kind, size);
             }
-            None => Err(TodoError::TaskNotFound(id)),
         }
+        Ok(())
+    }
+
+    fn delete_task(&mut self, id: u32) -> Result<Task, TodoError> {
+        let task = self
+            .tasks
+            .get(&id)
+            .cloned()
+            .ok_or(TodoError::TaskNotFound(id))?;
+        self.record(EventKind::TaskDeleted(task.uid))?;
+        Ok(task)
     }
 
     fn list_all(&self) {
-        let mut tasks: Vec<&Task> = self.tasks.values().collect();
-        tasks.sort_by_key(|t| t.id);
-        for task in tasks {
-            println!("  {}", task);
+        for line in self.list_lines(i18n::catalog()) {
+            println!("{}", line);
         }
     }
 
+    // Every task as `list` shows it, in the language of `catalog`.
+    fn list_lines(&self, catalog: &'static Catalog) -> Vec<String> {
+        i18n::with_catalog(catalog, || self.task_lines(self.tasks.values().collect()))
+    }
+
     fn list_by_status(&self, status: Status) {
-        for task in self.tasks.values().filter(|t| t.status == status) {
-            println!("  {}", task);
-        }
+        self.print_tasks(self.tasks_with_status(status));
     }
 
-    fn search(&self, query: &str) -> Vec<&Task> {
-        let q = query.to_lowercase();
-        self.tasks
-            .values()
-            .filter(|t| t.title.to_lowercase().contains(&q))
+    fn tasks_with_status(&self, status: Status) -> Vec<&Task> {
+        self.index
+            .with_status(status)
+            .map(|id| &self.tasks[&id])
             .collect()
     }
 
-    fn print_stats(&self) {
-        let total = self.tasks.len();
-        let completed = self
-            .tasks
-            .values()
-            .filter(|t| t.status == Status::Completed)
-            .count();
-        println!(
-            "Total: {}, Completed: {}, Pending: {}",
-            total,
-            completed,
-            total - completed
-        );
-        if total > 0 {
-            println!(
-                "Completion rate: {:.1}%",
-                (completed as f64 / total as f64) * 100.0
-            );
+    fn print_tasks(&self, tasks: Vec<&Task>) {
+        for line in self.task_lines(tasks) {
+            println!("{}", line);
         }
     }
 
-    fn save_to_file(&self, filename: &str) -> Result<(), TodoError> {
-        let mut file = File::create(filename)?;
-        for task in self.tasks.values() {
-            writeln!(
-                file,
-                "{}|{}|{}|{}|{}",
-                task.id,
-                task.title,
-                task.priority,
-                task.status,
-                task.tags.join(",")
-            )?;
+    // The lines listing `tasks` in the app's sort order, under group headers
+    // if grouping is set.
+    fn task_lines(&self, mut tasks: Vec<&Task>) -> Vec<String> {
+        self.sort.sort(&mut tasks);
+        let mut lines = Vec::new();
+        match self.group {
+            Some(group) => {
+                for (label, members) in sort::group_tasks(&tasks, group) {
+                    lines.push(format!("{} ({}):", label, members.len()));
+                    for task in members {
+                        lines.push(format!("  {}", self.task_line(task)));
+                    }
+                }
+            }
+            None => {
+                for task in tasks {
+                    lines.push(format!("  {}", self.task_line(task)));
+                }
+            }
         }
-        Ok(())
+        lines
+    }
+
+    fn task_line(&self, task: &Task) -> String {
+        if self.show_uids {
+            format!("{} {}", task.uid.short(), task)
+        } else {
+            task.to_string()
+        }
+    }
+
+    // Tasks whose title contains `query`, ignoring case. A blank query
+    // matches nothing rather than every title with a space in it.
+    fn search(&self, query: &str) -> Vec<&Task> {
+        if query.trim().is_empty() {
+            return Vec::new();
+        }
+        let q = query.to_lowercase();
+        let mut results: Vec<&Task> = match self.index.search_candidates(&q) {
+            Some(ids) => ids
+                .into_iter()
+                .map(|id| &self.tasks[&id])
+                .filter(|t| t.title.to_lowercase().contains(&q))
+                .collect(),
+            None => self.tasks.values().collect(),
+        };
+        self.sort.sort(&mut results);
+        results
     }
 }
 
+// Prints the closing message in a box sized to fit the translated text.
 fn print_completion_message() {
-    println!(
-        "
-+-------------------------------------------------------+
-|  Congratulations on completing 21 Days of Rust!       |
-|                                                       |
-|  You've learned:                                      |
-|  - Variables, Data Types, Functions                   |
-|  - Ownership, Borrowing, Lifetimes                    |
-|  - Structs, Enums, Pattern Matching                   |
-|  - Error Handling, Collections                        |
-|  - Generics, Traits                                   |
-|  - Closures, Iterators                                |
-|  - Smart Pointers, Concurrency                        |
-|  - Modules, Crates, Macros                            |
-|                                                       |
-|  Keep practicing and building projects!               |
-+-------------------------------------------------------+
-    "
-    );
+    let message = t!("banner");
+    let width = message
+        .lines()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        + 4;
+    let border = format!("+{}+", "-".repeat(width));
+    println!("\n{}", border);
+    for line in message.lines() {
+        println!("|  {:<w$}|", line, w = width - 2);
+    }
+    println!("{}", border);
 }
@@ -0,0 +1,376 @@
+// Importing tasks from Markdown checklists.
+// Every "- [ ] item" or "- [x] item" line of a Markdown file becomes a task:
+// the headings above it become tags, a nested item gets a "sub:<parent uid>"
+// tag, and a "!high" / "!low" marker sets the priority. Imported tasks
+// remember the file they came from, so importing it again only adds new
+// items and completion can be written back into the file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::events::EventKind;
+use crate::storage::write_atomically;
+use crate::uid::TaskUid;
+use crate::{Priority, Status, Task, TodoApp, TodoError};
+
+#[derive(Debug, Clone)]
+pub(crate) struct ChecklistItem {
+    // Zero-based line number in the file.
+    pub(crate) line: usize,
+    pub(crate) checked: bool,
+    pub(crate) title: String,
+    pub(crate) priority: Option<Priority>,
+    // Tags made from the enclosing headings, outermost first.
+    pub(crate) headings: Vec<String>,
+    // Index of the enclosing checklist item, for nested items.
+    pub(crate) parent: Option<usize>,
+}
+
+// Finds every checklist item in `text`, skipping fenced code blocks.
+pub(crate) fn parse_checklist(text: &str) -> Vec<ChecklistItem> {
+    let mut items: Vec<ChecklistItem> = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    // (indent, item index) of the checklist items that may be parents.
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            continue;
+        }
+
+        if let Some((level, title)) = parse_heading(trimmed) {
+            headings.retain(|&(l, _)| l < level);
+            let tag = slug(title);
+            if !tag.is_empty() {
+                headings.push((level, tag));
+            }
+            open.clear();
+            continue;
+        }
+
+        let Some((checked, text)) = parse_item(trimmed) else {
+            continue;
+        };
+        let indent = indent_width(line);
+        while open.last().is_some_and(|&(i, _)| i >= indent) {
+            open.pop();
+        }
+        let (title, priority) = strip_priority(text);
+        if title.is_empty() {
+            continue;
+        }
+        items.push(ChecklistItem {
+            line: line_no,
+            checked,
+            title,
+            priority,
+            headings: headings.iter().map(|(_, tag)| tag.clone()).collect(),
+            parent: open.last().map(|&(_, index)| index),
+        });
+        open.push((indent, items.len() - 1));
+    }
+    items
+}
+
+// "## Action items" -> (2, "Action items")
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim()))
+}
+
+// "- [x] text", "* [ ] text" or "1. [ ] text" -> (checked, "text")
+fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let rest = if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        rest
+    } else {
+        let digits = line.chars().take_while(char::is_ascii_digit).count();
+        if digits == 0 {
+            return None;
+        }
+        line[digits..].strip_prefix(['.', ')'])?
+    };
+    let rest = rest.strip_prefix([' ', '\t'])?.trim_start();
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &rest[3..];
+    if !text.is_empty() && !text.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((checked, text.trim()))
+}
+
+// Tabs count as four columns, as in most editors.
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+// Removes "!high"-style markers from an item, returning the cleaned title and
+// the last valid priority. Words starting with '!' that are not priorities stay.
+fn strip_priority(text: &str) -> (String, Option<Priority>) {
+    let mut priority = None;
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        match word.strip_prefix('!').map(str::parse::<Priority>) {
+            Some(Ok(p)) => priority = Some(p),
+            _ => words.push(word),
+        }
+    }
+    (words.join(" "), priority)
+}
+
+// "Action Items (Q3)" -> "action-items-q3"
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// The name imported tasks record as their source.
+pub(crate) fn source_name(path: &Path) -> Result<String, TodoError> {
+    Ok(fs::canonicalize(path)?.display().to_string())
+}
+
+impl TodoApp {
+    // Maps (source, title) to the uid of every task ever imported, including
+    // tasks deleted since, so deleting an imported task does not bring it back.
+    fn imported(&self) -> HashMap<(String, String), TaskUid> {
+        self.log
+            .iter()
+            .filter_map(|event| match &event.kind {
+                EventKind::TaskAdded(task) => task
+                    .source
+                    .clone()
+                    .map(|source| ((source, task.title.clone()), task.uid)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Adds the checklist items of `text` that were not imported from `source`
+    // before, as one transaction; repeated items become a single task.
+    // Returns (added, skipped).
+    pub(crate) fn import_checklist(
+        &mut self,
+        text: &str,
+        source: &str,
+    ) -> Result<(usize, usize), TodoError> {
+        let mut imported = self.imported();
+        let items = parse_checklist(text);
+        // Uid of each item's task, new or previously imported.
+        let mut uids: Vec<TaskUid> = Vec::with_capacity(items.len());
+        let mut added = Vec::new();
+        let mut skipped = 0;
+
+        for item in &items {
+            let key = (source.to_string(), item.title.clone());
+            if let Some(&uid) = imported.get(&key) {
+                uids.push(uid);
+                skipped += 1;
+                continue;
+            }
+            let mut tags = item.headings.clone();
+            // The uid, unlike the display number, still names the parent
+            // after the list is renumbered.
+            if let Some(parent) = item.parent {
+                tags.push(format!("sub:{}", uids[parent]));
+            }
+            let id = self.next_id + added.len() as u32;
+            let mut task = Task::new(
+                id,
+                &item.title,
+                item.priority.unwrap_or(Priority::Medium),
+                tags,
+            );
+            if item.checked {
+                task.status = Status::Completed;
+            }
+            task.source = Some(source.to_string());
+            imported.insert(key, task.uid);
+            uids.push(task.uid);
+            added.push(EventKind::TaskAdded(task));
+        }
+
+        let count = added.len();
+        self.record_all(added)?;
+        Ok((count, skipped))
+    }
+
+    // Rewrites the checkboxes of `path` to match the completion of the tasks
+    // imported from it. Returns the number of checkboxes changed.
+    pub(crate) fn write_back(&self, path: &Path) -> Result<usize, TodoError> {
+        let source = source_name(path)?;
+        let text = fs::read_to_string(path)?;
+        let imported = self.imported();
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        let mut changed = 0;
+
+        for item in parse_checklist(&text) {
+            let Some(task) = imported
+                .get(&(source.clone(), item.title.clone()))
+                .and_then(|&uid| self.index.id_of(uid))
+                .map(|id| &self.tasks[&id])
+            else {
+                continue;
+            };
+            let done = task.status == Status::Completed;
+            if done == item.checked {
+                continue;
+            }
+            let line = &mut lines[item.line];
+            let (from, to) = if done { ("[ ]", "[x]") } else { ("[x]", "[ ]") };
+            let from = if !done && line.contains("[X]") {
+                "[X]"
+            } else {
+                from
+            };
+            if let Some(start) = line.find(from) {
+                line.replace_range(start..start + 3, to);
+                changed += 1;
+            }
+        }
+
+        if changed > 0 {
+            let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+            let mut out = lines.join(newline);
+            if text.ends_with('\n') {
+                out.push_str(newline);
+            }
+            write_atomically(&path.display().to_string(), out.as_bytes())?;
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    const NOTES: &str = "\
+# Meeting
+Some text.
+## Action Items (Q3)
+- [ ] Send the budget !high
+  - [ ] Check Q3 numbers
+    * [X] Ask finance !low
+- [x] Update the roadmap
+```
+- [ ] not a task
+```
+1. [ ] Book a room !urgent
+-[ ] no space
+- [ ]
+";
+
+    fn task_titled<'a>(app: &'a TodoApp, title: &str) -> &'a Task {
+        app.tasks.values().find(|t| t.title == title).unwrap()
+    }
+
+    #[test]
+    fn parses_items_headings_and_nesting() {
+        let items = parse_checklist(NOTES);
+        let titles: Vec<&str> = items.iter().map(|i| i.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Send the budget",
+                "Check Q3 numbers",
+                "Ask finance",
+                "Update the roadmap",
+                "Book a room !urgent",
+            ]
+        );
+        assert_eq!(items[0].line, 3);
+        assert_eq!(items[0].priority, Some(Priority::High));
+        assert_eq!(items[0].headings, vec!["meeting", "action-items-q3"]);
+        assert_eq!(
+            items.iter().map(|i| i.parent).collect::<Vec<_>>(),
+            vec![None, Some(0), Some(1), None, None]
+        );
+        assert_eq!(
+            items.iter().map(|i| i.checked).collect::<Vec<_>>(),
+            vec![false, false, true, true, false]
+        );
+        assert_eq!(items[4].priority, None);
+    }
+
+    #[test]
+    fn nested_items_are_tagged_with_the_parent_uid() {
+        let mut app = TodoApp::new();
+        assert_eq!(app.import_checklist(NOTES, "notes.md").unwrap(), (5, 0));
+
+        let parent = task_titled(&app, "Send the budget").uid;
+        let child = task_titled(&app, "Check Q3 numbers");
+        assert!(child.tags.contains(&format!("sub:{}", parent)));
+        let grandchild = task_titled(&app, "Ask finance");
+        assert!(grandchild.tags.contains(&format!("sub:{}", child.uid)));
+        assert_eq!(grandchild.status, Status::Completed);
+        assert_eq!(grandchild.priority, Priority::Low);
+    }
+
+    #[test]
+    fn importing_again_adds_only_new_items() {
+        let mut app = TodoApp::new();
+        app.import_checklist(NOTES, "notes.md").unwrap();
+        let deleted = task_titled(&app, "Update the roadmap").uid;
+        app.record(EventKind::TaskDeleted(deleted)).unwrap();
+
+        let text = format!("{}  - [ ] Follow up\n- [ ] Send the budget\n", NOTES);
+        assert_eq!(app.import_checklist(&text, "notes.md").unwrap(), (1, 6));
+        assert_eq!(app.tasks.len(), 5);
+        // The same file under another name is imported afresh.
+        assert_eq!(app.import_checklist(NOTES, "other.md").unwrap(), (5, 0));
+    }
+
+    #[test]
+    fn write_back_updates_the_checkboxes() {
+        let path = env::temp_dir().join(format!("day21-markdown-{}.md", process::id()));
+        fs::write(&path, NOTES).unwrap();
+        let mut app = TodoApp::new();
+        app.import_checklist(NOTES, &source_name(&path).unwrap())
+            .unwrap();
+
+        let child = task_titled(&app, "Check Q3 numbers").uid;
+        let grandchild = task_titled(&app, "Ask finance").uid;
+        app.record_all(vec![
+            EventKind::TaskCompleted(child),
+            EventKind::StatusChanged(grandchild, Status::Pending),
+        ])
+        .unwrap();
+        let changed = app.write_back(&path);
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(changed.unwrap(), 2);
+        let expected = NOTES
+            .replace("  - [ ] Check Q3", "  - [x] Check Q3")
+            .replace("* [X] Ask", "* [ ] Ask");
+        assert_eq!(text, expected);
+    }
+}
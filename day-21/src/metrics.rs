@@ -0,0 +1,477 @@
+// Statistics in the Prometheus text exposition format.
+// `metrics` prints them once, e.g. for a node exporter's textfile collector;
+// `metrics --serve [--port N]` answers GET /metrics on the loopback
+// interface, loading the todo file again whenever it has changed.
+// Task counts are gauges; the changes recorded in the event log are counted
+// by kind as `todo_operations_total`.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use crate::cli::{take_flag, take_option};
+use crate::events;
+use crate::stats::key;
+use crate::watch::{self, FileState};
+use crate::{TodoApp, TodoError};
+
+const DEFAULT_PORT: u16 = 9464;
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+// Slow or silent clients are dropped so that they cannot block the scraper.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_HEADER_LINES: usize = 100;
+
+pub(crate) fn run(
+    mut app: TodoApp,
+    filename: &str,
+    passphrase: Option<&str>,
+    mut args: Vec<String>,
+) -> Result<(), TodoError> {
+    let serve = take_flag(&mut args, "--serve");
+    let port = match take_option(&mut args, "--port")? {
+        Some(value) => value
+            .parse()
+            .map_err(|_| TodoError::InvalidInput(t!("error.bad_port", value = value)))?,
+        None => DEFAULT_PORT,
+    };
+    if !serve {
+        print!("{}", app.metrics(events::now()));
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    println!(
+        "{}",
+        t!("metrics.listening", address = listener.local_addr()?)
+    );
+    let mut state = FileState::read(filename)?;
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        // A file that cannot be read right now (e.g. halfway through a
+        // write) is tried again on the next request.
+        match FileState::read(filename) {
+            Ok(current) if current != state => match watch::load(filename, passphrase) {
+                Ok(fresh) => {
+                    app = fresh;
+                    state = current;
+                }
+                Err(e) => eprintln!(
+                    "{}",
+                    t!("metrics.reload_failed", file = filename, error = e)
+                ),
+            },
+            Ok(_) => {}
+            Err(e) => eprintln!(
+                "{}",
+                t!("metrics.reload_failed", file = filename, error = e)
+            ),
+        }
+        // A client that hangs up early is its own problem.
+        let _ = respond(&mut stream, &app);
+    }
+    Ok(())
+}
+
+fn respond(stream: &mut TcpStream, app: &TodoApp) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(&*stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers are read and ignored.
+    for _ in 0..MAX_HEADER_LINES {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+    let (status, body) = match (method, path) {
+        ("GET" | "HEAD", "/metrics") => ("200 OK", app.metrics(events::now())),
+        ("GET" | "HEAD", _) => ("404 Not Found", "Not found; try /metrics\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "Only GET is supported\n".to_string(),
+        ),
+    };
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        CONTENT_TYPE,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+impl TodoApp {
+    // The exposition of every metric as seen at `now`.
+    pub(crate) fn metrics(&self, now: u64) -> String {
+        let stats = self.stats(now);
+        let mut out = String::new();
+
+        family(&mut out, "todo_tasks", "gauge", "Tasks by status.");
+        for (status, count) in &stats.by_status {
+            sample(
+                &mut out,
+                "todo_tasks",
+                &[("status", &key(status.name()))],
+                *count as f64,
+            );
+        }
+
+        family(
+            &mut out,
+            "todo_priority_tasks",
+            "gauge",
+            "Open and completed tasks by priority.",
+        );
+        for (priority, group) in &stats.by_priority {
+            let priority = key(priority.name());
+            for (state, count) in [
+                ("open", group.total - group.completed),
+                ("completed", group.completed),
+            ] {
+                sample(
+                    &mut out,
+                    "todo_priority_tasks",
+                    &[("priority", &priority), ("state", state)],
+                    count as f64,
+                );
+            }
+        }
+
+        family(
+            &mut out,
+            "todo_tag_tasks",
+            "gauge",
+            "Open and completed tasks by tag (lowercased).",
+        );
+        for (tag, group) in &stats.by_tag {
+            for (state, count) in [
+                ("open", group.total - group.completed),
+                ("completed", group.completed),
+            ] {
+                sample(
+                    &mut out,
+                    "todo_tag_tasks",
+                    &[("tag", tag), ("state", state)],
+                    count as f64,
+                );
+            }
+        }
+
+        family(
+            &mut out,
+            "todo_overdue_tasks",
+            "gauge",
+            "Open tasks whose due date has passed.",
+        );
+        let overdue = self.tasks.values().filter(|t| t.is_overdue()).count();
+        sample(&mut out, "todo_overdue_tasks", &[], overdue as f64);
+
+        family(
+            &mut out,
+            "todo_operations_total",
+            "counter",
+            "Changes recorded in the event log, by kind.",
+        );
+        let mut operations: BTreeMap<&str, usize> = BTreeMap::new();
+        for event in &self.log {
+            *operations.entry(event.kind.name()).or_default() += 1;
+        }
+        for (operation, count) in operations {
+            sample(
+                &mut out,
+                "todo_operations_total",
+                &[("operation", operation)],
+                count as f64,
+            );
+        }
+
+        if let Some(event) = self.log.last() {
+            family(
+                &mut out,
+                "todo_last_change_timestamp_seconds",
+                "gauge",
+                "Time of the most recent change, in seconds since the epoch.",
+            );
+            sample(
+                &mut out,
+                "todo_last_change_timestamp_seconds",
+                &[],
+                event.at as f64,
+            );
+        }
+        out
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} {}\n",
+        name, help, name, kind
+    ));
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+            .collect();
+        out.push_str(&format!("{{{}}}", labels.join(",")));
+    }
+    out.push_str(&format!(" {}\n", value));
+}
+
+// Label values escape backslashes, double quotes and line feeds.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::*;
+    use crate::events::EventKind;
+    use crate::{Priority, Task};
+
+    const TRICKY_TAG: &str = "Say \"Hi\" \\ back\nnext";
+
+    #[derive(Debug)]
+    struct Sample {
+        name: String,
+        labels: BTreeMap<String, String>,
+        value: f64,
+    }
+
+    // Reads the exposition back, checking that every family has HELP and
+    // TYPE lines, in that order and once, before its samples. Returns the
+    // type of each family and the samples.
+    fn parse(text: &str) -> (HashMap<String, String>, Vec<Sample>) {
+        let mut helped = HashSet::new();
+        let mut types = HashMap::new();
+        let mut samples = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let (name, help) = rest.split_once(' ').unwrap();
+                assert!(!help.trim().is_empty(), "{}", line);
+                assert!(helped.insert(name.to_string()), "HELP twice: {}", line);
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').unwrap();
+                assert!(helped.contains(name), "TYPE before HELP: {}", line);
+                let previous = types.insert(name.to_string(), kind.to_string());
+                assert!(previous.is_none(), "TYPE twice: {}", line);
+            } else {
+                let (series, value) = line.rsplit_once(' ').unwrap();
+                let (name, labels) = match series.split_once('{') {
+                    Some((name, labels)) => (name, parse_labels(labels.strip_suffix('}').unwrap())),
+                    None => (series, BTreeMap::new()),
+                };
+                assert!(types.contains_key(name), "sample before TYPE: {}", line);
+                samples.push(Sample {
+                    name: name.to_string(),
+                    labels,
+                    value: value.parse().unwrap(),
+                });
+            }
+        }
+        (types, samples)
+    }
+
+    // name="value",... with \\, \" and \n escapes in the values.
+    fn parse_labels(text: &str) -> BTreeMap<String, String> {
+        let mut labels = BTreeMap::new();
+        let mut chars = text.chars();
+        loop {
+            let name: String = chars.by_ref().take_while(|&c| c != '=').collect();
+            assert_eq!(chars.next(), Some('"'), "in {}", text);
+            let mut value = String::new();
+            loop {
+                match chars.next().unwrap() {
+                    '"' => break,
+                    '\\' => value.push(match chars.next().unwrap() {
+                        'n' => '\n',
+                        c @ ('\\' | '"') => c,
+                        c => panic!("bad escape \\{} in {}", c, text),
+                    }),
+                    '\n' => panic!("raw line feed in {}", text),
+                    c => value.push(c),
+                }
+            }
+            labels.insert(name, value);
+            match chars.next() {
+                Some(',') => continue,
+                None => return labels,
+                Some(c) => panic!("unexpected '{}' in {}", c, text),
+            }
+        }
+    }
+
+    fn value(samples: &[Sample], name: &str, labels: &[(&str, &str)]) -> f64 {
+        let labels: BTreeMap<String, String> = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        samples
+            .iter()
+            .find(|s| s.name == name && s.labels == labels)
+            .unwrap_or_else(|| panic!("no sample {} {:?}", name, labels))
+            .value
+    }
+
+    // Three tasks added, one completed, one retagged and one deleted; the
+    // remaining open task is overdue.
+    fn app() -> TodoApp {
+        let mut app = TodoApp::new();
+        let mut overdue = Task::new(1, "Pay rent", Priority::High, vec!["home".to_string()]);
+        overdue.due = Some("2000-01-01".parse().unwrap());
+        app.record(EventKind::TaskAdded(overdue)).unwrap();
+        for (id, title) in [(2, "Write report"), (3, "Old idea")] {
+            let task = Task::new(id, title, Priority::Low, vec!["Work".to_string()]);
+            app.record(EventKind::TaskAdded(task)).unwrap();
+        }
+        let report = app.uid_of(2).unwrap();
+        app.record(EventKind::TaskCompleted(report)).unwrap();
+        app.record(EventKind::TagsChanged(
+            report,
+            vec!["Work".to_string(), TRICKY_TAG.to_string()],
+        ))
+        .unwrap();
+        let idea = app.uid_of(3).unwrap();
+        app.record(EventKind::TaskDeleted(idea)).unwrap();
+        app
+    }
+
+    #[test]
+    fn exposition_is_well_formed() {
+        let (types, samples) = parse(&app().metrics(events::now()));
+        let valid_name = |name: &str| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+        };
+        for (name, kind) in &types {
+            assert!(valid_name(name), "bad metric name {}", name);
+            assert!(name.starts_with("todo_"), "{}", name);
+            match kind.as_str() {
+                "counter" => assert!(name.ends_with("_total"), "{}", name),
+                "gauge" => assert!(!name.ends_with("_total"), "{}", name),
+                other => panic!("unexpected type {} for {}", other, name),
+            }
+        }
+        let expected = [
+            "todo_tasks",
+            "todo_priority_tasks",
+            "todo_tag_tasks",
+            "todo_overdue_tasks",
+            "todo_operations_total",
+            "todo_last_change_timestamp_seconds",
+        ];
+        let mut names: Vec<&str> = types.keys().map(String::as_str).collect();
+        names.sort();
+        let mut expected = expected.to_vec();
+        expected.sort();
+        assert_eq!(names, expected);
+
+        let mut series = HashSet::new();
+        for sample in &samples {
+            assert!(sample.value >= 0.0, "{:?}", sample);
+            assert!(
+                series.insert((sample.name.clone(), sample.labels.clone())),
+                "duplicate series {:?}",
+                sample
+            );
+        }
+    }
+
+    #[test]
+    fn counts_tasks_and_operations() {
+        let app = app();
+        let (_, samples) = parse(&app.metrics(events::now()));
+        let tasks = |status| value(&samples, "todo_tasks", &[("status", status)]);
+        assert_eq!(tasks("pending"), 1.0);
+        assert_eq!(tasks("in_progress"), 0.0);
+        assert_eq!(tasks("completed"), 1.0);
+
+        let priority = |priority, state| {
+            value(
+                &samples,
+                "todo_priority_tasks",
+                &[("priority", priority), ("state", state)],
+            )
+        };
+        assert_eq!(priority("high", "open"), 1.0);
+        assert_eq!(priority("low", "completed"), 1.0);
+        assert_eq!(priority("medium", "open"), 0.0);
+
+        let tag = |tag, state| {
+            value(
+                &samples,
+                "todo_tag_tasks",
+                &[("tag", tag), ("state", state)],
+            )
+        };
+        assert_eq!(tag("work", "completed"), 1.0);
+        assert_eq!(tag("home", "open"), 1.0);
+        assert_eq!(value(&samples, "todo_overdue_tasks", &[]), 1.0);
+
+        let operations = |operation| {
+            value(
+                &samples,
+                "todo_operations_total",
+                &[("operation", operation)],
+            )
+        };
+        assert_eq!(operations("added"), 3.0);
+        assert_eq!(operations("completed"), 1.0);
+        assert_eq!(operations("tags"), 1.0);
+        assert_eq!(operations("deleted"), 1.0);
+        assert_eq!(
+            value(&samples, "todo_last_change_timestamp_seconds", &[]),
+            app.log.last().unwrap().at as f64
+        );
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let text = app().metrics(events::now());
+        assert!(
+            text.contains(r#"tag="say \"hi\" \\ back\nnext""#),
+            "{}",
+            text
+        );
+        let (_, samples) = parse(&text);
+        let tricky = TRICKY_TAG.to_lowercase();
+        assert_eq!(
+            value(
+                &samples,
+                "todo_tag_tasks",
+                &[("tag", &tricky), ("state", "completed")]
+            ),
+            1.0
+        );
+    }
+
+    #[test]
+    fn an_empty_list_has_no_last_change() {
+        let (types, samples) = parse(&TodoApp::new().metrics(events::now()));
+        assert!(!types.contains_key("todo_last_change_timestamp_seconds"));
+        assert!(samples.iter().all(|s| s.value == 0.0));
+        assert!(!samples.iter().any(|s| s.name == "todo_operations_total"));
+    }
+}
@@ -0,0 +1,546 @@
+// Versions of the todo file format and the upgrades between them.
+// Since version 2 the file starts with a header line naming its version:
+//   #schema|<version>
+// Files without one are version 1. Loading runs the migrations from the
+// file's version up to SCHEMA_VERSION in order, each turning the text of one
+// version into the next, so the parser only ever sees the current format.
+//
+//   1  No header. Either plain task lines from before the event log, or a
+//      snapshot and event log; task lines have 5 to 10 fields, depending on
+//      which fields existed when they were written.
+//   2  Header line; every task line, including those inside "added" events,
+//      has all 10 fields.
+//   3  Task lines gain estimate, spent effort and completion date fields
+//      (13 fields). Completion dates of tasks in the snapshot are taken from
+//      the events that completed them; completions replayed from the log get
+//      theirs on load.
+//   4  Titles and tags are escaped like notes, with commas inside a tag
+//      written as \c. A version 3 task line with more than 13 fields had `|`
+//      in its title, and the extra fields are joined back into the title.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::date::Date;
+use crate::events::now;
+use crate::storage::{self, encode_tags, escape_field, unescape_field};
+use crate::uid::TaskUid;
+use crate::{TodoApp, TodoError, crypto};
+
+// Version of the format written by `TodoApp::to_text`.
+pub(crate) const SCHEMA_VERSION: u32 = 4;
+pub(crate) const HEADER: &str = "#schema|";
+
+// Turns the text of one version into the next; the second argument names
+// the file in errors.
+type Migration = fn(&str, &str) -> Result<String, TodoError>;
+
+// MIGRATIONS[n] upgrades version n + 1 to version n + 2.
+const MIGRATIONS: &[Migration] = &[v1_to_v2, v2_to_v3, v3_to_v4];
+
+// The version of a todo file's text; `source` names the file in errors.
+pub(crate) fn version_of(text: &str, source: &str) -> Result<u32, TodoError> {
+    let Some(first) = text.lines().find(|l| !l.trim().is_empty()) else {
+        return Ok(SCHEMA_VERSION);
+    };
+    let Some(version) = first.strip_prefix(HEADER) else {
+        return Ok(1);
+    };
+    let version: u32 = version
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&v| v >= 1)
+        .ok_or_else(|| {
+            TodoError::InvalidInput(t!("error.bad_schema", file = source, value = version))
+        })?;
+    if version > SCHEMA_VERSION {
+        return Err(TodoError::InvalidInput(t!(
+            "error.newer_schema",
+            file = source,
+            version = version,
+            supported = SCHEMA_VERSION
+        )));
+    }
+    Ok(version)
+}
+
+// Upgrades `text` to SCHEMA_VERSION; text already in it is borrowed as is.
+pub(crate) fn upgrade<'a>(text: &'a str, source: &str) -> Result<Cow<'a, str>, TodoError> {
+    let mut text = Cow::Borrowed(text);
+    for migration in &MIGRATIONS[version_of(&text, source)? as usize - 1..] {
+        text = Cow::Owned(migration(&text, source)?);
+    }
+    Ok(text)
+}
+
+// If `filename` holds an older version, keeps a copy of it next to the file
+// and rewrites it in the current format, encrypted again when `passphrase`
+// is given. Returns the old version and the backup's name.
+pub(crate) fn migrate_file(
+    filename: &str,
+    passphrase: Option<&str>,
+) -> Result<Option<(u32, String)>, TodoError> {
+    let data = fs::read(filename)?;
+    let data = match passphrase {
+        Some(secret) => crypto::decrypt(&data, secret)?,
+        None => data,
+    };
+    let text = String::from_utf8(data)
+        .map_err(|_| TodoError::InvalidInput(t!("error.not_utf8", file = filename)))?;
+    let version = version_of(&text, filename)?;
+    if version == SCHEMA_VERSION {
+        return Ok(None);
+    }
+
+    let app = TodoApp::from_text(&text, filename)?;
+    let backup = format!("{}.v{}.bak", filename, version);
+    fs::copy(filename, &backup)?;
+    match passphrase {
+        Some(secret) => app.save_encrypted(filename, secret)?,
+        None => storage::write_atomically(filename, app.to_text().as_bytes())?,
+    }
+    Ok(Some((version, backup)))
+}
+
+// Adds the header, fills every task line up to all 10 fields, and turns the
+// tasks of files from before the event log into "added" events.
+fn v1_to_v2(text: &str, source: &str) -> Result<String, TodoError> {
+    let has_log = text
+        .lines()
+        .any(|l| l.starts_with("#snapshot|") || l.starts_with('@'));
+    let mut out = format!("{}2\n", HEADER);
+
+    if !has_log {
+        let mut tasks = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            if !line.trim().is_empty() {
+                let location = format!("{}:{}", source, n + 1);
+                let line = fill_task_line(line, &location)?;
+                let id: u32 = line
+                    .split('|')
+                    .next()
+                    .and_then(|id| id.parse().ok())
+                    .unwrap_or(0);
+                tasks.push((id, line));
+            }
+        }
+        tasks.sort_by_key(|&(id, _)| id);
+        out.push_str("#snapshot|0\n");
+        let at = now();
+        for (seq, (_, line)) in tasks.iter().enumerate() {
+            out.push_str(&format!(
+                "@{}|{}|added|{}\n",
+                seq + 1,
+                at,
+                escape_field(line)
+            ));
+        }
+        return Ok(out);
+    }
+
+    for (n, line) in text.lines().enumerate() {
+        let location = format!("{}:{}", source, n + 1);
+        if line.trim().is_empty() || line.starts_with("#snapshot|") {
+            out.push_str(line);
+        } else if let Some(event) = line.strip_prefix('@') {
+            let fields: Vec<&str> = event.splitn(4, '|').collect();
+            match fields.as_slice() {
+                [seq, at, "added", task] => {
+                    let task = fill_task_line(&unescape_field(task), &location)?;
+                    out.push_str(&format!("@{}|{}|added|{}", seq, at, escape_field(&task)));
+                }
+                _ => out.push_str(line),
+            }
+        } else {
+            out.push_str(&fill_task_line(line, &location)?);
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// Pads a version 1 task line to all 10 fields; a missing uid is generated.
+fn fill_task_line(line: &str, location: &str) -> Result<String, TodoError> {
+    let mut fields: Vec<String> = line.split('|').map(String::from).collect();
+    if !(5..=10).contains(&fields.len()) {
+        return Err(TodoError::InvalidInput(t!(
+            "error.bad_v1_field_count",
+            location = location,
+            found = fields.len()
+        )));
+    }
+    fields.resize(10, String::new());
+    if fields[6].is_empty() {
+        fields[6] = TaskUid::generate().to_string();
+    }
+    Ok(fields.join("|"))
+}
+
+// Adds empty estimate and spent fields and the completion date to every task
+// line. Snapshot tasks that are completed get the day of the event that
+// completed them, if the log has one (not for tasks added as completed).
+fn v2_to_v3(text: &str, _source: &str) -> Result<String, TodoError> {
+    let snapshot_seq: u64 = text
+        .lines()
+        .find_map(|l| l.strip_prefix("#snapshot|"))
+        .and_then(|seq| seq.parse().ok())
+        .unwrap_or(0);
+
+    // Day each task was last completed, as of the snapshot.
+    let mut completed: HashMap<String, Option<Date>> = HashMap::new();
+    for event in text.lines().filter_map(|l| l.strip_prefix('@')) {
+        let fields: Vec<&str> = event.split('|').collect();
+        let (Some(seq), Some(at)) = (
+            fields.first().and_then(|s| s.parse::<u64>().ok()),
+            fields.get(1).and_then(|s| s.parse::<u64>().ok()),
+        ) else {
+            continue;
+        };
+        if seq > snapshot_seq {
+            continue;
+        }
+        let day = Date::from_timestamp(at);
+        match fields[2..] {
+            ["added", task] => {
+                let task = unescape_field(task);
+                let task: Vec<&str> = task.split('|').collect();
+                if let Some(uid) = task.get(6) {
+                    completed.insert(uid.to_string(), None);
+                }
+            }
+            ["completed", uid] => {
+                completed
+                    .entry(uid.to_string())
+                    .or_default()
+                    .get_or_insert(day);
+            }
+            ["status", uid, status] => {
+                let entry = completed.entry(uid.to_string()).or_default();
+                if status != "Completed" {
+                    *entry = None;
+                } else if entry.is_none() {
+                    *entry = Some(day);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        if line.starts_with(HEADER) {
+            out.push_str(&format!("{}3", HEADER));
+        } else if line.trim().is_empty() || line.starts_with("#snapshot|") {
+            out.push_str(line);
+        } else if let Some(event) = line.strip_prefix('@') {
+            let fields: Vec<&str> = event.splitn(4, '|').collect();
+            match fields.as_slice() {
+                [seq, at, "added", task] => {
+                    let task = format!("{}|||", unescape_field(task));
+                    out.push_str(&format!("@{}|{}|added|{}", seq, at, escape_field(&task)));
+                }
+                _ => out.push_str(line),
+            }
+        } else {
+            let fields: Vec<&str> = line.split('|').collect();
+            let day = match (fields.get(3), fields.get(6)) {
+                (Some(&"Completed"), Some(uid)) => completed.get(*uid).copied().flatten(),
+                _ => None,
+            };
+            let day = day.map(|d| d.to_string()).unwrap_or_default();
+            out.push_str(&format!("{}|||{}", line, day));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// Escapes the title and tags of every task line, and the tags of every "tags"
+// event, which were written as one escaped list.
+fn v3_to_v4(text: &str, _source: &str) -> Result<String, TodoError> {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        if line.starts_with(HEADER) {
+            out.push_str(&format!("{}4", HEADER));
+        } else if line.trim().is_empty() || line.starts_with("#snapshot|") {
+            out.push_str(line);
+        } else if let Some(event) = line.strip_prefix('@') {
+            let fields: Vec<&str> = event.splitn(5, '|').collect();
+            match fields.as_slice() {
+                [seq, at, "added", ..] => {
+                    let task = event.splitn(4, '|').nth(3).unwrap_or_default();
+                    let task = escape_task_line(&unescape_field(task));
+                    out.push_str(&format!("@{}|{}|added|{}", seq, at, escape_field(&task)));
+                }
+                [seq, at, "tags", uid, tags] => {
+                    let tags = split_v3_tags(&unescape_field(tags));
+                    out.push_str(&format!(
+                        "@{}|{}|tags|{}|{}",
+                        seq,
+                        at,
+                        uid,
+                        encode_tags(&tags)
+                    ));
+                }
+                _ => out.push_str(line),
+            }
+        } else {
+            out.push_str(&escape_task_line(line));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// A version 3 task line with its title and tags escaped. Lines with too few
+// fields are left for the parser to report.
+fn escape_task_line(line: &str) -> String {
+    let fields: Vec<&str> = line.split('|').collect();
+    if fields.len() < 13 {
+        return line.to_string();
+    }
+    let extra = fields.len() - 13;
+    let title = fields[1..=1 + extra].join("|");
+    let rest = &fields[2 + extra..];
+    let mut out = vec![
+        fields[0].to_string(),
+        escape_field(&title),
+        rest[0].to_string(),
+        rest[1].to_string(),
+        encode_tags(&split_v3_tags(rest[2])),
+    ];
+    out.extend(rest[3..].iter().map(|f| f.to_string()));
+    out.join("|")
+}
+
+fn split_v3_tags(field: &str) -> Vec<String> {
+    field
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::effort::Effort;
+    use crate::{Priority, Status, Task};
+
+    // Header-less files from before the event log, and with one.
+    const V1_TASKS: &str = include_str!("../tests/fixtures/v1-tasks.txt");
+    const V1_LOG: &str = include_str!("../tests/fixtures/v1-log.txt");
+    const V2: &str = include_str!("../tests/fixtures/v2.txt");
+    // Includes a title with `|`, which version 3 wrote unescaped.
+    const V3: &str = include_str!("../tests/fixtures/v3.txt");
+    const FIXTURES: [(&str, u32); 4] = [(V1_TASKS, 1), (V1_LOG, 1), (V2, 2), (V3, 3)];
+
+    fn load(text: &str) -> TodoApp {
+        TodoApp::from_text(text, "fixture").unwrap()
+    }
+
+    fn task<'a>(app: &'a TodoApp, title: &str) -> &'a Task {
+        app.tasks.values().find(|t| t.title == title).unwrap()
+    }
+
+    fn date(text: &str) -> Option<Date> {
+        Some(text.parse().unwrap())
+    }
+
+    // The fields of every task line, including those inside "added" events.
+    fn task_lines(text: &str) -> Vec<Vec<String>> {
+        text.lines()
+            .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+            .filter_map(|l| match l.strip_prefix('@') {
+                Some(event) => match event.splitn(4, '|').collect::<Vec<_>>()[..] {
+                    [_, _, "added", task] => Some(unescape_field(task)),
+                    _ => None,
+                },
+                None => Some(l.to_string()),
+            })
+            .map(|l| l.split('|').map(String::from).collect())
+            .collect()
+    }
+
+    fn error_message(text: &str) -> String {
+        match TodoApp::from_text(text, "fixture") {
+            Err(TodoError::InvalidInput(message)) => message,
+            Err(other) => panic!("unexpected error {:?}", other),
+            Ok(_) => panic!("accepted {:?}", text),
+        }
+    }
+
+    #[test]
+    fn detects_the_version_of_each_fixture() {
+        for (text, version) in FIXTURES {
+            assert_eq!(version_of(text, "fixture").unwrap(), version);
+        }
+        assert_eq!(version_of("", "fixture").unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn refuses_newer_and_unknown_versions() {
+        for text in ["#schema|5\n", "#schema|0\n", "#schema|two\n"] {
+            assert!(version_of(text, "fixture").is_err(), "accepted {:?}", text);
+        }
+    }
+
+    #[test]
+    fn v1_to_v2_fills_every_task_line() {
+        for text in [V1_TASKS, V1_LOG] {
+            let upgraded = v1_to_v2(text, "fixture").unwrap();
+            assert_eq!(version_of(&upgraded, "fixture").unwrap(), 2);
+            let lines = task_lines(&upgraded);
+            assert!(!lines.is_empty());
+            for fields in lines {
+                assert_eq!(fields.len(), 10, "{:?}", fields);
+                assert!(fields[6].parse::<TaskUid>().is_ok(), "{:?}", fields);
+            }
+        }
+        // Tasks from before the event log become "added" events, by id.
+        let upgraded = v1_to_v2(V1_TASKS, "fixture").unwrap();
+        let ids: Vec<String> = task_lines(&upgraded)
+            .into_iter()
+            .map(|f| f[0].clone())
+            .collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        assert!(upgraded.lines().all(|l| l.starts_with(['#', '@'])));
+        assert!(v1_to_v2("1|Too|few\n", "fixture").is_err());
+    }
+
+    #[test]
+    fn v2_to_v3_adds_effort_and_completion_fields() {
+        let upgraded = v2_to_v3(V2, "fixture").unwrap();
+        assert_eq!(version_of(&upgraded, "fixture").unwrap(), 3);
+        for fields in task_lines(&upgraded) {
+            assert_eq!(fields.len(), 13, "{:?}", fields);
+        }
+        // Completed in the snapshot by event 3, on 2023-11-15.
+        let buy_milk = upgraded.lines().find(|l| l.starts_with("1|")).unwrap();
+        assert!(buy_milk.ends_with("|||2023-11-15"), "{}", buy_milk);
+    }
+
+    #[test]
+    fn v3_to_v4_escapes_titles_and_tags() {
+        let upgraded = v3_to_v4(V3, "fixture").unwrap();
+        assert_eq!(version_of(&upgraded, "fixture").unwrap(), 4);
+        for fields in task_lines(&upgraded) {
+            assert_eq!(fields.len(), 13, "{:?}", fields);
+        }
+        assert!(
+            upgraded.contains("\n1|Fix \\p in titles|High|Pending|bug,a\\\\b|"),
+            "{}",
+            upgraded
+        );
+        assert!(upgraded.contains("|tags|33333333333333333333333333333333|bug,needs\\pinfo\n"));
+    }
+
+    #[test]
+    fn loads_v1_tasks() {
+        let app = load(V1_TASKS);
+        assert_eq!(app.tasks.len(), 3);
+        let milk = task(&app, "Buy milk");
+        assert_eq!(milk.priority, Priority::High);
+        assert_eq!(milk.tags, ["home", "errands"]);
+        assert_eq!(milk.due, date("2024-03-01"));
+        assert_eq!(task(&app, "Water the plants").status, Status::Completed);
+        let notes = task(&app, "Read the notes");
+        assert_eq!(notes.status, Status::InProgress);
+        assert_eq!(notes.notes, "First line\nsecond line");
+    }
+
+    #[test]
+    fn loads_a_v1_event_log() {
+        let app = load(V1_LOG);
+        assert_eq!(app.tasks.len(), 2);
+        assert_eq!(task(&app, "Buy milk").status, Status::Pending);
+        let call = task(&app, "Call mum");
+        assert_eq!(call.status, Status::Completed);
+        assert_eq!(call.completed, date("2023-11-16"));
+        assert_eq!(
+            call.uid,
+            "22222222222222222222222222222222".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn loads_v2() {
+        let app = load(V2);
+        let milk = task(&app, "Buy milk");
+        assert_eq!(milk.status, Status::Completed);
+        assert_eq!(milk.completed, date("2023-11-15"));
+        let call = task(&app, "Call mum");
+        assert_eq!(call.tags, ["family", "weekend"]);
+        assert_eq!(call.notes, "Ask about\nthe trip");
+        assert_eq!(call.completed, None);
+    }
+
+    #[test]
+    fn loads_v3() {
+        let app = load(V3);
+        let fix = task(&app, "Fix | in titles");
+        assert_eq!(fix.status, Status::InProgress);
+        assert_eq!(fix.tags, ["bug", "needs|info"]);
+        assert_eq!(fix.estimate, Some(Effort::from_minutes(30)));
+        assert_eq!(task(&app, "Ship it").completed, date("2023-11-01"));
+    }
+
+    #[test]
+    fn every_fixture_round_trips_in_the_current_format() {
+        for (text, _) in FIXTURES {
+            let saved = load(text).to_text();
+            assert_eq!(version_of(&saved, "saved").unwrap(), SCHEMA_VERSION);
+            assert_eq!(load(&saved).to_text(), saved);
+        }
+    }
+
+    #[test]
+    fn migrate_file_keeps_a_backup() {
+        for (n, (text, version)) in FIXTURES.into_iter().enumerate() {
+            let path =
+                env::temp_dir().join(format!("day21-migrate-{}-{}.txt", std::process::id(), n));
+            let path = path.to_str().unwrap().to_string();
+            fs::write(&path, text).unwrap();
+
+            let backup = format!("{}.v{}.bak", path, version);
+            assert_eq!(
+                migrate_file(&path, None).unwrap(),
+                Some((version, backup.clone()))
+            );
+            assert_eq!(fs::read_to_string(&backup).unwrap(), text);
+            let migrated = fs::read_to_string(&path).unwrap();
+            assert_eq!(version_of(&migrated, &path).unwrap(), SCHEMA_VERSION);
+            let mut before: Vec<String> = load(text).tasks.into_values().map(|t| t.title).collect();
+            let mut after: Vec<String> = load(&migrated)
+                .tasks
+                .into_values()
+                .map(|t| t.title)
+                .collect();
+            before.sort();
+            after.sort();
+            assert_eq!(before, after);
+
+            // A current file is left alone.
+            assert_eq!(migrate_file(&path, None).unwrap(), None);
+            assert_eq!(fs::read_to_string(&path).unwrap(), migrated);
+            fs::remove_file(&path).unwrap();
+            fs::remove_file(&backup).unwrap();
+        }
+    }
+
+    #[test]
+    fn field_count_errors_name_the_expected_count() {
+        let current = error_message("#schema|4\n#snapshot|0\n1|Too|few\n");
+        assert!(
+            current.contains("fixture:3") && current.contains("13"),
+            "{}",
+            current
+        );
+        let v1 = error_message("1|Too|few\n");
+        assert!(v1.contains("fixture:1") && v1.contains("10"), "{}", v1);
+    }
+}
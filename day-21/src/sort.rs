@@ -0,0 +1,350 @@
+// Sorting and grouping for task listings.
+// A sort spec such as "priority desc, due asc, id" is a list of keys compared in
+// order; ties are always broken by id so output is identical from run to run.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Priority, Status, Task, TodoError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortField {
+    Id,
+    Priority,
+    Status,
+    Title,
+    Due,
+    Tag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SortKey {
+    pub(crate) field: SortField,
+    pub(crate) descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SortSpec {
+    keys: Vec<SortKey>,
+}
+
+impl Default for SortSpec {
+    fn default() -> Self {
+        SortSpec {
+            keys: vec![SortKey {
+                field: SortField::Id,
+                descending: false,
+            }],
+        }
+    }
+}
+
+impl SortSpec {
+    pub(crate) fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        self.keys
+            .iter()
+            .map(|key| {
+                let ord = compare_field(key.field, a, b);
+                if key.descending { ord.reverse() } else { ord }
+            })
+            .find(|ord| ord.is_ne())
+            .unwrap_or_else(|| a.id.cmp(&b.id))
+    }
+
+    pub(crate) fn sort(&self, tasks: &mut [&Task]) {
+        tasks.sort_by(|a, b| self.compare(a, b));
+    }
+}
+
+fn compare_field(field: SortField, a: &Task, b: &Task) -> Ordering {
+    match field {
+        SortField::Id => a.id.cmp(&b.id),
+        SortField::Priority => a.priority.cmp(&b.priority),
+        SortField::Status => a.status.cmp(&b.status),
+        SortField::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        // Tasks without a due date sort after every dated task.
+        SortField::Due => match (a.due, b.due) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+        SortField::Tag => first_tag(a).cmp(&first_tag(b)),
+    }
+}
+
+fn first_tag(task: &Task) -> String {
+    task.tags
+        .iter()
+        .map(|t| t.to_lowercase())
+        .min()
+        .unwrap_or_default()
+}
+
+impl FromStr for SortField {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "id" => Ok(SortField::Id),
+            "priority" => Ok(SortField::Priority),
+            "status" => Ok(SortField::Status),
+            "title" => Ok(SortField::Title),
+            "due" => Ok(SortField::Due),
+            "tag" => Ok(SortField::Tag),
+            _ => Err(TodoError::InvalidInput(t!(
+                "error.unknown_sort_field",
+                value = s
+            ))),
+        }
+    }
+}
+
+impl FromStr for SortSpec {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let words: Vec<&str> = part.split_whitespace().collect();
+            let descending = match words.get(1).map(|w| w.to_lowercase()).as_deref() {
+                None | Some("asc") => false,
+                Some("desc") => true,
+                Some(other) => {
+                    return Err(TodoError::InvalidInput(t!(
+                        "error.bad_sort_direction",
+                        value = other
+                    )));
+                }
+            };
+            if words.len() > 2 {
+                return Err(TodoError::InvalidInput(t!(
+                    "error.bad_sort_key",
+                    value = part
+                )));
+            }
+            keys.push(SortKey {
+                field: words[0].parse()?,
+                descending,
+            });
+        }
+        if keys.is_empty() {
+            return Err(TodoError::InvalidInput(t!("error.empty_sort_spec")));
+        }
+        Ok(SortSpec { keys })
+    }
+}
+
+impl fmt::Display for SortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|k| {
+                let name = format!("{:?}", k.field).to_lowercase();
+                if k.descending {
+                    format!("{} desc", name)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        write!(f, "{}", keys.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GroupBy {
+    Priority,
+    Status,
+    Tag,
+}
+
+impl FromStr for GroupBy {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "priority" => Ok(GroupBy::Priority),
+            "status" => Ok(GroupBy::Status),
+            "tag" => Ok(GroupBy::Tag),
+            _ => Err(TodoError::InvalidInput(t!(
+                "error.unknown_grouping",
+                value = s
+            ))),
+        }
+    }
+}
+
+// Splits already-sorted tasks into labelled groups, keeping their order.
+// Groups come in a fixed order (High first, Pending first, tags alphabetical)
+// and empty groups are left out. A task with several tags appears in each.
+// Tags differing only in case share a group, labelled as first spelled.
+pub(crate) fn group_tasks<'a>(tasks: &[&'a Task], group: GroupBy) -> Vec<(String, Vec<&'a Task>)> {
+    let groups: Vec<(String, Vec<&'a Task>)> = match group {
+        GroupBy::Priority => [Priority::High, Priority::Medium, Priority::Low]
+            .iter()
+            .map(|&p| {
+                let members = tasks.iter().copied().filter(|t| t.priority == p).collect();
+                (p.to_string(), members)
+            })
+            .collect(),
+        GroupBy::Status => [Status::Pending, Status::InProgress, Status::Completed]
+            .iter()
+            .map(|&s| {
+                let members = tasks.iter().copied().filter(|t| t.status == s).collect();
+                (s.to_string(), members)
+            })
+            .collect(),
+        GroupBy::Tag => {
+            let mut tags: BTreeMap<String, &str> = BTreeMap::new();
+            for tag in tasks.iter().flat_map(|t| &t.tags) {
+                tags.entry(tag.to_lowercase()).or_insert(tag);
+            }
+            let mut groups: Vec<(String, Vec<&'a Task>)> = tags
+                .into_iter()
+                .map(|(key, label)| {
+                    let members = tasks
+                        .iter()
+                        .copied()
+                        .filter(|t| t.tags.iter().any(|x| x.to_lowercase() == key))
+                        .collect();
+                    (label.to_string(), members)
+                })
+                .collect();
+            let untagged = tasks
+                .iter()
+                .copied()
+                .filter(|t| t.tags.is_empty())
+                .collect();
+            groups.push((t!("group.untagged"), untagged));
+            groups
+        }
+    };
+    groups
+        .into_iter()
+        .filter(|(_, members)| !members.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::Date;
+
+    fn task(id: u32, title: &str, priority: Priority, tags: &[&str]) -> Task {
+        let tags = tags.iter().map(|t| t.to_string()).collect();
+        Task::new(id, title, priority, tags)
+    }
+
+    fn sample() -> Vec<Task> {
+        let mut tasks = vec![
+            task(1, "write report", Priority::Low, &["work"]),
+            task(2, "Buy milk", Priority::High, &["home", "errands"]),
+            task(3, "call plumber", Priority::High, &[]),
+            task(4, "Archive mail", Priority::Medium, &["Work"]),
+        ];
+        tasks[0].due = Some("2026-10-20".parse().unwrap());
+        tasks[2].due = Some("2026-10-19".parse().unwrap());
+        tasks[3].due = Some("2026-10-20".parse().unwrap());
+        tasks[1].status = Status::Completed;
+        tasks[3].status = Status::InProgress;
+        tasks
+    }
+
+    fn sorted(tasks: &[Task], spec: &str) -> Vec<u32> {
+        let mut refs: Vec<&Task> = tasks.iter().collect();
+        spec.parse::<SortSpec>().unwrap().sort(&mut refs);
+        refs.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn parses_specs() {
+        let spec: SortSpec = " Priority DESC ,due asc,, id ".parse().unwrap();
+        assert_eq!(spec.to_string(), "priority desc, due, id");
+        assert_eq!(spec.to_string().parse::<SortSpec>().unwrap(), spec);
+        assert_eq!(SortSpec::default().to_string(), "id");
+        for bad in ["", " , ", "size", "priority up", "priority desc now"] {
+            assert!(
+                matches!(bad.parse::<SortSpec>(), Err(TodoError::InvalidInput(_))),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn sorts_by_each_key() {
+        let tasks = sample();
+        assert_eq!(sorted(&tasks, "id desc"), vec![4, 3, 2, 1]);
+        assert_eq!(sorted(&tasks, "status"), vec![1, 3, 4, 2]);
+        // Case is ignored for titles and tags; untagged tasks come first.
+        assert_eq!(sorted(&tasks, "title"), vec![4, 2, 3, 1]);
+        assert_eq!(sorted(&tasks, "tag"), vec![3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn ties_fall_through_to_later_keys_then_id() {
+        let tasks = sample();
+        assert_eq!(sorted(&tasks, "priority desc"), vec![2, 3, 4, 1]);
+        assert_eq!(
+            sorted(&tasks, "priority desc, title desc"),
+            vec![3, 2, 4, 1]
+        );
+        assert_eq!(sorted(&tasks, "due"), vec![3, 1, 4, 2]);
+        assert_eq!(sorted(&tasks, "due, priority desc"), vec![3, 4, 1, 2]);
+        assert_eq!(sorted(&tasks, "tag desc"), vec![1, 4, 2, 3]);
+    }
+
+    #[test]
+    fn undated_tasks_sort_last_unless_reversed() {
+        let mut tasks = sample();
+        tasks[0].due = None;
+        assert_eq!(sorted(&tasks, "due"), vec![3, 4, 1, 2]);
+        assert_eq!(sorted(&tasks, "due desc"), vec![1, 2, 4, 3]);
+        tasks[2].due = Some(Date::new(2026, 10, 21).unwrap());
+        assert_eq!(sorted(&tasks, "due"), vec![4, 3, 1, 2]);
+    }
+
+    #[test]
+    fn groups_keep_order_and_skip_empty_groups() {
+        let tasks = sample();
+        let refs: Vec<&Task> = tasks.iter().collect();
+        let ids = |groups: Vec<(String, Vec<&Task>)>| -> Vec<(String, Vec<u32>)> {
+            groups
+                .into_iter()
+                .map(|(label, members)| (label, members.iter().map(|t| t.id).collect()))
+                .collect()
+        };
+
+        assert_eq!(
+            ids(group_tasks(&refs, GroupBy::Priority)),
+            vec![
+                (Priority::High.to_string(), vec![2, 3]),
+                (Priority::Medium.to_string(), vec![4]),
+                (Priority::Low.to_string(), vec![1]),
+            ]
+        );
+        let open: Vec<&Task> = refs.iter().copied().filter(|t| t.id != 2).collect();
+        assert_eq!(
+            ids(group_tasks(&open, GroupBy::Status)),
+            vec![
+                (Status::Pending.to_string(), vec![1, 3]),
+                (Status::InProgress.to_string(), vec![4]),
+            ]
+        );
+        // Tags are grouped ignoring case; a task with two tags is in both.
+        assert_eq!(
+            ids(group_tasks(&refs, GroupBy::Tag)),
+            vec![
+                ("errands".to_string(), vec![2]),
+                ("home".to_string(), vec![2]),
+                ("work".to_string(), vec![1, 4]),
+                (t!("group.untagged"), vec![3]),
+            ]
+        );
+        assert!(group_tasks(&[], GroupBy::Tag).is_empty());
+    }
+}
@@ -0,0 +1,392 @@
+// Statistics over the task list: totals, breakdowns by status, priority and
+// tag with a completion rate for each group, and the age of open tasks.
+// `stats` prints them as a table; `stats --json` prints the same numbers for
+// scripts, with English keys whatever the display language.
+
+use std::collections::BTreeMap;
+
+use crate::i18n::{self, Catalog};
+use crate::{Priority, Status, Task, TodoApp};
+
+// Number of open tasks listed as the oldest.
+const OLDEST: usize = 5;
+const DAY: f64 = 86_400.0;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Group {
+    pub(crate) total: usize,
+    pub(crate) completed: usize,
+}
+
+impl Group {
+    fn new((total, completed): (usize, usize)) -> Self {
+        Group { total, completed }
+    }
+
+    // Percentage of completed tasks, or None for an empty group.
+    pub(crate) fn rate(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.completed as f64 / self.total as f64 * 100.0)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Stats<'a> {
+    pub(crate) all: Group,
+    pub(crate) by_status: Vec<(Status, usize)>,
+    pub(crate) by_priority: Vec<(Priority, Group)>,
+    // Keyed by the lowercased tag, as tag filters ignore case.
+    pub(crate) by_tag: BTreeMap<String, Group>,
+    pub(crate) untagged: Group,
+    // Average age in days of the open tasks whose creation was recorded.
+    pub(crate) average_open_age: Option<f64>,
+    // The oldest open tasks with their age in days, oldest first.
+    pub(crate) oldest_open: Vec<(&'a Task, f64)>,
+}
+
+impl TodoApp {
+    // Computes the statistics as seen at `now` (seconds since the epoch).
+    // The counts come from the index; only the open tasks are looked at, for
+    // their ages.
+    pub(crate) fn stats(&self, now: u64) -> Stats<'_> {
+        let all = Group::new((self.tasks.len(), self.index.count(Status::Completed)));
+        let by_tag: BTreeMap<String, Group> = self
+            .index
+            .tag_counts()
+            .map(|(tag, total, completed)| (tag.to_string(), Group::new((total, completed))))
+            .collect();
+
+        // Tasks from files older than the event log have no recorded creation
+        // time and are left out of the ages.
+        let mut open: Vec<(&Task, f64)> = [Status::Pending, Status::InProgress]
+            .into_iter()
+            .flat_map(|status| self.index.with_status(status))
+            .filter_map(|id| {
+                let task = &self.tasks[&id];
+                let at = self.index.created(task.uid)?;
+                Some((task, now.saturating_sub(at) as f64 / DAY))
+            })
+            .collect();
+        let average_open_age = (!open.is_empty())
+            .then(|| open.iter().map(|(_, age)| age).sum::<f64>() / open.len() as f64);
+        open.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.id.cmp(&b.0.id)));
+        open.truncate(OLDEST);
+
+        Stats {
+            all,
+            by_status: Status::ALL
+                .into_iter()
+                .map(|status| (status, self.index.count(status)))
+                .collect(),
+            by_priority: Priority::ALL
+                .into_iter()
+                .rev()
+                .map(|p| (p, Group::new(self.index.priority_counts(p))))
+                .collect(),
+            by_tag,
+            untagged: Group::new(self.index.untagged_counts()),
+            average_open_age,
+            oldest_open: open,
+        }
+    }
+
+    pub(crate) fn print_stats(&self, now: u64) {
+        print!("{}", self.stats_report(now, i18n::catalog()));
+    }
+
+    // The statistics as `stats` shows them, in the language of `catalog`.
+    pub(crate) fn stats_report(&self, now: u64, catalog: &'static Catalog) -> String {
+        i18n::with_catalog(catalog, || {
+            let stats = self.stats(now);
+            let total = stats.all.total;
+            let completed = stats.all.completed;
+            let mut out = format!(
+                "{}\n",
+                t!(
+                    "stats.summary",
+                    total = total,
+                    completed = completed,
+                    pending = total - completed
+                )
+            );
+            let Some(rate) = stats.all.rate() else {
+                return out;
+            };
+            out.push_str(&format!(
+                "{}\n",
+                t!("stats.rate", rate = format!("{:.1}", rate))
+            ));
+
+            out.push_str(&format!("\n{}\n", t!("stats.by_status")));
+            for (status, count) in &stats.by_status {
+                out.push_str(&format!("  {:<18} {:>9}\n", status.to_string(), count));
+            }
+
+            out.push_str(&header(&t!("stats.by_priority")));
+            for (priority, group) in &stats.by_priority {
+                out.push_str(&group_row(&priority.to_string(), group));
+            }
+
+            out.push_str(&header(&t!("stats.by_tag")));
+            for (tag, group) in &stats.by_tag {
+                out.push_str(&group_row(tag, group));
+            }
+            if stats.untagged.total > 0 {
+                out.push_str(&group_row(&t!("stats.untagged"), &stats.untagged));
+            }
+
+            if let Some(age) = stats.average_open_age {
+                out.push_str(&format!(
+                    "\n{}\n",
+                    t!("stats.average_age", days = format!("{:.1}", age))
+                ));
+                out.push_str(&format!("{}\n", t!("stats.oldest")));
+                for (task, age) in &stats.oldest_open {
+                    out.push_str(&format!(
+                        "  {:>10}  {}\n",
+                        t!("stats.age", days = format!("{:.1}", age)),
+                        task.render(None)
+                    ));
+                }
+            }
+            out
+        })
+    }
+
+    pub(crate) fn print_stats_json(&self, now: u64) {
+        let stats = self.stats(now);
+        let mut out = String::from("{\n");
+        out.push_str(&format!("  \"total\": {},\n", stats.all.total));
+        out.push_str(&format!("  \"completed\": {},\n", stats.all.completed));
+        out.push_str(&format!(
+            "  \"completion_rate\": {},\n",
+            number(stats.all.rate())
+        ));
+
+        let statuses: Vec<String> = stats
+            .by_status
+            .iter()
+            .map(|(status, count)| format!("\"{}\": {}", key(status.name()), count))
+            .collect();
+        out.push_str(&format!("  \"by_status\": {{{}}},\n", statuses.join(", ")));
+
+        let priorities: Vec<(String, Group)> = stats
+            .by_priority
+            .iter()
+            .map(|(p, group)| (key(p.name()), *group))
+            .collect();
+        out.push_str(&format!("  \"by_priority\": {},\n", groups(&priorities)));
+        let tags: Vec<(String, Group)> = stats
+            .by_tag
+            .iter()
+            .map(|(tag, group)| (tag.clone(), *group))
+            .collect();
+        out.push_str(&format!("  \"by_tag\": {},\n", groups(&tags)));
+        out.push_str(&format!("  \"untagged\": {},\n", group(&stats.untagged)));
+        out.push_str(&format!(
+            "  \"average_open_age_days\": {},\n",
+            number(stats.average_open_age)
+        ));
+
+        let oldest: Vec<String> = stats
+            .oldest_open
+            .iter()
+            .map(|(task, age)| {
+                format!(
+                    "    {{\"id\": {}, \"uid\": \"{}\", \"title\": {}, \"age_days\": {:.2}}}",
+                    task.id,
+                    task.uid,
+                    string(&task.title),
+                    age
+                )
+            })
+            .collect();
+        if oldest.is_empty() {
+            out.push_str("  \"oldest_open\": []\n");
+        } else {
+            out.push_str(&format!(
+                "  \"oldest_open\": [\n{}\n  ]\n",
+                oldest.join(",\n")
+            ));
+        }
+        out.push('}');
+        println!("{}", out);
+    }
+}
+
+fn header(title: &str) -> String {
+    format!(
+        "\n{:<20} {:>9} {:>9} {:>8}\n",
+        title,
+        t!("stats.tasks"),
+        t!("stats.done"),
+        t!("stats.rate_column")
+    )
+}
+
+fn group_row(label: &str, group: &Group) -> String {
+    let rate = group
+        .rate()
+        .map_or_else(|| "-".to_string(), |r| format!("{:.1}%", r));
+    format!(
+        "  {:<18} {:>9} {:>9} {:>8}\n",
+        label, group.total, group.completed, rate
+    )
+}
+
+// "In Progress" -> "in_progress"
+pub(crate) fn key(name: &str) -> String {
+    name.to_lowercase().replace(' ', "_")
+}
+
+fn group(group: &Group) -> String {
+    format!(
+        "{{\"total\": {}, \"completed\": {}, \"completion_rate\": {}}}",
+        group.total,
+        group.completed,
+        number(group.rate())
+    )
+}
+
+fn groups(groups: &[(String, Group)]) -> String {
+    if groups.is_empty() {
+        return "{}".to_string();
+    }
+    let entries: Vec<String> = groups
+        .iter()
+        .map(|(name, g)| format!("    {}: {}", string(name), group(g)))
+        .collect();
+    format!("{{\n{}\n  }}", entries.join(",\n"))
+}
+
+fn number(value: Option<f64>) -> String {
+    value.map_or_else(|| "null".to_string(), |v| format!("{:.2}", v))
+}
+
+// A JSON string literal.
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    fn group(tasks: &[&Task]) -> (usize, usize) {
+        let completed = tasks
+            .iter()
+            .filter(|t| t.status == Status::Completed)
+            .count();
+        (tasks.len(), completed)
+    }
+
+    fn counts(group: &Group) -> (usize, usize) {
+        (group.total, group.completed)
+    }
+
+    // The figures from the index agree with counting every task.
+    fn assert_matches_scan(app: &TodoApp) {
+        let stats = app.stats(0);
+        let tasks: Vec<&Task> = app.tasks.values().collect();
+        assert_eq!(counts(&stats.all), group(&tasks));
+        for (status, count) in &stats.by_status {
+            assert_eq!(*count, tasks.iter().filter(|t| t.status == *status).count());
+        }
+        for (priority, g) in &stats.by_priority {
+            let with: Vec<&Task> = tasks
+                .iter()
+                .copied()
+                .filter(|t| t.priority == *priority)
+                .collect();
+            assert_eq!(counts(g), group(&with), "{}", priority);
+        }
+        let mut tags: Vec<String> = tasks
+            .iter()
+            .flat_map(|t| t.tags.iter().map(|tag| tag.to_lowercase()))
+            .collect();
+        tags.sort();
+        tags.dedup();
+        assert_eq!(stats.by_tag.keys().cloned().collect::<Vec<_>>(), tags);
+        for (tag, g) in &stats.by_tag {
+            let with: Vec<&Task> = tasks
+                .iter()
+                .copied()
+                .filter(|t| t.tags.iter().any(|t| t.to_lowercase() == *tag))
+                .collect();
+            assert_eq!(counts(g), group(&with), "{}", tag);
+        }
+        let untagged: Vec<&Task> = tasks
+            .iter()
+            .copied()
+            .filter(|t| t.tags.is_empty())
+            .collect();
+        assert_eq!(counts(&stats.untagged), group(&untagged));
+    }
+
+    #[test]
+    fn counts_follow_every_change() {
+        let mut app = TodoApp::new();
+        assert_matches_scan(&app);
+        let report = app.add_task("Write report", Priority::High, "Work");
+        let milk = app.add_task("Buy milk", Priority::Low, "");
+        let call = app.add_task("Call bank", Priority::Medium, "home");
+        assert_matches_scan(&app);
+
+        app.complete_task(report).unwrap();
+        app.complete_task(milk).unwrap();
+        assert_matches_scan(&app);
+
+        let uid = |app: &TodoApp, id| app.uid_of(id).unwrap();
+        app.record_all(vec![
+            EventKind::TagsChanged(
+                uid(&app, report),
+                vec!["work".to_string(), "WORK".to_string(), "q3".to_string()],
+            ),
+            EventKind::PriorityChanged(uid(&app, report), Priority::Low),
+            EventKind::TagsChanged(uid(&app, milk), vec!["home".to_string()]),
+            EventKind::StatusChanged(uid(&app, call), Status::InProgress),
+        ])
+        .unwrap();
+        assert_matches_scan(&app);
+        assert_eq!(counts(&app.stats(0).by_tag["work"]), (1, 1));
+
+        app.record_all(vec![
+            EventKind::StatusChanged(uid(&app, report), Status::Pending),
+            EventKind::TagsChanged(uid(&app, call), Vec::new()),
+            EventKind::TaskDeleted(uid(&app, milk)),
+        ])
+        .unwrap();
+        assert_matches_scan(&app);
+        let stats = app.stats(0);
+        assert_eq!(counts(&stats.all), (2, 0));
+        assert_eq!(counts(&stats.untagged), (1, 0));
+        assert!(!stats.by_tag.contains_key("home"));
+    }
+
+    #[test]
+    fn ages_cover_only_open_tasks() {
+        let mut app = TodoApp::new();
+        let done = app.add_task("Done", Priority::Low, "");
+        app.add_task("Open", Priority::Low, "");
+        app.complete_task(done).unwrap();
+        let added = app.log.iter().map(|e| e.at).max().unwrap();
+        let stats = app.stats(added + 2 * DAY as u64);
+        assert_eq!(stats.oldest_open.len(), 1);
+        assert_eq!(stats.oldest_open[0].0.title, "Open");
+        assert_eq!(stats.average_open_age, Some(2.0));
+    }
+}
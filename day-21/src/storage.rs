@@ -0,0 +1,421 @@
+// Saving and loading the todo file, as plain text or encrypted with a passphrase.
+//
+// The file holds a schema header, a snapshot marker, the snapshot's tasks and
+// then the event log:
+//   #schema|<format version>                                    (see migrate.rs)
+//   #snapshot|<seq of the last event included in the snapshot>
+//   id|title|priority|status|tags|due|uid|notes|attachments|source|estimate|spent|completed
+//                                                               (one per task)
+//   @seq|time|kind|payload...                                   (one per event)
+// Titles, tags, notes, attachments, sources and event payloads are escaped so
+// they cannot break the line structure.
+
+use std::fs::{self, File};
+use std::io::Write;
+
+use crate::attachment::Attachment;
+use crate::date::Date;
+use crate::effort::Effort;
+use crate::events::{Event, EventKind};
+use crate::migrate::{self, HEADER, SCHEMA_VERSION};
+use crate::{Task, TodoApp, TodoError, crypto};
+
+// Number of fields in a task line.
+const TASK_FIELDS: usize = 13;
+
+// Number of events after which the next save writes a fresh snapshot.
+const SNAPSHOT_INTERVAL: u64 = 100;
+
+impl TodoApp {
+    // Serializes the latest snapshot followed by the whole event log. The
+    // snapshot is refreshed once SNAPSHOT_INTERVAL events have accumulated
+    // since the previous one; otherwise the loaded snapshot is written back.
+    pub(crate) fn to_text(&self) -> String {
+        let (snapshot_seq, snapshot) = match &self.snapshot_text {
+            Some(text) if self.last_seq() - self.snapshot_seq < SNAPSHOT_INTERVAL => {
+                (self.snapshot_seq, text.clone())
+            }
+            _ => (self.last_seq(), self.tasks_text()),
+        };
+
+        let mut out = format!("{}{}\n#snapshot|{}\n", HEADER, SCHEMA_VERSION, snapshot_seq);
+        out.push_str(&snapshot);
+        for event in &self.log {
+            out.push_str(&event_line(event));
+            out.push('\n');
+        }
+        out
+    }
+
+    // Every task, one per line, ordered by id.
+    fn tasks_text(&self) -> String {
+        let mut tasks: Vec<&Task> = self.tasks.values().collect();
+        tasks.sort_by_key(|t| t.id);
+        tasks
+            .into_iter()
+            .map(|task| task_line(task) + "\n")
+            .collect()
+    }
+
+    // Parses the output of `to_text`, upgrading files written in an older
+    // format first; `source` names the file in error messages.
+    pub(crate) fn from_text(text: &str, source: &str) -> Result<Self, TodoError> {
+        let text = migrate::upgrade(text, source)?;
+        let mut app = TodoApp::new();
+        let mut tasks = Vec::new();
+        let mut snapshot = String::new();
+        for (n, line) in text.lines().enumerate() {
+            let location = format!("{}:{}", source, n + 1);
+            if line.trim().is_empty() || line.starts_with(HEADER) {
+                continue;
+            } else if let Some(seq) = line.strip_prefix("#snapshot|") {
+                app.snapshot_seq = seq.parse().map_err(|_| {
+                    TodoError::InvalidInput(t!("error.bad_snapshot", location = location))
+                })?;
+            } else if let Some(event) = line.strip_prefix('@') {
+                let event = parse_event_line(event, &location)?;
+                // Tasks in the snapshot were added by events not replayed below.
+                if let EventKind::TaskAdded(task) = &event.kind {
+                    app.index.record_created(task.uid, event.at);
+                }
+                app.log.push(event);
+            } else {
+                tasks.push(parse_task_line(line, &location)?);
+                snapshot.push_str(line);
+                snapshot.push('\n');
+            }
+        }
+
+        for task in tasks {
+            app.insert_task(task);
+        }
+
+        let pending: Vec<(EventKind, u64)> = app
+            .log
+            .iter()
+            .filter(|e| e.seq > app.snapshot_seq)
+            .map(|e| (e.kind.clone(), e.at))
+            .collect();
+        for (kind, at) in &pending {
+            app.apply_event(kind, *at)?;
+        }
+        app.snapshot_text = Some(snapshot);
+        Ok(app)
+    }
+
+    pub(crate) fn save_to_file(&self, filename: &str) -> Result<(), TodoError> {
+        write_atomically(filename, self.to_text().as_bytes())
+    }
+
+    pub(crate) fn load_from_file(filename: &str) -> Result<Self, TodoError> {
+        let data = fs::read(filename)?;
+        if crypto::is_encrypted(&data) {
+            return Err(TodoError::PassphraseRequired(filename.to_string()));
+        }
+        let text = String::from_utf8(data)
+            .map_err(|_| TodoError::InvalidInput(t!("error.not_utf8", file = filename)))?;
+        TodoApp::from_text(&text, filename)
+    }
+
+    pub(crate) fn save_encrypted(&self, filename: &str, passphrase: &str) -> Result<(), TodoError> {
+        let data = crypto::encrypt(self.to_text().as_bytes(), passphrase)?;
+        write_atomically(filename, &data)
+    }
+
+    pub(crate) fn load_encrypted(filename: &str, passphrase: &str) -> Result<Self, TodoError> {
+        let plaintext = crypto::decrypt(&fs::read(filename)?, passphrase)?;
+        let text = String::from_utf8(plaintext)
+            .map_err(|_| TodoError::InvalidInput(t!("error.not_utf8", file = filename)))?;
+        TodoApp::from_text(&text, filename)
+    }
+}
+
+pub(crate) fn is_encrypted_file(filename: &str) -> Result<bool, TodoError> {
+    Ok(crypto::is_encrypted(&fs::read(filename)?))
+}
+
+// id|title|priority|status|tags|due|uid|notes|attachments|source|estimate|spent|completed,
+// with efforts in minutes.
+fn task_line(task: &Task) -> String {
+    format!(
+        "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+        task.id,
+        escape_field(&task.title),
+        task.priority.name(),
+        task.status.name(),
+        encode_tags(&task.tags),
+        task.due.map(|d| d.to_string()).unwrap_or_default(),
+        task.uid,
+        escape_field(&task.notes),
+        encode_attachments(&task.attachments),
+        task.source.as_deref().map(escape_field).unwrap_or_default(),
+        encode_effort(task.estimate),
+        encode_effort(task.spent),
+        task.completed.map(|d| d.to_string()).unwrap_or_default()
+    )
+}
+
+fn parse_task_line(line: &str, location: &str) -> Result<Task, TodoError> {
+    let fields: Vec<&str> = line.split('|').collect();
+    if fields.len() != TASK_FIELDS {
+        return Err(TodoError::InvalidInput(t!(
+            "error.bad_field_count",
+            location = location,
+            expected = TASK_FIELDS,
+            found = fields.len()
+        )));
+    }
+    let id: u32 = fields[0].parse().map_err(|_| {
+        TodoError::InvalidInput(t!("error.bad_id", location = location, value = fields[0]))
+    })?;
+    let title = unescape_field(fields[1]);
+    let mut task = Task::new(id, &title, fields[2].parse()?, split_tags(fields[4]));
+    task.status = fields[3].parse()?;
+    task.due = parse_due(fields[5])?;
+    task.uid = fields[6].parse()?;
+    task.notes = unescape_field(fields[7]);
+    task.attachments = decode_attachments(fields[8])?;
+    if !fields[9].is_empty() {
+        task.source = Some(unescape_field(fields[9]));
+    }
+    task.estimate = parse_effort(fields[10], location)?;
+    task.spent = parse_effort(fields[11], location)?;
+    task.completed = parse_due(fields[12])?;
+    Ok(task)
+}
+
+// seq|time|kind|payload..., with the payload fields escaped.
+fn event_line(event: &Event) -> String {
+    let payload = match &event.kind {
+        EventKind::TaskAdded(task) => format!("added|{}", escape_field(&task_line(task))),
+        EventKind::TaskCompleted(uid) => format!("completed|{}", uid),
+        EventKind::TitleChanged(uid, title) => format!("title|{}|{}", uid, escape_field(title)),
+        EventKind::StatusChanged(uid, status) => format!("status|{}|{}", uid, status.name()),
+        EventKind::PriorityChanged(uid, priority) => {
+            format!("priority|{}|{}", uid, priority.name())
+        }
+        EventKind::TagsChanged(uid, tags) => format!("tags|{}|{}", uid, encode_tags(tags)),
+        EventKind::DueChanged(uid, due) => format!(
+            "due|{}|{}",
+            uid,
+            due.map(|d| d.to_string()).unwrap_or_default()
+        ),
+        EventKind::EstimateChanged(uid, estimate) => {
+            format!("estimate|{}|{}", uid, encode_effort(*estimate))
+        }
+        EventKind::SpentChanged(uid, spent) => format!("spent|{}|{}", uid, encode_effort(*spent)),
+        EventKind::NotesChanged(uid, notes) => format!("notes|{}|{}", uid, escape_field(notes)),
+        EventKind::AttachmentsChanged(uid, attachments) => format!(
+            "attachments|{}|{}",
+            uid,
+            escape_field(&encode_attachments(attachments))
+        ),
+        EventKind::TaskDeleted(uid) => format!("deleted|{}", uid),
+    };
+    format!("@{}|{}|{}", event.seq, event.at, payload)
+}
+
+fn parse_event_line(line: &str, location: &str) -> Result<Event, TodoError> {
+    let bad = || TodoError::InvalidInput(t!("error.bad_event", location = location, value = line));
+    let fields: Vec<&str> = line.split('|').collect();
+    if fields.len() < 4 {
+        return Err(bad());
+    }
+    let seq = fields[0].parse().map_err(|_| bad())?;
+    let at = fields[1].parse().map_err(|_| bad())?;
+    let arg = |i: usize| fields.get(i).copied().ok_or_else(bad);
+
+    let kind = match fields[2] {
+        "added" => EventKind::TaskAdded(parse_task_line(&unescape_field(arg(3)?), location)?),
+        "completed" => EventKind::TaskCompleted(arg(3)?.parse()?),
+        "title" => EventKind::TitleChanged(arg(3)?.parse()?, unescape_field(arg(4)?)),
+        "status" => EventKind::StatusChanged(arg(3)?.parse()?, arg(4)?.parse()?),
+        "priority" => EventKind::PriorityChanged(arg(3)?.parse()?, arg(4)?.parse()?),
+        "tags" => EventKind::TagsChanged(arg(3)?.parse()?, split_tags(arg(4)?)),
+        "due" => EventKind::DueChanged(arg(3)?.parse()?, parse_due(arg(4)?)?),
+        "estimate" => {
+            EventKind::EstimateChanged(arg(3)?.parse()?, parse_effort(arg(4)?, location)?)
+        }
+        "spent" => EventKind::SpentChanged(arg(3)?.parse()?, parse_effort(arg(4)?, location)?),
+        "notes" => EventKind::NotesChanged(arg(3)?.parse()?, unescape_field(arg(4)?)),
+        "attachments" => EventKind::AttachmentsChanged(
+            arg(3)?.parse()?,
+            decode_attachments(&unescape_field(arg(4)?))?,
+        ),
+        "deleted" => EventKind::TaskDeleted(arg(3)?.parse()?),
+        _ => return Err(bad()),
+    };
+    Ok(Event { seq, at, kind })
+}
+
+// Tags separated by commas, each escaped with commas in it written as \c.
+pub(crate) fn encode_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|t| escape_field(t).replace(',', "\\c"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn split_tags(field: &str) -> Vec<String> {
+    field
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .map(unescape_field)
+        .collect()
+}
+
+fn parse_due(field: &str) -> Result<Option<Date>, TodoError> {
+    if field.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(field.parse()?))
+    }
+}
+
+fn encode_effort(effort: Option<Effort>) -> String {
+    effort.map(|e| e.minutes().to_string()).unwrap_or_default()
+}
+
+fn parse_effort(field: &str, location: &str) -> Result<Option<Effort>, TodoError> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    field
+        .parse()
+        .map(|m| Some(Effort::from_minutes(m)))
+        .map_err(|_| {
+            TodoError::InvalidInput(t!(
+                "error.bad_stored_effort",
+                location = location,
+                value = field
+            ))
+        })
+}
+
+fn encode_attachments(attachments: &[Attachment]) -> String {
+    attachments
+        .iter()
+        .map(|a| escape_field(&a.encode()))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn decode_attachments(field: &str) -> Result<Vec<Attachment>, TodoError> {
+    field
+        .split(';')
+        .filter(|a| !a.is_empty())
+        .map(|a| Attachment::decode(&unescape_field(a)))
+        .collect()
+}
+
+// Writes to a temporary file first and renames it into place, so a
+// failed save never leaves a half-written todo file behind.
+pub(crate) fn write_atomically(filename: &str, data: &[u8]) -> Result<(), TodoError> {
+    let tmp = format!("{}.tmp", filename);
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, filename)?;
+    Ok(())
+}
+
+// Escapes the characters that separate records and fields in the todo file,
+// so multi-line notes and odd file names survive a save and load.
+pub(crate) fn escape_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '|' => out.push_str("\\p"),
+            ';' => out.push_str("\\s"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub(crate) fn unescape_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('p') => out.push('|'),
+            Some('s') => out.push(';'),
+            Some('c') => out.push(','),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+
+    // Every character that separates records, fields, tags or attachments.
+    const AWKWARD: &str = "a | b, c; d \\ e \\p\nsecond line\r";
+
+    fn reload(app: &TodoApp) -> TodoApp {
+        TodoApp::from_text(&app.to_text(), "test").unwrap()
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        assert_eq!(unescape_field(&escape_field(AWKWARD)), AWKWARD);
+        assert!(!escape_field(AWKWARD).contains(['|', '\n', '\r', ';']));
+    }
+
+    #[test]
+    fn task_fields_with_separators_round_trip() {
+        let tags = vec![AWKWARD.to_string(), "x,y".to_string(), "plain".to_string()];
+        let mut task = Task::new(1, AWKWARD, Priority::High, tags.clone());
+        task.notes = AWKWARD.to_string();
+        task.source = Some(AWKWARD.to_string());
+        let mut app = TodoApp::new();
+        app.record(EventKind::TaskAdded(task)).unwrap();
+
+        let text = app.to_text();
+        assert_eq!(text.lines().count(), 4, "one line per record:\n{}", text);
+        let loaded = reload(&app);
+        let task = &loaded.tasks[&1];
+        assert_eq!(task.title, AWKWARD);
+        assert_eq!(task.tags, tags);
+        assert_eq!(task.notes, AWKWARD);
+        assert_eq!(task.source.as_deref(), Some(AWKWARD));
+        assert_eq!(loaded.to_text(), text);
+    }
+
+    #[test]
+    fn title_and_tag_changes_replay_from_the_log() {
+        let mut app = TodoApp::new();
+        app.record(EventKind::TaskAdded(Task::new(
+            1,
+            "first",
+            Priority::Low,
+            vec![],
+        )))
+        .unwrap();
+        // The snapshot now holds the task; later changes are replayed.
+        let mut app = reload(&app);
+        let uid = app.uid_of(1).unwrap();
+        let tags = vec![AWKWARD.to_string(), "b".to_string()];
+        app.record(EventKind::TitleChanged(uid, AWKWARD.to_string()))
+            .unwrap();
+        app.record(EventKind::TagsChanged(uid, tags.clone()))
+            .unwrap();
+
+        let loaded = reload(&app);
+        assert_eq!(loaded.tasks[&1].title, AWKWARD);
+        assert_eq!(loaded.tasks[&1].tags, tags);
+    }
+}
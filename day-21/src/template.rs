@@ -0,0 +1,577 @@
+// Task templates for checklists that are run again and again.
+// A template is a text file "<name>.txt" in the template directory
+// (TODO_TEMPLATE_DIR, default ~/.config/todo/templates):
+//
+//   # Release checklist
+//   description: Steps for shipping a release
+//   priority: medium                 default priority of every task
+//   tags: release, v{version}        tags added to every task
+//   default channel: #releases       value used when none is given
+//   - Freeze the {version} branch !high
+//   - Write release notes for {version} +docs
+//
+// Each "- " line is a task; "!priority" and "+tag" words work as in bulk
+// commands. {name} placeholders are filled in by `apply <name> key=value...`,
+// and "{{" / "}}" stand for literal braces.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::events::EventKind;
+use crate::{Priority, Task, TodoApp, TodoError};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Template {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    priority: Priority,
+    tags: Vec<String>,
+    defaults: BTreeMap<String, String>,
+    tasks: Vec<TemplateTask>,
+}
+
+#[derive(Debug, Clone)]
+struct TemplateTask {
+    title: String,
+    priority: Option<Priority>,
+    tags: Vec<String>,
+}
+
+// TODO_TEMPLATE_DIR, then $XDG_CONFIG_HOME/todo/templates, then
+// ~/.config/todo/templates.
+pub(crate) fn template_dir() -> PathBuf {
+    if let Ok(dir) = env::var("TODO_TEMPLATE_DIR") {
+        return PathBuf::from(dir);
+    }
+    let config = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(".config")
+        });
+    config.join("todo").join("templates")
+}
+
+// `templates` lists the templates with their placeholders;
+// `templates check [name...]` validates them and fails if any is invalid.
+pub(crate) fn run(args: Vec<String>) -> Result<(), TodoError> {
+    let dir = template_dir();
+    let check = match args.first().map(String::as_str) {
+        None => false,
+        Some("check") => true,
+        Some(other) => {
+            return Err(TodoError::InvalidInput(t!(
+                "error.unknown_templates_command",
+                command = other
+            )));
+        }
+    };
+    let names = if args.len() > 1 {
+        args[1..].to_vec()
+    } else {
+        names(&dir)?
+    };
+    if names.is_empty() {
+        println!("{}", t!("template.none", dir = dir.display()));
+        return Ok(());
+    }
+
+    let mut invalid = 0;
+    for name in &names {
+        match &load(&dir, name) {
+            Ok(template) if check => println!(
+                "{}",
+                tn!("template.valid", template.task_count(), name = name)
+            ),
+            Ok(template) => {
+                println!("{:<16} {}", name, template.description);
+                let placeholders: Vec<String> = template
+                    .placeholders()
+                    .into_iter()
+                    .map(|(key, default)| match default {
+                        Some(value) => format!("{}={}", key, value),
+                        None => key,
+                    })
+                    .collect();
+                println!(
+                    "{:<16} {}",
+                    "",
+                    tn!(
+                        "template.summary",
+                        template.task_count(),
+                        placeholders = if placeholders.is_empty() {
+                            "-".to_string()
+                        } else {
+                            placeholders.join(", ")
+                        }
+                    )
+                );
+            }
+            Err(e) => {
+                invalid += 1;
+                // Every template error already names the template.
+                println!("{}", message(e));
+            }
+        }
+    }
+    if check && invalid > 0 {
+        return Err(TodoError::InvalidInput(tn!(
+            "error.invalid_templates",
+            invalid
+        )));
+    }
+    Ok(())
+}
+
+// Parses the `key=value` arguments of `apply`.
+pub(crate) fn parse_values(args: &[String]) -> Result<HashMap<String, String>, TodoError> {
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.to_string()))
+            }
+            _ => Err(TodoError::InvalidInput(t!(
+                "error.bad_template_value",
+                value = arg
+            ))),
+        })
+        .collect()
+}
+
+// The names of the template files in `dir`, sorted.
+fn names(dir: &Path) -> Result<Vec<String>, TodoError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let is_template = path.extension().is_some_and(|ext| ext == "txt");
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            is_template.then_some(name)
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+pub(crate) fn load(dir: &Path, name: &str) -> Result<Template, TodoError> {
+    let path = dir.join(format!("{}.txt", name));
+    if !path.is_file() {
+        return Err(TodoError::InvalidInput(t!(
+            "error.unknown_template",
+            name = name,
+            dir = dir.display()
+        )));
+    }
+    Template::parse(name, &fs::read_to_string(path)?)
+}
+
+impl Template {
+    pub(crate) fn parse(name: &str, text: &str) -> Result<Self, TodoError> {
+        let mut template = Template {
+            name: name.to_string(),
+            description: String::new(),
+            priority: Priority::Medium,
+            tags: Vec::new(),
+            defaults: BTreeMap::new(),
+            tasks: Vec::new(),
+        };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let location = format!("{}:{}", name, n + 1);
+            let bad =
+                |message: String| TodoError::InvalidInput(format!("{}: {}", location, message));
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(task) = line.strip_prefix("- ") {
+                template
+                    .tasks
+                    .push(parse_task(task).map_err(|e| bad(message(&e)))?);
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(bad(t!("error.bad_template_line", line = line)));
+            };
+            let value = value.trim();
+            match key.trim() {
+                "description" => template.description = value.to_string(),
+                "priority" => template.priority = value.parse().map_err(|e| bad(message(&e)))?,
+                "tags" => template.tags = split_list(value),
+                key => match key.strip_prefix("default ") {
+                    Some(placeholder) => {
+                        template
+                            .defaults
+                            .insert(placeholder.trim().to_string(), value.to_string());
+                    }
+                    None => return Err(bad(t!("error.bad_template_key", key = key))),
+                },
+            }
+        }
+        if template.tasks.is_empty() {
+            return Err(TodoError::InvalidInput(t!(
+                "error.empty_template",
+                name = name
+            )));
+        }
+        // Checking every text once catches unbalanced braces before `apply`.
+        for text in template.texts() {
+            placeholders(text)
+                .map_err(|e| TodoError::InvalidInput(format!("{}: {}", name, message(&e))))?;
+        }
+        Ok(template)
+    }
+
+    pub(crate) fn task_count(&self) -> usize {
+        self.tasks.len()
+    }
+
+    // Every placeholder used by the template, with its default if it has one.
+    pub(crate) fn placeholders(&self) -> BTreeMap<String, Option<String>> {
+        self.texts()
+            .filter_map(|text| placeholders(text).ok())
+            .flatten()
+            .map(|name| {
+                let default = self.defaults.get(&name).cloned();
+                (name, default)
+            })
+            .collect()
+    }
+
+    fn texts(&self) -> impl Iterator<Item = &String> {
+        self.tags.iter().chain(
+            self.tasks
+                .iter()
+                .flat_map(|task| std::iter::once(&task.title).chain(&task.tags)),
+        )
+    }
+
+    // The tasks with every placeholder filled in from `values` or the defaults.
+    // Values for placeholders the template does not use are refused, as they
+    // are most likely misspelled.
+    fn instantiate(
+        &self,
+        values: &HashMap<String, String>,
+    ) -> Result<Vec<(String, Priority, Vec<String>)>, TodoError> {
+        let placeholders = self.placeholders();
+        let mut unknown: Vec<&str> = values
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !placeholders.contains_key(*key))
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort_unstable();
+            let known: Vec<&str> = placeholders.keys().map(String::as_str).collect();
+            return Err(TodoError::InvalidInput(t!(
+                "error.unknown_template_values",
+                name = self.name,
+                keys = unknown.join(", "),
+                known = if known.is_empty() {
+                    "-".to_string()
+                } else {
+                    known.join(", ")
+                }
+            )));
+        }
+        let missing: Vec<String> = placeholders
+            .into_iter()
+            .filter(|(name, default)| default.is_none() && !values.contains_key(name))
+            .map(|(name, _)| name)
+            .collect();
+        if !missing.is_empty() {
+            return Err(TodoError::InvalidInput(t!(
+                "error.missing_template_values",
+                name = self.name,
+                keys = missing.join(", ")
+            )));
+        }
+        let lookup = |name: &str| {
+            values
+                .get(name)
+                .or_else(|| self.defaults.get(name))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let fill_all = |texts: &[String]| -> Result<Vec<String>, TodoError> {
+            texts.iter().map(|text| fill(text, lookup)).collect()
+        };
+
+        let shared_tags = fill_all(&self.tags)?;
+        self.tasks
+            .iter()
+            .map(|task| {
+                let mut tags = shared_tags.clone();
+                for tag in fill_all(&task.tags)? {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                tags.retain(|tag| !tag.is_empty());
+                let title = fill(&task.title, lookup)?;
+                if title.trim().is_empty() {
+                    return Err(TodoError::InvalidInput(t!("error.empty_title")));
+                }
+                Ok((title, task.priority.unwrap_or(self.priority), tags))
+            })
+            .collect()
+    }
+}
+
+// "Write notes for {version} !high +docs"
+fn parse_task(text: &str) -> Result<TemplateTask, TodoError> {
+    let mut priority = None;
+    let mut tags = Vec::new();
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(p) = word.strip_prefix('!') {
+            priority = Some(p.parse()?);
+        } else if let Some(tag) = word.strip_prefix('+').filter(|t| !t.is_empty()) {
+            tags.push(tag.to_string());
+        } else {
+            words.push(word);
+        }
+    }
+    if words.is_empty() {
+        return Err(TodoError::InvalidInput(t!("error.empty_title")));
+    }
+    Ok(TemplateTask {
+        title: words.join(" "),
+        priority,
+        tags,
+    })
+}
+
+// The message of an input error without the "Invalid input" prefix.
+fn message(error: &TodoError) -> String {
+    match error {
+        TodoError::InvalidInput(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// The placeholder names in `text`, or an error for a stray brace.
+fn placeholders(text: &str) -> Result<BTreeSet<String>, TodoError> {
+    let mut names = BTreeSet::new();
+    fill(text, |name: &str| {
+        names.insert(name.to_string());
+        String::new()
+    })?;
+    Ok(names)
+}
+
+// Replaces each {name} with `lookup(name)`; "{{" and "}}" are literal braces.
+fn fill(text: &str, mut lookup: impl FnMut(&str) -> String) -> Result<String, TodoError> {
+    let bad = || TodoError::InvalidInput(t!("error.bad_placeholder", text = text));
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                let name = name.trim();
+                if !closed || name.is_empty() || name.contains('{') {
+                    return Err(bad());
+                }
+                out.push_str(&lookup(name));
+            }
+            '}' => return Err(bad()),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+impl TodoApp {
+    // Adds every task of `template` as one transaction and returns their ids.
+    pub(crate) fn apply_template(
+        &mut self,
+        template: &Template,
+        values: &HashMap<String, String>,
+    ) -> Result<Vec<u32>, TodoError> {
+        let tasks = template.instantiate(values)?;
+        let ids: Vec<u32> = (0..tasks.len() as u32).map(|n| self.next_id + n).collect();
+        let events = tasks
+            .into_iter()
+            .zip(&ids)
+            .map(|((title, priority, tags), &id)| {
+                EventKind::TaskAdded(Task::new(id, &title, priority, tags))
+            })
+            .collect();
+        self.record_all(events)?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE: &str = "\
+# Release checklist
+description: Steps for shipping a release
+priority: low
+tags: release, v{version}
+default channel: #releases
+
+- Freeze the {version} branch !high
+- Announce in {channel} +comms +release
+- Print {{braces}}
+";
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn assert_invalid<T: std::fmt::Debug>(result: Result<T, TodoError>, expected: &str) {
+        match result {
+            Err(TodoError::InvalidInput(message)) => {
+                assert!(message.contains(expected), "{:?}", message)
+            }
+            other => panic!(
+                "expected an error mentioning {:?}, got {:?}",
+                expected, other
+            ),
+        }
+    }
+
+    #[test]
+    fn parses_settings_tasks_and_placeholders() {
+        let template = Template::parse("release", RELEASE).unwrap();
+        assert_eq!(template.description, "Steps for shipping a release");
+        assert_eq!(template.priority, Priority::Low);
+        assert_eq!(template.tags, ["release", "v{version}"]);
+        assert_eq!(template.task_count(), 3);
+        assert_eq!(template.tasks[0].priority, Some(Priority::High));
+        assert_eq!(template.tasks[1].tags, ["comms", "release"]);
+        assert_eq!(
+            template.placeholders().into_iter().collect::<Vec<_>>(),
+            vec![
+                ("channel".to_string(), Some("#releases".to_string())),
+                ("version".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        assert_invalid(Template::parse("t", "description: nothing\n"), "t");
+        assert_invalid(Template::parse("t", "- task\ncolour: red\n"), "t:2");
+        assert_invalid(Template::parse("t", "- task\nno colon here\n"), "t:2");
+        assert_invalid(Template::parse("t", "- task !urgent\n"), "t:1");
+        assert_invalid(Template::parse("t", "- !high +tag\n"), "t:1");
+        assert_invalid(Template::parse("t", "- open {brace\n"), "t");
+        assert_invalid(Template::parse("t", "tags: a}\n- task\n"), "t");
+    }
+
+    #[test]
+    fn fills_placeholders_and_braces() {
+        let lookup = |name: &str| name.to_uppercase();
+        assert_eq!(fill("v{ version }-{x}", lookup).unwrap(), "vVERSION-X");
+        assert_eq!(fill("{{literal}} }}", lookup).unwrap(), "{literal} }");
+        assert_eq!(fill("no placeholders", lookup).unwrap(), "no placeholders");
+        for bad in ["{", "}", "{}", "{ }", "{a{b}", "a}b"] {
+            assert!(fill(bad, lookup).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn instantiates_with_values_and_defaults() {
+        let template = Template::parse("release", RELEASE).unwrap();
+        let tasks = template
+            .instantiate(&values(&[("version", "2.0")]))
+            .unwrap();
+        assert_eq!(
+            tasks,
+            vec![
+                (
+                    "Freeze the 2.0 branch".to_string(),
+                    Priority::High,
+                    vec!["release".to_string(), "v2.0".to_string()]
+                ),
+                (
+                    "Announce in #releases".to_string(),
+                    Priority::Low,
+                    vec![
+                        "release".to_string(),
+                        "v2.0".to_string(),
+                        "comms".to_string()
+                    ]
+                ),
+                (
+                    "Print {braces}".to_string(),
+                    Priority::Low,
+                    vec!["release".to_string(), "v2.0".to_string()]
+                ),
+            ]
+        );
+        let overridden = template
+            .instantiate(&values(&[("version", "2.0"), ("channel", "#ops")]))
+            .unwrap();
+        assert_eq!(overridden[1].0, "Announce in #ops");
+    }
+
+    #[test]
+    fn refuses_missing_and_unknown_values() {
+        let template = Template::parse("release", RELEASE).unwrap();
+        assert_invalid(template.instantiate(&values(&[])), "version");
+        assert_invalid(
+            template.instantiate(&values(&[("version", "2.0"), ("verison", "2.1")])),
+            "verison",
+        );
+        assert_invalid(parse_values(&["version".to_string()]), "version");
+        assert_invalid(parse_values(&["=2.0".to_string()]), "=2.0");
+        assert_eq!(
+            parse_values(&[" version =2.0=final".to_string()]).unwrap(),
+            values(&[("version", "2.0=final")])
+        );
+    }
+
+    #[test]
+    fn applying_adds_every_task_at_once() {
+        let template = Template::parse("release", RELEASE).unwrap();
+        let mut app = TodoApp::new();
+        app.add_task("Existing", Priority::Medium, "");
+        assert_eq!(
+            app.apply_template(&template, &values(&[("version", "3.1")]))
+                .unwrap(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(app.tasks[&2].title, "Freeze the 3.1 branch");
+
+        let log = app.log.len();
+        assert!(app.apply_template(&template, &values(&[])).is_err());
+        assert_eq!(app.log.len(), log);
+        assert_eq!(app.tasks.len(), 4);
+    }
+}
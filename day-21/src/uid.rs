@@ -0,0 +1,194 @@
+// Globally unique task identifiers in the UUID version 4 format.
+// Randomness comes from the standard library's randomly seeded hasher mixed
+// with the clock, process id and a counter, so no crates or services are needed.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::TodoError;
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct TaskUid(u128);
+
+impl TaskUid {
+    pub(crate) fn generate() -> Self {
+        let high = random_u64();
+        let low = random_u64();
+        let mut value = ((high as u128) << 64) | low as u128;
+        // Set the version (4) and variant (RFC 4122) bits.
+        value = (value & !(0xf << 76)) | (0x4 << 76);
+        value = (value & !(0x3 << 62)) | (0x2 << 62);
+        TaskUid(value)
+    }
+
+    // The uid as 32 lowercase hex digits, without hyphens.
+    pub(crate) fn hex(&self) -> String {
+        format!("{:032x}", self.0)
+    }
+
+    // The first eight hex digits, enough to tell tasks apart in listings.
+    pub(crate) fn short(&self) -> String {
+        self.hex()[..8].to_string()
+    }
+}
+
+fn random_u64() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(nanos);
+    hasher.write_u32(process::id());
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+impl fmt::Display for TaskUid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = self.hex();
+        write!(
+            f,
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+}
+
+impl FromStr for TaskUid {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex: String = s.trim().chars().filter(|&c| c != '-').collect();
+        // from_str_radix alone would also take a leading '+'.
+        if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(TodoError::InvalidInput(t!("error.bad_uid", value = s)));
+        }
+        u128::from_str_radix(&hex, 16)
+            .map(TaskUid)
+            .map_err(|_| TodoError::InvalidInput(t!("error.bad_uid", value = s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use crate::events::EventKind;
+    use crate::{Priority, Task, TodoApp};
+
+    fn uid(s: &str) -> TaskUid {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn generated_uids_are_version_4_and_distinct() {
+        let uids: HashSet<TaskUid> = (0..1_000).map(|_| TaskUid::generate()).collect();
+        assert_eq!(uids.len(), 1_000);
+        for uid in &uids {
+            let text = uid.to_string();
+            assert_eq!(text.len(), 36);
+            assert_eq!(&text[14..15], "4");
+            assert!(matches!(&text[19..20], "8" | "9" | "a" | "b"), "{}", text);
+            assert_eq!(text.parse::<TaskUid>().unwrap(), *uid);
+        }
+    }
+
+    #[test]
+    fn parses_with_or_without_hyphens() {
+        let text = "0123abcd-4567-4def-89ab-0123456789ab";
+        let parsed = uid(text);
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.hex(), "0123abcd45674def89ab0123456789ab");
+        assert_eq!(parsed.short(), "0123abcd");
+        assert_eq!(uid(" 0123ABCD45674DEF89AB0123456789AB "), parsed);
+        for bad in [
+            "",
+            "0123abcd-4567-4def-89ab-0123456789a",
+            "0123abcd-4567-4def-89ab-0123456789abc",
+            "0123abcd-4567-4def-89ab-0123456789ag",
+            "+123abcd-4567-4def-89ab-0123456789ab",
+        ] {
+            assert!(
+                matches!(bad.parse::<TaskUid>(), Err(TodoError::InvalidInput(_))),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    fn app() -> TodoApp {
+        let mut app = TodoApp::new();
+        for (id, text) in [
+            (1, "aaaa1111-0000-4000-8000-000000000000"),
+            (2, "aaaa2222-0000-4000-8000-000000000000"),
+            (3, "1234ffff-0000-4000-8000-000000000000"),
+        ] {
+            let mut task = Task::new(id, "task", Priority::Low, Vec::new());
+            task.uid = uid(text);
+            app.record(EventKind::TaskAdded(task)).unwrap();
+        }
+        app
+    }
+
+    #[test]
+    fn prefixes_select_a_range_of_uids() {
+        let app = app();
+        assert_eq!(app.index.ids_with_uid_prefix("aaaa"), vec![1, 2]);
+        assert_eq!(app.index.ids_with_uid_prefix("aaaa2"), vec![2]);
+        assert_eq!(app.index.ids_with_uid_prefix("aaab"), Vec::<u32>::new());
+        assert_eq!(
+            app.index
+                .ids_with_uid_prefix("1234ffff000040008000000000000000"),
+            vec![3]
+        );
+        assert!(
+            app.index
+                .ids_with_uid_prefix("1234ffff0000400080000000000000000")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn references_resolve_by_number_or_unique_prefix() {
+        let app = app();
+        assert_eq!(app.resolve("2").unwrap(), 2);
+        assert_eq!(app.resolve("#3").unwrap(), 3);
+        assert_eq!(app.resolve("AAAA-1").unwrap(), 1);
+        assert_eq!(
+            app.resolve("aaaa2222-0000-4000-8000-000000000000").unwrap(),
+            2
+        );
+        // Not a display number, so taken as a uid prefix.
+        assert_eq!(app.resolve("1234").unwrap(), 3);
+        assert!(matches!(
+            app.resolve("aaaa"),
+            Err(TodoError::AmbiguousId(_, 2))
+        ));
+        for unknown in [
+            "aaab",
+            "aaa",
+            "7",
+            "xyzw",
+            "aaaa2222-0000-4000-8000-000000000001",
+        ] {
+            assert!(
+                matches!(app.resolve(unknown), Err(TodoError::NoMatch(_))),
+                "{:?}",
+                unknown
+            );
+        }
+    }
+}
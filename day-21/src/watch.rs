@@ -0,0 +1,313 @@
+// Noticing changes other programs make to the todo file.
+// `watch` keeps a listing on screen and redraws it whenever the file changes.
+// Every other command checks before saving whether the file changed since it
+// was loaded; if so, it loads the file again and replays its own changes on
+// top, instead of overwriting the other program's work.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, ErrorKind, IsTerminal};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::cli::take_option;
+use crate::date;
+use crate::events::{self, Event};
+use crate::storage;
+use crate::{Status, TodoApp, TodoError};
+
+const DEFAULT_INTERVAL: f64 = 1.0;
+
+// What the todo file looked like at some moment. The metadata is cheap to
+// check on every poll; the hash tells real changes from a mere touch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileState {
+    // The current state of `filename`, or None if it does not exist.
+    pub(crate) fn read(filename: &str) -> Result<Option<Self>, TodoError> {
+        let data = match fs::read(filename) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&data);
+        Ok(Some(FileState {
+            modified: fs::metadata(filename)?.modified().ok(),
+            len: data.len() as u64,
+            hash: hasher.finish(),
+        }))
+    }
+
+    // Whether the file may differ from `self`, judged by its metadata alone.
+    fn metadata_changed(this: &Option<Self>, filename: &str) -> bool {
+        match (this, fs::metadata(filename)) {
+            (None, Ok(_)) => true,
+            (Some(_), Err(_)) => true,
+            (None, Err(_)) => false,
+            (Some(state), Ok(meta)) => {
+                meta.len() != state.len || meta.modified().ok() != state.modified
+            }
+        }
+    }
+}
+
+// Loads `filename`, decrypting it with `passphrase` if it is encrypted.
+pub(crate) fn load(filename: &str, passphrase: Option<&str>) -> Result<TodoApp, TodoError> {
+    match passphrase {
+        Some(secret) if storage::is_encrypted_file(filename)? => {
+            TodoApp::load_encrypted(filename, secret)
+        }
+        _ => TodoApp::load_from_file(filename),
+    }
+}
+
+// Called before saving `app`, which was loaded from `filename` in state
+// `loaded` with its log ending at `base_seq`. If the file has changed since,
+// returns the file's new contents with the events recorded after `base_seq`
+// replayed on top, and warns about those that no longer apply or that
+// overwrite a field the other program changed too.
+pub(crate) fn reconcile(
+    app: TodoApp,
+    filename: &str,
+    passphrase: Option<&str>,
+    loaded: &Option<FileState>,
+    base_seq: u64,
+) -> Result<TodoApp, TodoError> {
+    let current = FileState::read(filename)?;
+    if current == *loaded || current.is_none() {
+        return Ok(app);
+    }
+    let mut fresh = load(filename, passphrase)?;
+    fresh.sort = app.sort.clone();
+    fresh.group = app.group;
+    fresh.show_uids = app.show_uids;
+    let ours: Vec<Event> = app.log.into_iter().filter(|e| e.seq > base_seq).collect();
+
+    println!("{}", t!("watch.rebased", file = filename));
+    let theirs: Vec<&Event> = fresh.log.iter().filter(|e| e.seq > base_seq).collect();
+    for (mine, other) in conflicts(&theirs, &ours) {
+        println!(
+            "{}",
+            t!(
+                "watch.conflict",
+                task = mine.kind.uid().short(),
+                field = mine.kind.field().unwrap_or_default(),
+                theirs = other.kind,
+                ours = mine.kind
+            )
+        );
+    }
+    for (event, error) in fresh.replay(ours) {
+        println!(
+            "{}",
+            t!(
+                "watch.skipped",
+                change = event.kind,
+                task = event.kind.uid().short(),
+                error = error
+            )
+        );
+    }
+    Ok(fresh)
+}
+
+// Pairs each of `ours` with the last of `theirs` that set the same field of
+// the same task to something else, i.e. the changes replaying `ours` would
+// silently overwrite.
+fn conflicts<'a>(theirs: &[&'a Event], ours: &'a [Event]) -> Vec<(&'a Event, &'a Event)> {
+    ours.iter()
+        .filter_map(|mine| {
+            let field = mine.kind.field()?;
+            let other = theirs
+                .iter()
+                .rev()
+                .find(|e| e.kind.uid() == mine.kind.uid() && e.kind.field() == Some(field))?;
+            // Both sides making the same change is no conflict.
+            (other.kind.to_string() != mine.kind.to_string()).then_some((mine, *other))
+        })
+        .collect()
+}
+
+// `watch [--status S] [--interval SECONDS]`: shows the listing and redraws it
+// whenever the file changes, until interrupted.
+pub(crate) fn run(
+    mut app: TodoApp,
+    filename: &str,
+    passphrase: Option<&str>,
+    mut args: Vec<String>,
+) -> Result<(), TodoError> {
+    let status = take_option(&mut args, "--status")?
+        .map(|s| s.parse::<Status>())
+        .transpose()?;
+    let interval = match take_option(&mut args, "--interval")? {
+        Some(value) => value
+            .parse::<f64>()
+            .ok()
+            .filter(|&s| s > 0.0 && s.is_finite())
+            .ok_or_else(|| TodoError::InvalidInput(t!("error.bad_interval", value = value)))?,
+        None => DEFAULT_INTERVAL,
+    };
+    let interval = Duration::from_secs_f64(interval);
+
+    let mut state = FileState::read(filename)?;
+    render(&app, filename, status, None);
+    loop {
+        thread::sleep(interval);
+        if !FileState::metadata_changed(&state, filename) {
+            continue;
+        }
+        let current = FileState::read(filename)?;
+        if current == state {
+            continue;
+        }
+        // Content equal but metadata different (e.g. `touch`): nothing to redraw.
+        let same_content =
+            matches!((&current, &state), (Some(a), Some(b)) if a.hash == b.hash && a.len == b.len);
+        state = current;
+        if same_content {
+            continue;
+        }
+        if state.is_none() {
+            render(
+                &TodoApp::new(),
+                filename,
+                status,
+                Some(t!("watch.missing", file = filename)),
+            );
+            continue;
+        }
+        match load(filename, passphrase) {
+            Ok(mut fresh) => {
+                fresh.sort = app.sort.clone();
+                fresh.group = app.group;
+                fresh.show_uids = app.show_uids;
+                app = fresh;
+                render(&app, filename, status, None);
+            }
+            // Most likely caught halfway through a write; the next poll
+            // sees the finished file.
+            Err(e) => render(
+                &app,
+                filename,
+                status,
+                Some(t!("watch.reload_failed", file = filename, error = e)),
+            ),
+        }
+    }
+}
+
+fn render(app: &TodoApp, filename: &str, status: Option<Status>, warning: Option<String>) {
+    if io::stdout().is_terminal() {
+        print!("\x1b[2J\x1b[H");
+    }
+    println!(
+        "{}\n",
+        t!(
+            "watch.header",
+            file = filename,
+            time = date::format_timestamp(events::now())
+        )
+    );
+    match status {
+        Some(status) => app.list_by_status(status),
+        None => app.list_all(),
+    }
+    if let Some(warning) = warning {
+        println!("\n{}", warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Priority;
+    use crate::events::EventKind;
+    use std::{env, process};
+
+    #[test]
+    fn second_writer_replays_on_top_and_sees_conflicts() {
+        let path = env::temp_dir().join(format!("day21-watch-{}.txt", process::id()));
+        let filename = path.display().to_string();
+        let mut app = TodoApp::new();
+        app.add_task("Shared", Priority::Medium, "");
+        app.add_task("Other", Priority::Medium, "");
+        app.save_to_file(&filename).unwrap();
+        let shared = app.tasks[&1].uid;
+        let other = app.tasks[&2].uid;
+
+        // Both writers load the same file.
+        let loaded = FileState::read(&filename).unwrap();
+        let mut first = TodoApp::load_from_file(&filename).unwrap();
+        let mut second = TodoApp::load_from_file(&filename).unwrap();
+        let base_seq = first.last_seq();
+
+        first
+            .record_all(vec![
+                EventKind::TitleChanged(shared, "First's title".into()),
+                EventKind::TaskCompleted(other),
+            ])
+            .unwrap();
+        first.save_to_file(&filename).unwrap();
+
+        second
+            .record_all(vec![
+                EventKind::TitleChanged(shared, "Second's title".into()),
+                EventKind::PriorityChanged(shared, Priority::High),
+                EventKind::TaskCompleted(other),
+            ])
+            .unwrap();
+        let ours: Vec<Event> = second
+            .log
+            .iter()
+            .filter(|e| e.seq > base_seq)
+            .cloned()
+            .collect();
+        let merged = reconcile(second, &filename, None, &loaded, base_seq).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Everything from both writers is kept; the later save wins the title.
+        let task = &merged.tasks[&1];
+        assert_eq!(task.title, "Second's title");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(merged.tasks[&2].status, Status::Completed);
+        assert_eq!(merged.log.len() as u64, base_seq + 5);
+
+        // Only the title clashes: the priority was changed by one writer, and
+        // both completed the other task.
+        let theirs: Vec<&Event> = merged
+            .log
+            .iter()
+            .filter(|e| e.seq > base_seq && e.seq <= base_seq + 2)
+            .collect();
+        let clashes = conflicts(&theirs, &ours);
+        assert_eq!(clashes.len(), 1);
+        let (mine, other) = clashes[0];
+        assert_eq!(mine.kind.field(), Some("title"));
+        assert!(matches!(&other.kind, EventKind::TitleChanged(_, t) if t == "First's title"));
+    }
+
+    #[test]
+    fn unchanged_file_is_left_alone() {
+        let path = env::temp_dir().join(format!("day21-watch-same-{}.txt", process::id()));
+        let filename = path.display().to_string();
+        let mut app = TodoApp::new();
+        app.add_task("Only", Priority::Low, "");
+        app.save_to_file(&filename).unwrap();
+        let loaded = FileState::read(&filename).unwrap();
+        let base_seq = app.last_seq();
+        let uid = app.tasks[&1].uid;
+        app.record(EventKind::TitleChanged(uid, "Renamed".into()))
+            .unwrap();
+
+        let kept = reconcile(app, &filename, None, &loaded, base_seq).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(kept.tasks[&1].title, "Renamed");
+    }
+}